@@ -5,13 +5,14 @@ use infinigen_common::mesh::block::VoxelBlock;
 use infinigen_common::mesh::shapes::{PADDED_CHUNK_VOLUME, PaddedChunk};
 use infinigen_common::mesh::textures::BlockAppearances;
 use infinigen_common::mesh::{mesh_chunk_greedy_quads, mesh_chunk_visible_block_faces};
-use infinigen_common::world::MappedBlockID;
+use infinigen_common::noise::Fbm;
+use infinigen_common::world::{BlockState, ChunkPosition};
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
 
 fn random_padded_chunk<F>(block_constructor: F, fill_prob: f32) -> PaddedChunk
 where
-    F: Fn(MappedBlockID) -> VoxelBlock,
+    F: Fn(BlockState) -> VoxelBlock,
 {
     let mut rng = StdRng::seed_from_u64(42);
     let mut padded = [VoxelBlock::Empty; PADDED_CHUNK_VOLUME];
@@ -19,7 +20,7 @@ where
     for voxel in padded.iter_mut() {
         let roll: f32 = rng.random();
         if roll < fill_prob {
-            *voxel = block_constructor(MappedBlockID::default());
+            *voxel = block_constructor(BlockState::default());
         } else {
             *voxel = VoxelBlock::Empty;
         }
@@ -37,6 +38,8 @@ fn bench_mesh_visible_block_faces(c: &mut Criterion) {
             let _mesh_info = mesh_chunk_visible_block_faces(
                 black_box(&padded_chunk),
                 black_box(&block_textures),
+                ChunkPosition::default(),
+                None,
             );
         });
     });
@@ -49,14 +52,35 @@ fn bench_mesh_greedy_quads(c: &mut Criterion) {
     c.bench_function("mesh_chunk_greedy_quads", |b| {
         b.iter(|| {
             let _mesh_info =
-                mesh_chunk_greedy_quads(black_box(&padded_chunk), black_box(&block_textures));
+                mesh_chunk_greedy_quads(black_box(&padded_chunk), black_box(&block_textures), None);
         });
     });
 }
 
+fn bench_fbm_chunk_columns(c: &mut Criterion) {
+    let fbm = Fbm::new(0);
+    let xs: [f64; 32] = std::array::from_fn(|i| i as f64 * 0.01);
+    let zs: [f64; 32] = std::array::from_fn(|i| (i as f64).mul_add(0.01, 100.));
+
+    c.bench_function("fbm_chunk_columns_scalar", |b| {
+        b.iter(|| {
+            let mut out = [[0.; 32]; 32];
+            for (row, &x) in out.iter_mut().zip(&xs) {
+                for (value, &z) in row.iter_mut().zip(&zs) {
+                    *value = fbm.get(black_box([x, z]));
+                }
+            }
+            out
+        });
+    });
+    c.bench_function("fbm_chunk_columns_grid", |b| {
+        b.iter(|| fbm.get_grid(black_box(&xs), black_box(&zs)));
+    });
+}
+
 criterion_group!(
     name = benches;
     config = Criterion::default();
-    targets = bench_mesh_visible_block_faces, bench_mesh_greedy_quads
+    targets = bench_mesh_visible_block_faces, bench_mesh_greedy_quads, bench_fbm_chunk_columns
 );
 criterion_main!(benches);
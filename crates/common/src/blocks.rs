@@ -1,11 +1,11 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
-use ahash::AHashMap;
+use ahash::{AHashMap, AHashSet};
 use linearize::Linearize;
 use serde::{Deserialize, Serialize};
 use strum::{EnumCount, EnumIter};
 
-use crate::world::MappedBlockID;
+use crate::world::{BlockStateID, MappedBlockID};
 
 #[derive(
     Debug,
@@ -29,9 +29,17 @@ pub enum BlockVisibility {
 
 pub type BlockID = String;
 pub type BlockColor = [u8; 4];
+/// A namespaced tag for grouping blocks, e.g. `infinigen:soil` (conventionally written `#infinigen:soil`).
+pub type BlockTag = String;
 
 type TextureFilename = String;
 
+/// A block as defined in a `.block.ron` file.
+///
+/// Every texture, or frame or tile of one, must be the same size, as faces find their textures and
+/// material maps by their cell in a grid of them: the texture atlas, from left to right then top
+/// to bottom, or the layers of the texture array. Textures of translucent blocks are drawn with
+/// their alpha, and repeat across the larger quads that faces are merged into.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Ord, PartialOrd)]
 pub struct BlockType {
     pub id: BlockID,
@@ -40,6 +48,250 @@ pub struct BlockType {
     #[serde(default = "default_block_color")]
     pub color: BlockColor,
     pub textures: Option<BTreeMap<Face, TextureFilename>>,
+    /// Normal, metallic/roughness and emissive maps of faces with a texture. Faces without them
+    /// are flat, fairly rough and non-metallic, and don't glow.
+    #[serde(default)]
+    pub maps: MaterialMaps,
+    /// Textures of the block which are animated, by name. Their frames are consecutive cells of
+    /// the grid of textures, and are stepped through on the GPU without remeshing.
+    #[serde(default)]
+    pub animations: BTreeMap<TextureFilename, Animation>,
+    /// Textures of the block which vary from block to block, by name. Faces pick by the position
+    /// of their block, so they look the same every time their chunk is meshed.
+    #[serde(default)]
+    pub variation: BTreeMap<TextureFilename, TextureVariation>,
+    /// Textures of the block which join up with the same block next to it, by name. Each is a
+    /// grid of tiles, as described in [`connected`](crate::mesh::connected), and faces of
+    /// translucent blocks with connected textures aren't merged.
+    #[serde(default)]
+    pub connected: BTreeSet<TextureFilename>,
+    /// Faces whose colour is multiplied by the tint of the column the block is in, as given by the
+    /// world generator. Tinted faces of translucent blocks aren't merged.
+    #[serde(default)]
+    pub tint: BTreeSet<Face>,
+    /// Light level emitted by the block, from 0 (none) to [`MAX_LIGHT_EMISSION`].
+    #[serde(default)]
+    pub light_emission: u8,
+    #[serde(default)]
+    pub tags: BTreeSet<BlockTag>,
+    /// Free-form metadata, for anything not covered by the other fields.
+    #[serde(default)]
+    pub properties: BTreeMap<String, String>,
+    /// Properties whose combinations make up the block's states, each with its possible values.
+    /// The first value of each is its default.
+    #[serde(default)]
+    pub states: BTreeMap<String, Vec<String>>,
+    /// Appearances of particular states, applied in order over the block's own.
+    #[serde(default)]
+    pub variants: Vec<BlockVariant>,
+}
+
+pub const MAX_LIGHT_EMISSION: u8 = 15;
+/// A block type can have at most this many states, so that a state fits in a [`BlockStateID`].
+pub const MAX_BLOCK_STATES: usize = BlockStateID::MAX as usize + 1;
+
+/// Overrides the appearance of a block's states when their properties match `when`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Ord, PartialOrd)]
+pub struct BlockVariant {
+    pub when: BTreeMap<String, String>,
+    #[serde(default)]
+    pub color: Option<BlockColor>,
+    /// Only the faces given are overridden.
+    #[serde(default)]
+    pub textures: Option<BTreeMap<Face, TextureFilename>>,
+    /// Only the faces given are overridden, for each kind of map.
+    #[serde(default)]
+    pub maps: MaterialMaps,
+    /// Replaces the tinted faces of the block.
+    #[serde(default)]
+    pub tint: Option<BTreeSet<Face>>,
+}
+
+/// Textures which describe how the faces of a block react to light.
+///
+/// Each must be the same size as the colour texture of its face, and faces without a colour
+/// texture ignore them.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Ord, PartialOrd)]
+pub struct MaterialMaps {
+    /// Tangent space normals, with green pointing up the texture (as in OpenGL).
+    #[serde(default)]
+    pub normal: BTreeMap<Face, TextureFilename>,
+    /// Roughness in the green channel and metalness in the blue channel, as in glTF.
+    #[serde(default)]
+    pub metallic_roughness: BTreeMap<Face, TextureFilename>,
+    /// Light given off by the face regardless of lighting.
+    #[serde(default)]
+    pub emissive: BTreeMap<Face, TextureFilename>,
+}
+
+/// A texture made up of frames stacked from top to bottom, which faces show one after another.
+///
+/// Material maps of the animated faces are split into frames in the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Ord, PartialOrd)]
+pub struct Animation {
+    pub frames: u32,
+    /// How long each frame is shown for, in milliseconds.
+    pub frame_time_ms: u32,
+}
+
+/// How a texture varies from block to block, to break up the grid it would otherwise make across
+/// large areas.
+///
+/// Each face picks from the choices by the position of its block, so it looks the same whenever
+/// it's meshed. Faces of translucent blocks are merged into larger quads, so they don't vary.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Ord, PartialOrd)]
+pub struct TextureVariation {
+    /// Textures used in place of this one, each with its relative weight, which keep the material
+    /// maps of the face. The texture itself is only used if it's one of them, or if there are none.
+    #[serde(default)]
+    pub textures: Vec<(TextureFilename, u32)>,
+    /// Whether the texture may be turned by a quarter, half or three quarters.
+    #[serde(default)]
+    pub rotate: bool,
+    /// Whether the texture may be mirrored.
+    #[serde(default)]
+    pub flip: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Ord, PartialOrd, EnumIter, EnumCount)]
+pub enum MaterialMap {
+    Normal,
+    MetallicRoughness,
+    Emissive,
+}
+
+impl MaterialMaps {
+    pub const fn get(&self, map: MaterialMap) -> &BTreeMap<Face, TextureFilename> {
+        match map {
+            MaterialMap::Normal => &self.normal,
+            MaterialMap::MetallicRoughness => &self.metallic_roughness,
+            MaterialMap::Emissive => &self.emissive,
+        }
+    }
+
+    /// Every texture named, for any face and kind of map.
+    pub fn textures(&self) -> impl Iterator<Item = &str> {
+        [&self.normal, &self.metallic_roughness, &self.emissive]
+            .into_iter()
+            .flat_map(|textures| textures.values().map(String::as_str))
+    }
+
+    fn extend(&mut self, other: &Self) {
+        self.normal.extend(other.normal.clone());
+        self.metallic_roughness
+            .extend(other.metallic_roughness.clone());
+        self.emissive.extend(other.emissive.clone());
+    }
+}
+
+impl BlockType {
+    /// Whether the block has `tag`, which may be written with or without a leading `#`.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag.trim_start_matches('#'))
+    }
+
+    pub fn property(&self, key: &str) -> Option<&str> {
+        self.properties.get(key).map(String::as_str)
+    }
+
+    /// Number of combinations of the block's state properties, which may exceed
+    /// [`MAX_BLOCK_STATES`].
+    pub fn state_count(&self) -> usize {
+        self.states
+            .values()
+            .map(|values| values.len().max(1))
+            .product()
+    }
+
+    /// The state with the given property values, where properties not given take their default.
+    /// Returns `None` for unknown properties or values.
+    pub fn state_id<'a>(
+        &self,
+        properties: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Option<BlockStateID> {
+        let mut indices: BTreeMap<&str, usize> = BTreeMap::new();
+        for (property, value) in properties {
+            let values = self.states.get(property)?;
+            let index = values.iter().position(|v| v == value)?;
+            indices.insert(property, index);
+        }
+        // states are numbered in mixed radix, with the first property varying fastest
+        let mut id = 0;
+        let mut stride = 1;
+        for (property, values) in &self.states {
+            id += indices.get(property.as_str()).unwrap_or(&0) * stride;
+            stride *= values.len().max(1);
+        }
+        BlockStateID::try_from(id).ok()
+    }
+
+    /// The property values making up `state`, or `None` if the block has no such state.
+    pub fn state_properties(&self, state: BlockStateID) -> Option<BTreeMap<&str, &str>> {
+        if state as usize >= self.state_count() {
+            return None;
+        }
+        let mut rest = state as usize;
+        let mut properties = BTreeMap::new();
+        for (property, values) in &self.states {
+            let len = values.len().max(1);
+            if let Some(value) = values.get(rest % len) {
+                properties.insert(property.as_str(), value.as_str());
+            }
+            rest /= len;
+        }
+        Some(properties)
+    }
+
+    /// Colour and textures of `state`, after applying any variants which match it.
+    pub fn appearance(
+        &self,
+        state: BlockStateID,
+    ) -> (BlockColor, Option<BTreeMap<Face, TextureFilename>>) {
+        let mut color = self.color;
+        let mut textures = self.textures.clone();
+        for variant in self.variants_of(state) {
+            if let Some(variant_color) = variant.color {
+                color = variant_color;
+            }
+            if let Some(variant_textures) = &variant.textures {
+                textures
+                    .get_or_insert_default()
+                    .extend(variant_textures.clone());
+            }
+        }
+        (color, textures)
+    }
+
+    /// Material maps of `state`, after applying any variants which match it.
+    pub fn maps(&self, state: BlockStateID) -> MaterialMaps {
+        let mut maps = self.maps.clone();
+        for variant in self.variants_of(state) {
+            maps.extend(&variant.maps);
+        }
+        maps
+    }
+
+    /// Tinted faces of `state`, after applying any variants which match it.
+    pub fn tint(&self, state: BlockStateID) -> BTreeSet<Face> {
+        let mut tint = self.tint.clone();
+        for variant in self.variants_of(state) {
+            if let Some(variant_tint) = &variant.tint {
+                tint.clone_from(variant_tint);
+            }
+        }
+        tint
+    }
+
+    /// The variants which match `state`, in the order they apply.
+    fn variants_of(&self, state: BlockStateID) -> impl Iterator<Item = &BlockVariant> {
+        let properties = self.state_properties(state).unwrap_or_default();
+        self.variants.iter().filter(move |variant| {
+            variant
+                .when
+                .iter()
+                .all(|(property, value)| properties.get(property.as_str()) == Some(&value.as_str()))
+        })
+    }
 }
 
 impl Default for BlockType {
@@ -49,6 +301,16 @@ impl Default for BlockType {
             id: "default".to_string(),
             visibility: BlockVisibility::Opaque,
             textures: None,
+            maps: MaterialMaps::default(),
+            animations: BTreeMap::new(),
+            variation: BTreeMap::new(),
+            connected: BTreeSet::new(),
+            tint: BTreeSet::new(),
+            light_emission: 0,
+            tags: BTreeSet::new(),
+            properties: BTreeMap::new(),
+            states: BTreeMap::new(),
+            variants: Vec::new(),
         }
     }
 }
@@ -60,11 +322,73 @@ const fn default_block_color() -> BlockColor {
 #[derive(Debug, Default)]
 pub struct Palette {
     pub inner: AHashMap<BlockID, MappedBlockID>,
+    tagged: AHashMap<BlockTag, AHashSet<MappedBlockID>>,
+    /// Blocks with tinted faces in any of their states.
+    tinted: AHashSet<MappedBlockID>,
+    /// Colours of opaque blocks, as given in their definitions.
+    opaque_colors: AHashMap<MappedBlockID, BlockColor>,
+}
+
+impl Palette {
+    /// Maps `block_type` to `mapped_id`, including its tags, whether it's tinted and, if it's
+    /// opaque, its colour.
+    pub fn insert(&mut self, block_type: &BlockType, mapped_id: MappedBlockID) {
+        self.inner.insert(block_type.id.clone(), mapped_id);
+        for tag in &block_type.tags {
+            self.tagged
+                .entry(tag.clone())
+                .or_default()
+                .insert(mapped_id);
+        }
+        let tinted_variant = block_type
+            .variants
+            .iter()
+            .any(|variant| variant.tint.as_ref().is_some_and(|tint| !tint.is_empty()));
+        if !block_type.tint.is_empty() || tinted_variant {
+            self.tinted.insert(mapped_id);
+        }
+        if block_type.visibility == BlockVisibility::Opaque {
+            self.opaque_colors.insert(mapped_id, block_type.color);
+        }
+    }
+
+    /// Every block with `tag`, which may be written with or without a leading `#`.
+    pub fn tagged(&self, tag: &str) -> impl Iterator<Item = MappedBlockID> + '_ {
+        self.tagged
+            .get(tag.trim_start_matches('#'))
+            .into_iter()
+            .flatten()
+            .copied()
+    }
+
+    pub fn has_tag(&self, mapped_id: MappedBlockID, tag: &str) -> bool {
+        self.tagged
+            .get(tag.trim_start_matches('#'))
+            .is_some_and(|blocks| blocks.contains(&mapped_id))
+    }
+
+    /// Every block with tinted faces in any of its states.
+    pub fn tinted(&self) -> impl Iterator<Item = MappedBlockID> + '_ {
+        self.tinted.iter().copied()
+    }
+
+    /// Every opaque block along with its colour, in no particular order.
+    pub fn opaque_colors(&self) -> impl Iterator<Item = (MappedBlockID, BlockColor)> + '_ {
+        self.opaque_colors
+            .iter()
+            .map(|(mapped_id, color)| (*mapped_id, *color))
+    }
 }
 
+/// A palette without any tags, tinted blocks or colours.
 impl From<AHashMap<BlockID, MappedBlockID>> for Palette {
     fn from(value: AHashMap<BlockID, MappedBlockID>) -> Self {
-        Self { inner: value }
+        Self {
+            inner: value,
+            tagged: AHashMap::default(),
+            tinted: AHashSet::default(),
+            opaque_colors: AHashMap::default(),
+        }
     }
 }
 
@@ -91,3 +415,157 @@ pub enum Face {
     Front,
     Back,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log() -> BlockType {
+        BlockType {
+            id: "infinigen:log".to_string(),
+            color: [1, 1, 1, 255],
+            textures: Some(BTreeMap::from([
+                (Face::Top, "log_top".to_string()),
+                (Face::Left, "log".to_string()),
+            ])),
+            maps: MaterialMaps {
+                normal: BTreeMap::from([
+                    (Face::Top, "log_top_normal".to_string()),
+                    (Face::Left, "log_normal".to_string()),
+                ]),
+                ..Default::default()
+            },
+            states: BTreeMap::from([
+                (
+                    "axis".to_string(),
+                    vec!["y".to_string(), "x".to_string(), "z".to_string()],
+                ),
+                (
+                    "mossy".to_string(),
+                    vec!["false".to_string(), "true".to_string()],
+                ),
+            ]),
+            variants: vec![
+                BlockVariant {
+                    when: BTreeMap::from([("axis".to_string(), "x".to_string())]),
+                    color: None,
+                    textures: Some(BTreeMap::from([(Face::Left, "log_top".to_string())])),
+                    maps: MaterialMaps {
+                        normal: BTreeMap::from([(Face::Left, "log_top_normal".to_string())]),
+                        ..Default::default()
+                    },
+                    tint: None,
+                },
+                BlockVariant {
+                    when: BTreeMap::from([("mossy".to_string(), "true".to_string())]),
+                    color: Some([0, 255, 0, 255]),
+                    textures: None,
+                    maps: MaterialMaps {
+                        metallic_roughness: BTreeMap::from([(Face::Top, "moss_mr".to_string())]),
+                        ..Default::default()
+                    },
+                    tint: Some(BTreeSet::from([Face::Top])),
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn states_are_combinations_of_properties() {
+        let log = log();
+        assert_eq!(log.state_count(), 6);
+        assert_eq!(BlockType::default().state_count(), 1);
+        assert_eq!(log.state_id([]), Some(0));
+        assert_eq!(log.state_id([("axis", "y"), ("mossy", "false")]), Some(0));
+        assert_eq!(log.state_id([("mossy", "true"), ("axis", "z")]), Some(5));
+        assert_eq!(log.state_id([("axis", "w")]), None);
+        assert_eq!(log.state_id([("colour", "red")]), None);
+        for state in 0..6 {
+            let properties = log.state_properties(state).unwrap();
+            assert_eq!(log.state_id(properties), Some(state));
+        }
+        assert_eq!(log.state_properties(6), None);
+    }
+
+    #[test]
+    fn variants_override_appearance() {
+        let log = log();
+        let (color, textures) = log.appearance(0);
+        assert_eq!(color, [1, 1, 1, 255]);
+        assert_eq!(textures, log.textures);
+
+        let state = log.state_id([("axis", "x"), ("mossy", "true")]).unwrap();
+        let (color, textures) = log.appearance(state);
+        assert_eq!(color, [0, 255, 0, 255]);
+        let textures = textures.unwrap();
+        assert_eq!(textures[&Face::Left], "log_top");
+        assert_eq!(textures[&Face::Top], "log_top");
+    }
+
+    #[test]
+    fn variants_override_maps() {
+        let log = log();
+        assert_eq!(log.maps(0), log.maps);
+
+        let state = log.state_id([("axis", "x"), ("mossy", "true")]).unwrap();
+        let maps = log.maps(state);
+        assert_eq!(maps.normal[&Face::Left], "log_top_normal");
+        assert_eq!(maps.normal[&Face::Top], "log_top_normal");
+        assert_eq!(
+            maps.get(MaterialMap::MetallicRoughness)[&Face::Top],
+            "moss_mr"
+        );
+        assert!(maps.emissive.is_empty());
+        assert_eq!(
+            maps.textures().collect::<Vec<_>>(),
+            ["log_top_normal", "log_top_normal", "moss_mr"]
+        );
+    }
+
+    #[test]
+    fn variants_override_tint() {
+        let log = log();
+        assert!(log.tint(0).is_empty());
+
+        let state = log.state_id([("mossy", "true")]).unwrap();
+        assert_eq!(log.tint(state), BTreeSet::from([Face::Top]));
+
+        let mut palette = Palette::default();
+        palette.insert(&log, MappedBlockID::default());
+        assert_eq!(
+            palette.tinted().collect::<Vec<_>>(),
+            [MappedBlockID::default()]
+        );
+    }
+
+    #[test]
+    fn palette_tag_queries() {
+        let soil = |id: &str| BlockType {
+            id: id.to_string(),
+            tags: BTreeSet::from(["infinigen:soil".to_string()]),
+            ..Default::default()
+        };
+        let ids: Vec<_> = (1..=3u16)
+            .map(|i| MappedBlockID::try_from(i).unwrap())
+            .collect();
+        let mut palette = Palette::default();
+        palette.insert(&soil("infinigen:dirt"), ids[0]);
+        palette.insert(&soil("infinigen:sand"), ids[1]);
+        palette.insert(
+            &BlockType {
+                id: "infinigen:stone".to_string(),
+                ..Default::default()
+            },
+            ids[2],
+        );
+
+        let mut tagged: Vec<_> = palette.tagged("#infinigen:soil").collect();
+        tagged.sort();
+        assert_eq!(tagged, &ids[..2]);
+        assert!(palette.has_tag(ids[0], "infinigen:soil"));
+        assert!(!palette.has_tag(ids[2], "infinigen:soil"));
+        assert_eq!(palette.tagged("infinigen:ore").count(), 0);
+        assert_eq!(palette.inner["infinigen:stone"], ids[2]);
+    }
+}
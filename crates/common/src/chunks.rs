@@ -1,9 +1,11 @@
 use std::fmt;
+use std::hash::{Hash, Hasher};
 
+use ahash::AHashMap;
 use ndarray::Array3;
 
 use super::world::MappedBlockID;
-use crate::world::BlockPosition;
+use crate::world::{BlockPosition, BlockState};
 
 /// The length of one side of a cubic chunk.
 pub const CHUNK_SIZE: u8 = 32;
@@ -14,10 +16,89 @@ pub const CHUNK_SIZE_I32: i32 = CHUNK_SIZE as i32;
 pub const CHUNK_SIZE_F32: f32 = CHUNK_SIZE as f32;
 pub const CHUNK_SIZE_F64: f64 = CHUNK_SIZE as f64;
 
-/// Chunk represented as a 3D array of [`MappedBlockID`].
-#[derive(Clone, PartialEq, Eq, Hash)]
+/// sRGB colours of each column of a chunk, indexed by `[x][z]` block position within it.
+pub type ColumnTints = [[[u8; 3]; CHUNK_USIZE]; CHUNK_USIZE];
+
+/// Chunk represented as a 3D array of [`BlockState`]s.
+///
+/// Blocks are palette compressed: each position stores an index into a list of the distinct blocks
+/// in the chunk, which only needs a byte per block unless there are more than 255 of them.
+#[derive(Clone)]
 pub struct Array3Chunk {
-    blocks: Array3<Option<MappedBlockID>>,
+    /// Index 0 is always empty. Entries which are no longer used are only dropped once the palette
+    /// is about to outgrow its indices.
+    palette: Vec<Option<BlockState>>,
+    /// Index of each block in `palette`.
+    palette_indices: AHashMap<BlockState, usize>,
+    indices: PaletteIndices,
+    /// Only allocated once a column is tinted, as most chunks never are.
+    tints: Option<Box<ColumnTints>>,
+}
+
+#[derive(Clone)]
+enum PaletteIndices {
+    Narrow(Array3<u8>),
+    /// A chunk has fewer blocks than `u16::MAX`, so once unused entries are dropped, its palette
+    /// always fits.
+    Wide(Array3<u16>),
+}
+
+impl PaletteIndices {
+    /// The largest palette index that can be stored.
+    const fn max(&self) -> usize {
+        match self {
+            Self::Narrow(_) => u8::MAX as usize,
+            Self::Wide(_) => u16::MAX as usize,
+        }
+    }
+
+    fn get(&self, index: [usize; 3]) -> usize {
+        match self {
+            Self::Narrow(indices) => indices[index] as usize,
+            Self::Wide(indices) => indices[index] as usize,
+        }
+    }
+
+    fn set(&mut self, index: [usize; 3], palette_index: usize) {
+        if let Self::Narrow(indices) = self {
+            match u8::try_from(palette_index) {
+                Ok(palette_index) => {
+                    indices[index] = palette_index;
+                    return;
+                }
+                Err(_) => *self = Self::Wide(indices.mapv(u16::from)),
+            }
+        }
+        if let Self::Wide(indices) = self {
+            indices[index] = u16::try_from(palette_index)
+                .expect("palette should be compacted before overflowing");
+        }
+    }
+
+    /// Which of the first `len` palette indices are used.
+    fn used(&self, len: usize) -> Vec<bool> {
+        let mut used = vec![false; len];
+        match self {
+            Self::Narrow(indices) => indices.iter().for_each(|&i| used[i as usize] = true),
+            Self::Wide(indices) => indices.iter().for_each(|&i| used[i as usize] = true),
+        }
+        used
+    }
+
+    /// Replaces each palette index `i` with `remapped[i]`, which is never larger.
+    fn remap(&mut self, remapped: &[usize]) {
+        match self {
+            Self::Narrow(indices) => indices.mapv_inplace(|i| remapped[i as usize] as u8),
+            Self::Wide(indices) => indices.mapv_inplace(|i| remapped[i as usize] as u16),
+        }
+    }
+
+    fn all_zero(&self) -> bool {
+        match self {
+            Self::Narrow(indices) => indices.iter().all(|&i| i == 0),
+            Self::Wide(indices) => indices.iter().all(|&i| i == 0),
+        }
+    }
 }
 
 impl fmt::Debug for Array3Chunk {
@@ -29,31 +110,127 @@ impl fmt::Debug for Array3Chunk {
 impl Default for Array3Chunk {
     fn default() -> Self {
         Self {
-            blocks: Array3::<Option<MappedBlockID>>::from_elem(
-                (CHUNK_USIZE, CHUNK_USIZE, CHUNK_USIZE),
-                None,
-            ),
+            palette: vec![None],
+            palette_indices: AHashMap::default(),
+            indices: PaletteIndices::Narrow(Array3::zeros((CHUNK_USIZE, CHUNK_USIZE, CHUNK_USIZE))),
+            tints: None,
+        }
+    }
+}
+
+/// Chunks are equal if they have the same blocks, regardless of how they're stored. Tints are only
+/// for show, so are ignored.
+impl PartialEq for Array3Chunk {
+    fn eq(&self, other: &Self) -> bool {
+        Self::positions().all(|pos| self.get_state(&pos) == other.get_state(&pos))
+    }
+}
+
+impl Eq for Array3Chunk {}
+
+impl Hash for Array3Chunk {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for pos in Self::positions() {
+            self.get_state(&pos).hash(state);
         }
     }
 }
 
 impl Array3Chunk {
     pub fn get(&self, pos: &BlockPosition) -> Option<MappedBlockID> {
-        self.blocks[[pos.x as usize, pos.y as usize, pos.z as usize]]
+        self.get_state(pos).map(|block| block.block)
     }
 
+    pub fn get_state(&self, pos: &BlockPosition) -> Option<BlockState> {
+        self.palette[self.indices.get(Self::index(pos))]
+    }
+
+    /// Inserts `block` in its default state.
     pub fn insert(&mut self, pos: &BlockPosition, block: MappedBlockID) {
-        self.blocks[[pos.x as usize, pos.y as usize, pos.z as usize]] = Some(block);
+        self.insert_state(pos, block.into());
+    }
+
+    pub fn insert_state(&mut self, pos: &BlockPosition, block: BlockState) {
+        let palette_index = self.palette_index(block);
+        self.indices.set(Self::index(pos), palette_index);
     }
 
     pub fn insert_if_free(&mut self, pos: &BlockPosition, block: MappedBlockID) {
-        if self.blocks[[pos.x as usize, pos.y as usize, pos.z as usize]].is_none() {
-            self.blocks[[pos.x as usize, pos.y as usize, pos.z as usize]] = Some(block);
+        if self.indices.get(Self::index(pos)) == 0 {
+            self.insert(pos, block);
         }
     }
 
     pub fn clear(&mut self, pos: &BlockPosition) {
-        self.blocks[[pos.x as usize, pos.y as usize, pos.z as usize]] = None;
+        self.indices.set(Self::index(pos), 0);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.all_zero()
+    }
+
+    /// Every block in the chunk, possibly along with some which have since been removed.
+    pub fn blocks(&self) -> impl Iterator<Item = MappedBlockID> + '_ {
+        self.palette.iter().flatten().map(|block| block.block)
+    }
+
+    /// Sets the colour that tinted faces of blocks in the column at `x`, `z` are multiplied by.
+    /// Columns which are never set are white, i.e. untinted.
+    pub fn set_tint(&mut self, x: u8, z: u8, tint: [u8; 3]) {
+        let tints = self
+            .tints
+            .get_or_insert_with(|| Box::new([[[u8::MAX; 3]; CHUNK_USIZE]; CHUNK_USIZE]));
+        tints[x as usize][z as usize] = tint;
+    }
+
+    /// Tints of every column, or `None` if none have been set.
+    pub fn tints(&self) -> Option<&ColumnTints> {
+        self.tints.as_deref()
+    }
+
+    const fn index(pos: &BlockPosition) -> [usize; 3] {
+        [pos.x as usize, pos.y as usize, pos.z as usize]
+    }
+
+    fn palette_index(&mut self, block: BlockState) -> usize {
+        if let Some(&index) = self.palette_indices.get(&block) {
+            return index;
+        }
+        if self.palette.len() > self.indices.max() {
+            self.compact();
+        }
+        let index = self.palette.len();
+        self.palette.push(Some(block));
+        self.palette_indices.insert(block, index);
+        index
+    }
+
+    /// Drops palette entries which no block uses any more.
+    fn compact(&mut self) {
+        let mut used = self.indices.used(self.palette.len());
+        used[0] = true;
+        let mut remapped = vec![0; self.palette.len()];
+        let mut palette = Vec::new();
+        self.palette_indices.clear();
+        for (i, block) in self.palette.iter().enumerate() {
+            if !used[i] {
+                continue;
+            }
+            remapped[i] = palette.len();
+            if let Some(block) = block {
+                self.palette_indices.insert(*block, palette.len());
+            }
+            palette.push(*block);
+        }
+        self.palette = palette;
+        self.indices.remap(&remapped);
+    }
+
+    fn positions() -> impl Iterator<Item = BlockPosition> {
+        (0..CHUNK_SIZE).flat_map(|x| {
+            (0..CHUNK_SIZE)
+                .flat_map(move |y| (0..CHUNK_SIZE).map(move |z| BlockPosition { x, y, z }))
+        })
     }
 }
 
@@ -86,3 +263,110 @@ pub fn top_chunk(block: MappedBlockID) -> Array3Chunk {
     }
     chunk
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn states_default_to_zero_and_are_reset() {
+        let block = MappedBlockID::default();
+        let pos = BlockPosition { x: 1, y: 2, z: 3 };
+        let other = BlockPosition { x: 3, y: 2, z: 1 };
+        let mut chunk = Array3Chunk::default();
+        assert_eq!(chunk.get_state(&pos), None);
+
+        chunk.insert(&pos, block);
+        assert_eq!(chunk.get_state(&pos), Some(block.into()));
+
+        let snowy = BlockState { block, state: 3 };
+        chunk.insert_state(&pos, snowy);
+        chunk.insert(&other, block);
+        assert_eq!(chunk.get(&pos), Some(block));
+        assert_eq!(chunk.get_state(&pos), Some(snowy));
+        assert_eq!(chunk.get_state(&other), Some(block.into()));
+
+        chunk.clear(&pos);
+        chunk.insert_if_free(&pos, block);
+        assert_eq!(chunk.get_state(&pos), Some(block.into()));
+    }
+
+    #[test]
+    fn palette_widens_past_255_blocks() {
+        let mut chunk = Array3Chunk::default();
+        let mut expected = Vec::new();
+        for (i, pos) in Array3Chunk::positions().take(1000).enumerate() {
+            let block = MappedBlockID::try_from(1 + i as u16).unwrap();
+            chunk.insert(&pos, block);
+            expected.push((pos, block));
+        }
+        assert!(matches!(chunk.indices, PaletteIndices::Wide(_)));
+        for (pos, block) in expected {
+            assert_eq!(chunk.get(&pos), Some(block));
+        }
+        assert!(!chunk.is_empty());
+    }
+
+    #[test]
+    fn unused_palette_entries_are_dropped() {
+        let mut chunk = Array3Chunk::default();
+        // widen the palette, then keep replacing a single block with ones not seen before
+        for (i, pos) in Array3Chunk::positions().take(1000).enumerate() {
+            chunk.insert(&pos, MappedBlockID::try_from(1 + i as u16).unwrap());
+        }
+        let pos = BlockPosition {
+            x: 31,
+            y: 31,
+            z: 31,
+        };
+        let mut last = BlockState::default();
+        for i in 0..70_000u32 {
+            last = BlockState {
+                block: MappedBlockID::try_from(1 + (i % 60_000) as u16).unwrap(),
+                state: (i / 60_000) as u8 + 1,
+            };
+            chunk.insert_state(&pos, last);
+        }
+        assert!(chunk.palette.len() <= u16::MAX as usize + 1);
+        assert_eq!(chunk.get_state(&pos), Some(last));
+        for (i, pos) in Array3Chunk::positions().take(1000).enumerate() {
+            assert_eq!(
+                chunk.get(&pos),
+                Some(MappedBlockID::try_from(1 + i as u16).unwrap())
+            );
+        }
+
+        // chunks which stay narrow are compacted too
+        let mut chunk = Array3Chunk::default();
+        for i in 0..1000u16 {
+            chunk.insert(&pos, MappedBlockID::try_from(1 + i).unwrap());
+        }
+        assert!(matches!(chunk.indices, PaletteIndices::Narrow(_)));
+        assert_eq!(
+            chunk.get(&pos),
+            Some(MappedBlockID::try_from(1000u16).unwrap())
+        );
+    }
+
+    #[test]
+    fn equality_ignores_palette_order() {
+        let [a, b] = [1u16, 2].map(|i| MappedBlockID::try_from(i).unwrap());
+        let [first, second] = [
+            BlockPosition { x: 0, y: 0, z: 0 },
+            BlockPosition { x: 1, y: 0, z: 0 },
+        ];
+        let mut one = Array3Chunk::default();
+        one.insert(&first, a);
+        one.insert(&second, b);
+        let mut other = Array3Chunk::default();
+        other.insert(&second, b);
+        other.insert(&first, a);
+        assert_eq!(one, other);
+
+        other.clear(&second);
+        assert_ne!(one, other);
+        other.clear(&first);
+        assert!(other.is_empty());
+        assert_eq!(other, Array3Chunk::default());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::world::MappedBlockID;
+use crate::world::BlockState;
 
 /// Visibility category used to decide whether a face between two voxels
 /// should be meshed.
@@ -11,8 +11,8 @@ pub enum VoxelVisibility {
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum VoxelBlock {
-    Opaque(MappedBlockID),
-    Translucent(MappedBlockID),
+    Opaque(BlockState),
+    Translucent(BlockState),
     Empty,
 }
 
@@ -25,4 +25,12 @@ impl VoxelBlock {
             Self::Empty => VoxelVisibility::Empty,
         }
     }
+
+    #[inline]
+    pub const fn block(self) -> Option<BlockState> {
+        match self {
+            Self::Opaque(block) | Self::Translucent(block) => Some(block),
+            Self::Empty => None,
+        }
+    }
 }
@@ -0,0 +1,114 @@
+//! Connected textures, whose faces join up with the faces of the same block next to them.
+//!
+//! A connected texture is a grid of [`CONNECTED_TILES`] tiles, [`CONNECTED_COLUMNS`] to a row
+//! from left to right then top to bottom, and each face is drawn with the tile for which of its 8
+//! neighbours in the plane of the face are the same block. Neighbours are given as bits, in the
+//! directions that the texture itself goes in on the face, so that tiles are drawn as they should
+//! look.
+//!
+//! A corner only matters if both of the sides next to it connect, since otherwise it's covered
+//! by a border anyway. That leaves 47 distinct tiles, which are ordered by their neighbours as a
+//! number, e.g. the first has no neighbours, the second only one above and the last all of them.
+
+pub const UP: u8 = 1 << 0;
+pub const RIGHT: u8 = 1 << 1;
+pub const DOWN: u8 = 1 << 2;
+pub const LEFT: u8 = 1 << 3;
+pub const UP_RIGHT: u8 = 1 << 4;
+pub const DOWN_RIGHT: u8 = 1 << 5;
+pub const DOWN_LEFT: u8 = 1 << 6;
+pub const UP_LEFT: u8 = 1 << 7;
+
+/// Number of tiles in a connected texture.
+pub const CONNECTED_TILES: u32 = 47;
+/// Number of tiles in each row of a connected texture, which leaves the last cell of the last row
+/// empty.
+pub const CONNECTED_COLUMNS: u32 = 8;
+
+/// Each neighbour bit, along with its offset right and down the texture.
+pub const NEIGHBOURS: [(u8, [i32; 2]); 8] = [
+    (UP, [0, -1]),
+    (RIGHT, [1, 0]),
+    (DOWN, [0, 1]),
+    (LEFT, [-1, 0]),
+    (UP_RIGHT, [1, -1]),
+    (DOWN_RIGHT, [1, 1]),
+    (DOWN_LEFT, [-1, 1]),
+    (UP_LEFT, [-1, -1]),
+];
+
+/// Tiles by their neighbours, and neighbours by their tile.
+const TILES: ([u8; 256], [u8; CONNECTED_TILES as usize]) = {
+    let mut tiles = [0; 256];
+    let mut neighbours = [0; CONNECTED_TILES as usize];
+    let mut next = 0;
+    let mut mask = 0;
+    while mask < 256 {
+        let reduced = reduce(mask as u8);
+        if reduced == mask as u8 {
+            neighbours[next] = reduced;
+            next += 1;
+        }
+        mask += 1;
+    }
+    assert!(next == CONNECTED_TILES as usize);
+    let mut mask = 0;
+    while mask < 256 {
+        let reduced = reduce(mask as u8);
+        let mut tile = 0;
+        while neighbours[tile] != reduced {
+            tile += 1;
+        }
+        tiles[mask] = tile as u8;
+        mask += 1;
+    }
+    (tiles, neighbours)
+};
+
+/// Leaves out corners that don't matter.
+const fn reduce(neighbours: u8) -> u8 {
+    let mut reduced = neighbours & (UP | RIGHT | DOWN | LEFT);
+    let corners = [
+        (UP_RIGHT, UP | RIGHT),
+        (DOWN_RIGHT, DOWN | RIGHT),
+        (DOWN_LEFT, DOWN | LEFT),
+        (UP_LEFT, UP | LEFT),
+    ];
+    let mut i = 0;
+    while i < corners.len() {
+        let (corner, sides) = corners[i];
+        if neighbours & corner != 0 && neighbours & sides == sides {
+            reduced |= corner;
+        }
+        i += 1;
+    }
+    reduced
+}
+
+/// The tile of a face with the given neighbours.
+pub const fn tile(neighbours: u8) -> u32 {
+    TILES.0[neighbours as usize] as u32
+}
+
+/// The neighbours that a tile is drawn for, leaving out corners that don't matter.
+pub const fn tile_neighbours(tile: u32) -> u8 {
+    TILES.1[tile as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiles_are_ordered_by_their_neighbours() {
+        assert_eq!(tile(0), 0);
+        assert_eq!(tile(UP), 1);
+        assert_eq!(tile(u8::MAX), CONNECTED_TILES - 1);
+        // a corner without both of its sides is covered by a border
+        assert_eq!(tile(UP | UP_RIGHT), tile(UP));
+        assert_ne!(tile(UP | RIGHT | UP_RIGHT), tile(UP | RIGHT));
+        for tile_index in 0..CONNECTED_TILES {
+            assert_eq!(tile(tile_neighbours(tile_index)), tile_index);
+        }
+    }
+}
@@ -6,7 +6,7 @@ use crate::blocks::BlockVisibility;
 use crate::chunks::{Array3Chunk, CHUNK_SIZE, CHUNK_SIZE_U32};
 use crate::mesh::block::VoxelBlock;
 use crate::mesh::shapes::{ChunkFace, PADDED_CHUNK_VOLUME, PaddedChunk, padded_linearize};
-use crate::world::{BlockPosition, Direction, MappedBlockID};
+use crate::world::{BlockPosition, BlockState, Direction, MappedBlockID};
 
 const PADDED_BOUNDARY_MIN: u32 = 0;
 const PADDED_BOUNDARY_MAX: u32 = CHUNK_SIZE_U32 + 1;
@@ -85,16 +85,14 @@ const fn padded_coords(spec: FaceSpec, u: u8, v: u8) -> [u32; 3] {
     coords
 }
 
-fn map_block(block: Option<MappedBlockID>, checker: &impl BlockVisibilityChecker) -> VoxelBlock {
-    block.map_or(VoxelBlock::Empty, |mapped_id| {
-        map_non_empty(mapped_id, checker)
-    })
+fn map_block(block: Option<BlockState>, checker: &impl BlockVisibilityChecker) -> VoxelBlock {
+    block.map_or(VoxelBlock::Empty, |block| map_non_empty(block, checker))
 }
 
-fn map_non_empty(mapped_id: MappedBlockID, checker: &impl BlockVisibilityChecker) -> VoxelBlock {
-    match checker.get_visibility(&mapped_id) {
-        BlockVisibility::Opaque => VoxelBlock::Opaque(mapped_id),
-        BlockVisibility::Translucent => VoxelBlock::Translucent(mapped_id),
+fn map_non_empty(block: BlockState, checker: &impl BlockVisibilityChecker) -> VoxelBlock {
+    match checker.get_visibility(&block.block) {
+        BlockVisibility::Opaque => VoxelBlock::Opaque(block),
+        BlockVisibility::Translucent => VoxelBlock::Translucent(block),
     }
 }
 
@@ -123,7 +121,7 @@ pub fn extract_faces(
             for v in 0..CHUNK_SIZE {
                 let position = face_block_position(spec, u, v);
                 let index = chunk_face_index(u, v);
-                let block = chunk.get(&position);
+                let block = chunk.get_state(&position);
                 faces[dir][index] = map_block(block, checker);
             }
         }
@@ -155,14 +153,14 @@ pub fn prepare_padded_chunk(
     for bx in 0..CHUNK_SIZE {
         for by in 0..CHUNK_SIZE {
             for bz in 0..CHUNK_SIZE {
-                let block = chunk.get(&BlockPosition {
+                let block = chunk.get_state(&BlockPosition {
                     x: bx,
                     y: by,
                     z: bz,
                 });
-                if let Some(mapped_id) = block {
+                if let Some(block) = block {
                     let i = padded_linearize([bx as u32 + 1, by as u32 + 1, bz as u32 + 1]);
-                    padded[i] = map_non_empty(mapped_id, checker);
+                    padded[i] = map_non_empty(block, checker);
                 }
             }
         }
@@ -170,6 +168,57 @@ pub fn prepare_padded_chunk(
     padded
 }
 
+/// Offsets of the chunks which only meet a chunk along an edge or at a corner.
+pub fn diagonal_offsets() -> impl Iterator<Item = [i32; 3]> {
+    (-1..=1)
+        .flat_map(|x| (-1..=1).flat_map(move |y| (-1..=1).map(move |z| [x, y, z])))
+        .filter(|offset| offset.iter().filter(|&&c| c != 0).count() >= 2)
+}
+
+/// Fills the edges and corners of the padding with the chunks that meet it diagonally.
+///
+/// `diagonal_faces` gets the faces of the chunk at one of [`diagonal_offsets`], or `None` if that
+/// chunk is empty.
+pub fn pad_edges_and_corners<'a>(
+    padded: &mut PaddedChunk,
+    diagonal_faces: impl Fn([i32; 3]) -> Option<&'a StaticCopyMap<Direction, ChunkFace>>,
+) {
+    for offset in diagonal_offsets() {
+        let Some(faces) = diagonal_faces(offset) else {
+            continue;
+        };
+        // any face of the diagonal chunk which is towards this one holds the blocks
+        let axis = offset.iter().position(|&c| c != 0).unwrap();
+        let dir = match (axis, offset[axis]) {
+            (0, -1) => Direction::East,
+            (0, _) => Direction::West,
+            (1, -1) => Direction::Up,
+            (1, _) => Direction::Down,
+            (_, -1) => Direction::South,
+            (_, _) => Direction::North,
+        };
+        let spec = face_spec(dir);
+        let [xs, ys, zs] = offset.map(|c| match c {
+            -1 => PADDED_BOUNDARY_MIN..=PADDED_BOUNDARY_MIN,
+            0 => 1..=CHUNK_SIZE_U32,
+            _ => PADDED_BOUNDARY_MAX..=PADDED_BOUNDARY_MAX,
+        });
+        for x in xs {
+            for y in ys.clone() {
+                for z in zs.clone() {
+                    // position of the block within the diagonal chunk
+                    let local = [x, y, z].map(|c| (c + CHUNK_SIZE_U32 - 1) % CHUNK_SIZE_U32);
+                    let face_index = chunk_face_index(
+                        local[spec.iter_axes[0]] as u8,
+                        local[spec.iter_axes[1]] as u8,
+                    );
+                    padded[padded_linearize([x, y, z])] = faces[dir][face_index];
+                }
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use linearize::static_copy_map;
@@ -193,7 +242,7 @@ mod tests {
     const PADDED_CHUNK_MIN_INDEX: u32 = 0;
 
     pub fn full_chunk_face() -> ChunkFace {
-        [VoxelBlock::Opaque(MappedBlockID::default()); CHUNK_FACE_VOLUME]
+        [VoxelBlock::Opaque(BlockState::default()); CHUNK_FACE_VOLUME]
     }
 
     #[test]
@@ -231,6 +280,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn edges_and_corners_are_padded_from_diagonal_chunks() {
+        let full = filled_chunk(MappedBlockID::default());
+        let full_chunk_faces = static_copy_map! {
+            of type Direction:
+            _ => full_chunk_face(),
+        };
+        let mut padded = prepare_padded_chunk(&full, &EMPTY_CHUNK_FACES, AllOpaque);
+        pad_edges_and_corners(&mut padded, |offset| {
+            (offset == [1, 0, -1] || offset == [-1, 1, 1]).then_some(&full_chunk_faces)
+        });
+
+        let filled = (0..PADDED_CHUNK_SIZE)
+            .flat_map(|x| {
+                (0..PADDED_CHUNK_SIZE)
+                    .flat_map(move |y| (0..PADDED_CHUNK_SIZE).map(move |z| [x, y, z]))
+            })
+            .filter(|&[x, y, z]| {
+                let on_boundary = |c| c == PADDED_CHUNK_MIN_INDEX || c == PADDED_CHUNK_MAX_INDEX;
+                [x, y, z].into_iter().filter(|&c| on_boundary(c)).count() >= 2
+            })
+            .filter(|&coord| !matches!(padded[padded_linearize(coord)], VoxelBlock::Empty))
+            .collect::<Vec<_>>();
+        assert_eq!(filled.len(), CHUNK_SIZE as usize + 1);
+        assert!(filled.iter().all(|&[x, y, z]| (x == PADDED_CHUNK_MAX_INDEX
+            && z == PADDED_CHUNK_MIN_INDEX)
+            || [x, y, z]
+                == [
+                    PADDED_CHUNK_MIN_INDEX,
+                    PADDED_CHUNK_MAX_INDEX,
+                    PADDED_CHUNK_MAX_INDEX
+                ]));
+    }
+
     #[test]
     fn test_prepare_padded_chunk_with_full_faces() {
         let full = filled_chunk(MappedBlockID::default());
@@ -6,27 +6,31 @@
 //! - [`mesh_chunk_visible_block_faces`] emits one unit quad per visible face,
 //!   used for opaque geometry.
 //! - [`mesh_chunk_greedy_quads`] merges adjacent face quads into larger
-//!   rectangles, used for translucent geometry (water, etc).
+//!   rectangles, used for translucent geometry (water, etc). Faces with
+//!   connected textures or tints are left as unit quads.
 
 use self::block::{VoxelBlock, VoxelVisibility};
 use self::quad::{FaceDir, Quad};
 use self::shapes::{PADDED_CHUNK_MAX_INDEX, PaddedChunk, padded_linearize};
-use self::textures::BlockAppearances;
+use self::textures::{BlockAppearances, FaceTexture, UvTransform};
+use self::vertex::{NO_OCCLUSION, NO_TINT, VoxelVertex};
 use crate::blocks::Face;
+use crate::chunks::{CHUNK_SIZE_I32, ColumnTints};
+use crate::world::{BlockState, ChunkPosition};
 
 pub mod block;
+pub mod connected;
 pub mod faces;
 pub mod quad;
 pub mod shapes;
 pub mod textures;
+pub mod vertex;
 
 /// Stores details of a mesh, to be passed to a GPU for rendering.
 pub struct MeshInfo {
     pub indices: Vec<u32>,
-    pub positions: Vec<[f32; 3]>,
-    pub normals: Vec<[f32; 3]>,
-    pub colors: Vec<[f32; 4]>,
-    pub uvs: Vec<[f32; 2]>,
+    /// Vertices packed by [`VoxelVertex::pack`].
+    pub vertices: Vec<[u32; 3]>,
 }
 
 /// Whether the face between `voxel` (owner) and `neighbor` should be meshed.
@@ -83,18 +87,27 @@ pub fn visible_block_faces_quads(padded: &PaddedChunk) -> [Vec<Quad>; 6] {
 }
 
 /// Greedy-mesh the padded chunk, merging adjacent visible faces of the same
-/// voxel into larger quads to reduce triangle count.
+/// voxel into larger quads to reduce triangle count. Faces of blocks for
+/// which `is_unmerged` returns true are always unit quads.
 ///
 /// The returned array is indexed by [`FaceDir`] discriminant.
-pub fn greedy_quads(padded: &PaddedChunk) -> [Vec<Quad>; 6] {
+pub fn greedy_quads(
+    padded: &PaddedChunk,
+    is_unmerged: impl Fn(BlockState, FaceDir) -> bool,
+) -> [Vec<Quad>; 6] {
     let mut out: [Vec<Quad>; 6] = Default::default();
     for &face in &FaceDir::ALL {
-        greedy_quads_for_face(padded, face, &mut out[face as usize]);
+        greedy_quads_for_face(padded, face, &is_unmerged, &mut out[face as usize]);
     }
     out
 }
 
-fn greedy_quads_for_face(padded: &PaddedChunk, face: FaceDir, out: &mut Vec<Quad>) {
+fn greedy_quads_for_face(
+    padded: &PaddedChunk,
+    face: FaceDir,
+    is_unmerged: &impl Fn(BlockState, FaceDir) -> bool,
+    out: &mut Vec<Quad>,
+) {
     let normal_axis = face.normal_axis();
     let u_axis = face.u_axis();
     let v_axis = face.v_axis();
@@ -143,16 +156,23 @@ fn greedy_quads_for_face(padded: &PaddedChunk, face: FaceDir, out: &mut Vec<Quad
                     continue;
                 };
 
+                let unmerged = current
+                    .block()
+                    .is_some_and(|block| is_unmerged(block, face));
+
                 // Extend width along u.
                 let mut w = 1u32;
-                while ui + w < u_size && mask[((ui + w) + vi * u_size) as usize] == Some(current) {
+                while !unmerged
+                    && ui + w < u_size
+                    && mask[((ui + w) + vi * u_size) as usize] == Some(current)
+                {
                     w += 1;
                 }
 
                 // Extend height along v: each candidate row must match the
                 // full current width.
                 let mut h = 1u32;
-                'outer: while vi + h < v_size {
+                'outer: while !unmerged && vi + h < v_size {
                     for k in 0..w {
                         if mask[((ui + k) + (vi + h) * u_size) as usize] != Some(current) {
                             break 'outer;
@@ -199,26 +219,48 @@ const fn coord_from_axes(
     coord
 }
 
-/// Build a mesh of all visible opaque block faces in the padded chunk.
+/// Build a mesh of all visible opaque block faces in the padded chunk, at `chunk_position` in
+/// the world, with tinted faces multiplied by the `tints` of the chunk's columns.
 pub fn mesh_chunk_visible_block_faces(
     padded: &PaddedChunk,
     block_textures: &BlockAppearances,
+    chunk_position: ChunkPosition,
+    tints: Option<&ColumnTints>,
 ) -> Option<MeshInfo> {
     let quads = visible_block_faces_quads(padded);
-    build_mesh_info(padded, &quads, block_textures, MeshStyle::Opaque)
+    build_mesh_info(
+        padded,
+        &quads,
+        block_textures,
+        tints,
+        MeshStyle::Opaque(chunk_position),
+    )
 }
 
-/// Build a greedy-meshed translucent mesh from the padded chunk.
+/// Build a greedy-meshed translucent mesh from the padded chunk, with tinted faces multiplied by
+/// the `tints` of the chunk's columns.
 pub fn mesh_chunk_greedy_quads(
     padded: &PaddedChunk,
     block_textures: &BlockAppearances,
+    tints: Option<&ColumnTints>,
 ) -> Option<MeshInfo> {
-    let quads = greedy_quads(padded);
-    build_mesh_info(padded, &quads, block_textures, MeshStyle::Translucent)
+    // tints can differ from column to column
+    let quads = greedy_quads(padded, |block, face| {
+        let face = face.block_face();
+        block_textures.is_connected(&block, face) || block_textures.is_tinted(&block, face)
+    });
+    build_mesh_info(
+        padded,
+        &quads,
+        block_textures,
+        tints,
+        MeshStyle::Translucent,
+    )
 }
 
 enum MeshStyle {
-    Opaque,
+    /// Unit quads of the chunk at the given position, whose textures can vary by position.
+    Opaque(ChunkPosition),
     Translucent,
 }
 
@@ -226,6 +268,7 @@ fn build_mesh_info(
     padded: &PaddedChunk,
     quads: &[Vec<Quad>; 6],
     block_textures: &BlockAppearances,
+    tints: Option<&ColumnTints>,
     style: MeshStyle,
 ) -> Option<MeshInfo> {
     let total_quads: usize = quads.iter().map(Vec::len).sum();
@@ -234,88 +277,487 @@ fn build_mesh_info(
     }
 
     let mut indices = Vec::with_capacity(total_quads * 6);
-    let mut positions = Vec::with_capacity(total_quads * 4);
-    let mut normals = Vec::with_capacity(total_quads * 4);
-    let mut colors = Vec::with_capacity(total_quads * 4);
-    let mut uvs = Vec::with_capacity(total_quads * 4);
+    let mut vertices = Vec::with_capacity(total_quads * 4);
 
     for (face_idx, face_quads) in quads.iter().enumerate() {
         let face = FaceDir::ALL[face_idx];
-        let normal = face.normal_f32();
         let is_side = matches!(
             face,
             FaceDir::XNeg | FaceDir::XPos | FaceDir::ZNeg | FaceDir::ZPos
         );
 
         for quad in face_quads {
-            let base = positions.len() as u32;
-            indices.extend_from_slice(&Quad::indices(base, face));
-            positions.extend_from_slice(&quad.positions(face));
-            normals.extend_from_slice(&[normal; 4]);
-
             let voxel = padded[padded_linearize(quad.voxel)];
 
+            let (chunk_block_id, mut block_face, ao) = match style {
+                MeshStyle::Opaque(_) => {
+                    let VoxelBlock::Opaque(chunk_block_id) = voxel else {
+                        unimplemented!("only opaque blocks are supported")
+                    };
+                    (
+                        chunk_block_id,
+                        face.block_face(),
+                        ambient_occlusion(padded, quad, face),
+                    )
+                }
+                MeshStyle::Translucent => {
+                    let VoxelBlock::Translucent(chunk_block_id) = voxel else {
+                        unimplemented!("only translucent blocks are supported")
+                    };
+                    (chunk_block_id, face.block_face(), [NO_OCCLUSION; 4])
+                }
+            };
+
             // Texture-fallback hack: grass/snow-style blocks should use their
             // bottom texture on side faces when there's an opaque block
             // sitting on top (the side texture would look wrong without
-            // visible grass capping it).
-            let mut block_face = face.block_face();
-            if is_side {
+            // visible grass capping it). Merged translucent quads can have
+            // different blocks on top along their length, so they're left
+            // alone.
+            if is_side && matches!(style, MeshStyle::Opaque(_)) {
                 let above_idx = padded_linearize([quad.voxel[0], quad.voxel[1] + 1, quad.voxel[2]]);
                 if matches!(padded[above_idx].visibility(), VoxelVisibility::Opaque) {
                     block_face = Face::Bottom;
                 }
             }
 
-            match style {
-                MeshStyle::Opaque => {
-                    let VoxelBlock::Opaque(chunk_block_id) = voxel else {
-                        unimplemented!("only opaque blocks are supported")
-                    };
-                    let flip_v = is_side;
-                    let face_uvs = quad.uvs(face, flip_v);
-                    match block_textures.get(&chunk_block_id, block_face, face_uvs) {
-                        Some(textures::FaceAppearanceTransformed::Texture { coords }) => {
-                            uvs.extend_from_slice(&coords);
-                            colors.extend_from_slice(&[[1.0; 4]; 4]);
-                        }
-                        Some(textures::FaceAppearanceTransformed::Color { r, g, b, a }) => {
-                            uvs.extend_from_slice(&[[0.0; 2]; 4]);
-                            colors.extend_from_slice(&[[r, g, b, a]; 4]);
-                        }
-                        None => {
-                            tracing::error!(
-                                ?chunk_block_id,
-                                ?block_face,
-                                "No appearance defined for block face"
-                            );
-                            uvs.extend_from_slice(&[[0.0; 2]; 4]);
-                            colors.extend_from_slice(&[[0.0; 4]; 4]);
-                        }
-                    }
+            // connected faces have to line up with their neighbours, so they're never turned,
+            // and merged translucent quads cover many blocks, so only unit quads vary
+            let texture = if block_textures.is_connected(&chunk_block_id, block_face) {
+                let tile = connected::tile(connections(padded, quad, face, is_side));
+                block_textures
+                    .get_connected(&chunk_block_id, block_face, tile)
+                    .map(|texture| (texture, UvTransform::default()))
+            } else {
+                match style {
+                    MeshStyle::Opaque(chunk_position) => block_textures.get_varied(
+                        &chunk_block_id,
+                        block_face,
+                        face_seed(chunk_position, quad, face),
+                    ),
+                    MeshStyle::Translucent => block_textures
+                        .get(&chunk_block_id, block_face)
+                        .map(|texture| (texture, UvTransform::default())),
                 }
-                MeshStyle::Translucent => {
-                    let VoxelBlock::Translucent(chunk_block_id) = voxel else {
-                        unimplemented!("only translucent blocks are supported")
-                    };
-                    // Translucent blocks render as flat colors, so raw UVs
-                    // are fine — no atlas lookup needed.
-                    let face_uvs = quad.uvs(face, false);
-                    uvs.extend_from_slice(&face_uvs);
-                    let color = block_textures
-                        .to_color(&chunk_block_id, block_face)
-                        .unwrap_or([0.0, 0.0, 0.0, 1.0]);
-                    colors.extend_from_slice(&[color; 4]);
+            };
+            let (texture, transform) = texture.unwrap_or_else(|| {
+                tracing::error!(
+                    ?chunk_block_id,
+                    ?block_face,
+                    "No appearance defined for block face"
+                );
+                (FaceTexture::MISSING, UvTransform::default())
+            });
+            // padded chunks start one block before the chunk itself
+            let tint = match tints {
+                Some(tints) if block_textures.is_tinted(&chunk_block_id, block_face) => {
+                    tints[quad.voxel[0] as usize - 1][quad.voxel[2] as usize - 1]
                 }
+                _ => NO_TINT,
+            };
+            // textures repeat across merged translucent quads, as their UVs
+            // span the whole quad
+            let face_uvs = transform.apply(quad.uvs(face, is_side));
+
+            // split quads along the diagonal between their brighter corners, so
+            // that occlusion is interpolated evenly
+            let flip_diagonal = ao[0] + ao[3] > ao[1] + ao[2];
+            let base = vertices.len() as u32;
+            indices.extend_from_slice(&Quad::indices(base, face, flip_diagonal));
+            for ((position, uv), ao) in quad.positions(face).into_iter().zip(face_uvs).zip(ao) {
+                let vertex = VoxelVertex {
+                    position,
+                    uv,
+                    ao,
+                    face,
+                    texture,
+                    tint,
+                };
+                vertices.push(vertex.pack());
             }
         }
     }
 
-    Some(MeshInfo {
-        indices,
-        positions,
-        normals,
-        colors,
-        uvs,
+    Some(MeshInfo { indices, vertices })
+}
+
+/// Which of the 8 neighbours of a unit quad in the plane of its face are the
+/// same block, as [`connected`] neighbour bits in the directions that its
+/// texture goes, which match [`Quad::uvs`].
+fn connections(padded: &PaddedChunk, quad: &Quad, face: FaceDir, flip_v: bool) -> u8 {
+    let block = padded[padded_linearize(quad.voxel)]
+        .block()
+        .map(|b| b.block);
+    let right = if face.is_positive() { -1 } else { 1 };
+    let down = if flip_v { -1 } else { 1 };
+    let mut neighbours = 0;
+    for (bit, [du, dv]) in connected::NEIGHBOURS {
+        let mut voxel = quad.voxel.map(|c| c as i32);
+        voxel[face.u_axis()] += du * right;
+        voxel[face.v_axis()] += dv * down;
+        let neighbour = padded[padded_linearize(voxel.map(|c| c as u32))];
+        if neighbour.block().map(|b| b.block) == block {
+            neighbours |= bit;
+        }
+    }
+    neighbours
+}
+
+/// Picks the variation of a face of a unit quad, from the position of its block in the world.
+fn face_seed(chunk_position: ChunkPosition, quad: &Quad, face: FaceDir) -> u32 {
+    // padded chunks start one block before the chunk itself
+    let block = |chunk: i32, voxel: u32| chunk * CHUNK_SIZE_I32 + voxel as i32 - 1;
+    let x = block(chunk_position.x, quad.voxel[0]);
+    let y = block(chunk_position.y, quad.voxel[1]);
+    let z = block(chunk_position.z, quad.voxel[2]);
+    // mixes the block position and face, then finishes like MurmurHash3
+    let mut hash = (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ (z as u32).wrapping_mul(0xcb1a_b31f)
+        ^ (face as u32).wrapping_mul(0x1656_67b1);
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2_ae35);
+    hash ^ (hash >> 16)
+}
+
+/// Ambient occlusion of each corner of a unit quad, in the same order as
+/// [`Quad::positions`], from the opaque voxels diagonally in front of it.
+fn ambient_occlusion(padded: &PaddedChunk, quad: &Quad, face: FaceDir) -> [u8; 4] {
+    let normal = face.normal();
+    let mut front = quad.voxel.map(|c| c as i32);
+    for (c, n) in front.iter_mut().zip(normal) {
+        *c += n;
+    }
+    let opaque = |du: i32, dv: i32| {
+        let mut coord = front;
+        coord[face.u_axis()] += du;
+        coord[face.v_axis()] += dv;
+        matches!(
+            padded[padded_linearize(coord.map(|c| c as u32))].visibility(),
+            VoxelVisibility::Opaque
+        ) as u8
+    };
+    [(-1, -1), (1, -1), (-1, 1), (1, 1)].map(|(du, dv)| {
+        let side_u = opaque(du, 0);
+        let side_v = opaque(0, dv);
+        if side_u + side_v == 2 {
+            0
+        } else {
+            NO_OCCLUSION - side_u - side_v - opaque(du, dv)
+        }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use linearize::static_copy_map;
+
+    use super::*;
+    use crate::blocks::BlockVisibility;
+    use crate::chunks::{CHUNK_SIZE, CHUNK_SIZE_U32, filled_chunk};
+    use crate::mesh::block::VoxelBlock;
+    use crate::mesh::faces::{BlockVisibilityChecker, pad_edges_and_corners, prepare_padded_chunk};
+    use crate::mesh::shapes::{CHUNK_FACE_VOLUME, EMPTY_CHUNK_FACES, PADDED_CHUNK_VOLUME};
+    use crate::mesh::textures::{AnimatedTexture, FaceAppearance};
+    use crate::world::{BlockState, Direction, MappedBlockID};
+
+    #[derive(Clone)]
+    struct AllOpaque;
+
+    impl BlockVisibilityChecker for AllOpaque {
+        fn get_visibility(&self, _mapped_id: &MappedBlockID) -> BlockVisibility {
+            BlockVisibility::Opaque
+        }
+    }
+
+    #[derive(Clone)]
+    struct AllTranslucent;
+
+    impl BlockVisibilityChecker for AllTranslucent {
+        fn get_visibility(&self, _mapped_id: &MappedBlockID) -> BlockVisibility {
+            BlockVisibility::Translucent
+        }
+    }
+
+    fn appearances(top: FaceAppearance) -> BlockAppearances {
+        let color = FaceAppearance::Color {
+            r: 1.,
+            g: 0.,
+            b: 0.,
+            a: 1.,
+        };
+        let mut appearances = BlockAppearances::default();
+        appearances.add(
+            BlockState::default(),
+            static_copy_map! {
+                Face::Top => top,
+                Face::Bottom => color,
+                Face::Front => color,
+                Face::Back => color,
+                Face::Left => color,
+                Face::Right => color,
+            },
+        );
+        appearances
+    }
+
+    #[test]
+    fn vertices_carry_their_face_texture() {
+        let padded = prepare_padded_chunk(
+            &filled_chunk(MappedBlockID::default()),
+            &EMPTY_CHUNK_FACES,
+            AllOpaque,
+        );
+        let mesh = mesh_chunk_visible_block_faces(
+            &padded,
+            &appearances(FaceAppearance::Layer { index: 3 }),
+            ChunkPosition::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(mesh.indices.len() / 6, mesh.vertices.len() / 4);
+        for vertex in mesh.vertices.iter().copied().map(VoxelVertex::unpack) {
+            let expected = if vertex.face == FaceDir::YPos {
+                FaceTexture::Layer { index: 3 }
+            } else {
+                // red is the first colour after the one for missing appearances
+                FaceTexture::Color { index: 1 }
+            };
+            assert_eq!(vertex.texture, expected, "{vertex:?}");
+            // nothing surrounds the chunk, so only its edges could be occluded
+            assert_eq!(vertex.ao, NO_OCCLUSION);
+        }
+    }
+
+    #[test]
+    fn textures_repeat_across_translucent_quads() {
+        let padded = prepare_padded_chunk(
+            &filled_chunk(MappedBlockID::default()),
+            &EMPTY_CHUNK_FACES,
+            AllTranslucent,
+        );
+        let animation = AnimatedTexture {
+            first: 0,
+            frames: 2,
+            frame_time_ms: 100,
+        };
+        for (top, expected) in [
+            (
+                FaceAppearance::Layer { index: 3 },
+                FaceTexture::Layer { index: 3 },
+            ),
+            (
+                FaceAppearance::Animated(animation),
+                FaceTexture::Animated { index: 0 },
+            ),
+        ] {
+            let mesh = mesh_chunk_greedy_quads(&padded, &appearances(top), None).unwrap();
+            // each side of the chunk is merged into a single quad
+            assert_eq!(mesh.vertices.len(), 6 * 4);
+            for vertex in mesh.vertices.iter().copied().map(VoxelVertex::unpack) {
+                if vertex.face == FaceDir::YPos {
+                    assert_eq!(vertex.texture, expected);
+                    assert!(vertex.uv.iter().all(|&c| c == 0 || c == CHUNK_SIZE as u32));
+                } else {
+                    assert_eq!(vertex.texture, FaceTexture::Color { index: 1 });
+                }
+            }
+        }
+    }
+
+    /// Appearances of a block whose top face is connected, with each tile in its own layer.
+    fn connected_appearances() -> BlockAppearances {
+        let mut appearances = appearances(FaceAppearance::Layer { index: 0 });
+        let tiles = (0..connected::CONNECTED_TILES)
+            .map(|index| FaceAppearance::Layer { index })
+            .collect();
+        appearances.add_connected(BlockState::default(), Face::Top, tiles);
+        appearances
+    }
+
+    #[test]
+    fn connected_faces_join_up_with_the_same_block() {
+        let padded = prepare_padded_chunk(
+            &filled_chunk(MappedBlockID::default()),
+            &EMPTY_CHUNK_FACES,
+            AllOpaque,
+        );
+        let mesh = mesh_chunk_visible_block_faces(
+            &padded,
+            &connected_appearances(),
+            ChunkPosition::default(),
+            None,
+        )
+        .unwrap();
+        let tops: Vec<_> = mesh
+            .vertices
+            .iter()
+            .copied()
+            .map(VoxelVertex::unpack)
+            .filter(|vertex| vertex.face == FaceDir::YPos)
+            .step_by(4)
+            .map(|vertex| vertex.texture)
+            .collect();
+        let tile = |neighbours| FaceTexture::Layer {
+            index: connected::tile(neighbours),
+        };
+        // the texture goes right along -Z and down along +X on top faces, and nothing surrounds
+        // the chunk
+        assert_eq!(
+            tops[0],
+            tile(connected::DOWN | connected::LEFT | connected::DOWN_LEFT)
+        );
+        assert_eq!(tops[CHUNK_SIZE as usize + 1], tile(u8::MAX));
+        assert_eq!(
+            tops[tops.len() - 1],
+            tile(connected::UP | connected::RIGHT | connected::UP_RIGHT)
+        );
+    }
+
+    #[test]
+    fn connected_faces_join_up_across_chunk_corners() {
+        let full_face = [VoxelBlock::Opaque(BlockState::default()); CHUNK_FACE_VOLUME];
+        let mut neighbour_faces = EMPTY_CHUNK_FACES;
+        neighbour_faces[Direction::North] = full_face;
+        neighbour_faces[Direction::West] = full_face;
+        let diagonal = static_copy_map! {
+            of type Direction:
+            _ => full_face,
+        };
+        let mut padded = prepare_padded_chunk(
+            &filled_chunk(MappedBlockID::default()),
+            &neighbour_faces,
+            AllOpaque,
+        );
+        pad_edges_and_corners(&mut padded, |offset| {
+            (offset == [-1, 0, -1]).then_some(&diagonal)
+        });
+        let mesh = mesh_chunk_visible_block_faces(
+            &padded,
+            &connected_appearances(),
+            ChunkPosition::default(),
+            None,
+        )
+        .unwrap();
+        let top = mesh
+            .vertices
+            .iter()
+            .copied()
+            .map(VoxelVertex::unpack)
+            .find(|vertex| vertex.face == FaceDir::YPos)
+            .unwrap();
+        // the block in the corner is surrounded by the chunks to the north and west, and the one
+        // between them
+        assert_eq!(
+            top.texture,
+            FaceTexture::Layer {
+                index: connected::tile(u8::MAX)
+            }
+        );
+    }
+
+    #[test]
+    fn connected_translucent_faces_are_not_merged() {
+        let padded = prepare_padded_chunk(
+            &filled_chunk(MappedBlockID::default()),
+            &EMPTY_CHUNK_FACES,
+            AllTranslucent,
+        );
+        let mesh = mesh_chunk_greedy_quads(&padded, &connected_appearances(), None).unwrap();
+        let top_quads = CHUNK_SIZE as usize * CHUNK_SIZE as usize;
+        assert_eq!(mesh.vertices.len(), (5 + top_quads) * 4);
+    }
+
+    /// Number of vertices of `mesh` which are tinted, checking that they're the top face of the block whose
+    /// column has a tint.
+    fn tinted_vertices(mesh: &MeshInfo) -> usize {
+        let tinted: Vec<_> = mesh
+            .vertices
+            .iter()
+            .copied()
+            .map(VoxelVertex::unpack)
+            .filter(|vertex| vertex.tint != NO_TINT)
+            .collect();
+        for vertex in &tinted {
+            assert_eq!(vertex.face, FaceDir::YPos);
+            assert_eq!(vertex.tint, [200, 255, 100]);
+            // padded chunks start one block before the chunk itself
+            assert!([4, 5].contains(&vertex.position[0]), "{vertex:?}");
+            assert!([6, 7].contains(&vertex.position[2]), "{vertex:?}");
+        }
+        tinted.len()
+    }
+
+    #[test]
+    fn tinted_faces_take_the_tint_of_their_column() {
+        let mut chunk = filled_chunk(MappedBlockID::default());
+        chunk.set_tint(3, 5, [200, 255, 100]);
+        let mut appearances = appearances(FaceAppearance::Layer { index: 0 });
+        appearances.add_tinted(BlockState::default(), Face::Top);
+
+        let padded = prepare_padded_chunk(&chunk, &EMPTY_CHUNK_FACES, AllOpaque);
+        let mesh = mesh_chunk_visible_block_faces(
+            &padded,
+            &appearances,
+            ChunkPosition::default(),
+            chunk.tints(),
+        )
+        .unwrap();
+        assert_eq!(tinted_vertices(&mesh), 4);
+
+        // tinted translucent faces aren't merged, so that each keeps its own tint
+        let padded = prepare_padded_chunk(&chunk, &EMPTY_CHUNK_FACES, AllTranslucent);
+        let mesh = mesh_chunk_greedy_quads(&padded, &appearances, chunk.tints()).unwrap();
+        assert_eq!(tinted_vertices(&mesh), 4);
+        let top_quads = CHUNK_SIZE as usize * CHUNK_SIZE as usize;
+        assert_eq!(mesh.vertices.len(), (5 + top_quads) * 4);
+    }
+
+    #[test]
+    fn face_seeds_follow_world_positions() {
+        let quad = |x| Quad {
+            voxel: [x, 1, 1],
+            width: 1,
+            height: 1,
+        };
+        let chunk = |x| ChunkPosition { x, y: 0, z: 0 };
+        // the first block of one chunk is just past the last block of the chunk before it
+        assert_eq!(
+            face_seed(chunk(1), &quad(1), FaceDir::YPos),
+            face_seed(chunk(0), &quad(CHUNK_SIZE_U32 + 1), FaceDir::YPos)
+        );
+        assert_ne!(
+            face_seed(chunk(1), &quad(1), FaceDir::YPos),
+            face_seed(chunk(0), &quad(1), FaceDir::YPos)
+        );
+        assert_ne!(
+            face_seed(chunk(1), &quad(1), FaceDir::YPos),
+            face_seed(chunk(1), &quad(1), FaceDir::YNeg)
+        );
+    }
+
+    #[test]
+    fn corners_next_to_opaque_voxels_are_occluded() {
+        let mut padded = [VoxelBlock::Empty; PADDED_CHUNK_VOLUME];
+        let block = VoxelBlock::Opaque(BlockState::default());
+        padded[padded_linearize([1, 1, 1])] = block;
+        // next to the space above the first block, along the top face's v axis
+        padded[padded_linearize([2, 2, 1])] = block;
+        let quad = Quad {
+            voxel: [1, 1, 1],
+            width: 1,
+            height: 1,
+        };
+        assert_eq!(
+            ambient_occlusion(&padded, &quad, FaceDir::YPos),
+            [NO_OCCLUSION, NO_OCCLUSION, 2, 2]
+        );
+        padded[padded_linearize([1, 2, 2])] = block;
+        assert_eq!(
+            ambient_occlusion(&padded, &quad, FaceDir::YPos),
+            [NO_OCCLUSION, 2, 2, 0]
+        );
+    }
+}
@@ -116,20 +116,16 @@ pub struct Quad {
 impl Quad {
     /// Returns the 4 vertex positions of the quad, in the order:
     /// `[min_u/min_v, max_u/min_v, min_u/max_v, max_u/max_v]`.
-    pub fn positions(&self, face: FaceDir) -> [[f32; 3]; 4] {
-        let mut origin = [
-            self.voxel[0] as f32,
-            self.voxel[1] as f32,
-            self.voxel[2] as f32,
-        ];
+    pub const fn positions(&self, face: FaceDir) -> [[u32; 3]; 4] {
+        let mut origin = self.voxel;
         if face.is_positive() {
-            origin[face.normal_axis()] += 1.0;
+            origin[face.normal_axis()] += 1;
         }
 
         let u_axis = face.u_axis();
         let v_axis = face.v_axis();
-        let w = self.width as f32;
-        let h = self.height as f32;
+        let w = self.width;
+        let h = self.height;
 
         let min_u_min_v = origin;
         let mut max_u_min_v = origin;
@@ -145,46 +141,64 @@ impl Quad {
 
     /// Returns the two-triangle index list for the quad with winding chosen
     /// so the outward normal points in [`FaceDir::normal`]'s direction.
-    pub const fn indices(base_index: u32, face: FaceDir) -> [u32; 6] {
+    ///
+    /// The quad is split along its `max_u/min_v`-`min_u/max_v` diagonal,
+    /// unless `flip_diagonal` is set.
+    pub const fn indices(base_index: u32, face: FaceDir, flip_diagonal: bool) -> [u32; 6] {
         // Which faces wind counter-clockwise was chosen to match
         // block-mesh-rs's output so Bevy's default backface culling keeps the
         // same set of visible triangles as before.
         let ccw = matches!(face, FaceDir::XNeg | FaceDir::YPos | FaceDir::ZPos);
-        if ccw {
-            [
+        match (ccw, flip_diagonal) {
+            (true, true) => [
+                base_index,
+                base_index + 1,
+                base_index + 3,
+                base_index,
+                base_index + 3,
+                base_index + 2,
+            ],
+            (false, true) => [
+                base_index,
+                base_index + 3,
+                base_index + 1,
+                base_index,
+                base_index + 2,
+                base_index + 3,
+            ],
+            (true, false) => [
                 base_index,
                 base_index + 1,
                 base_index + 2,
                 base_index + 1,
                 base_index + 3,
                 base_index + 2,
-            ]
-        } else {
-            [
+            ],
+            (false, false) => [
                 base_index,
                 base_index + 2,
                 base_index + 1,
                 base_index + 1,
                 base_index + 2,
                 base_index + 3,
-            ]
+            ],
         }
     }
 
     /// UV coordinates for each vertex (pre-atlas), in the same order as
     /// [`Quad::positions`]. Width/height are baked in so textures repeat
     /// across merged quads.
-    pub const fn uvs(&self, face: FaceDir, flip_v: bool) -> [[f32; 2]; 4] {
+    pub const fn uvs(&self, face: FaceDir, flip_v: bool) -> [[u32; 2]; 4] {
         // Positive faces flip U to keep texture orientation consistent with
         // negative faces sharing the same axis.
         let flip_u = face.is_positive();
-        let w = self.width as f32;
-        let h = self.height as f32;
+        let w = self.width;
+        let h = self.height;
         match (flip_u, flip_v) {
-            (false, false) => [[0.0, 0.0], [w, 0.0], [0.0, h], [w, h]],
-            (true, false) => [[w, 0.0], [0.0, 0.0], [w, h], [0.0, h]],
-            (false, true) => [[0.0, h], [w, h], [0.0, 0.0], [w, 0.0]],
-            (true, true) => [[w, h], [0.0, h], [w, 0.0], [0.0, 0.0]],
+            (false, false) => [[0, 0], [w, 0], [0, h], [w, h]],
+            (true, false) => [[w, 0], [0, 0], [w, h], [0, h]],
+            (false, true) => [[0, h], [w, h], [0, 0], [w, 0]],
+            (true, true) => [[w, h], [0, h], [w, 0], [0, 0]],
         }
     }
 }
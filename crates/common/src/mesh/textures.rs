@@ -1,73 +1,482 @@
-use ahash::AHashMap;
+use ahash::{AHashMap, AHashSet};
 use linearize::StaticCopyMap;
 
 use crate::blocks::Face;
-use crate::world::MappedBlockID;
-
-const TEXTURE_SIZE: usize = 64;
-
-// translate UVs for a texture atlas given raw coordinates
-pub fn to_tex_coords_raw(
-    mut uvs: [[f32; 2]; 4],
-    texture_start: [usize; 2], // the top left corner of the texture in the atlas
-    texture_atlas_size: [usize; 2], // the (width, height) of the texture atlas
-) -> [[f32; 2]; 4] {
-    for uv in &mut uvs {
-        uv[0] *= TEXTURE_SIZE as f32;
-        uv[1] *= TEXTURE_SIZE as f32;
-        uv[0] += texture_start[0] as f32;
-        uv[1] += texture_start[1] as f32;
-        uv[0] /= texture_atlas_size[0] as f32;
-        uv[1] /= texture_atlas_size[1] as f32;
-    }
-    uvs
-}
+use crate::world::BlockState;
+
+/// Colour of faces without an appearance, which is always first in the palette.
+const MISSING_COLOR: [f32; 4] = [0., 0., 0., 1.];
 
-#[derive(Default, Debug, Clone)]
+#[derive(Debug, Clone)]
 pub struct BlockAppearances {
+    /// The (width, height) of the texture atlas, if textures are in one.
     pub size: [usize; 2],
-    appearance: AHashMap<MappedBlockID, StaticCopyMap<Face, FaceAppearance>>,
+    appearance: AHashMap<BlockState, StaticCopyMap<Face, FaceAppearance>>,
+    /// Every distinct colour of a face, which vertices refer to by index.
+    palette: Vec<[f32; 4]>,
+    palette_indices: AHashMap<[u32; 4], u32>,
+    /// Every distinct animated texture, which vertices refer to by index.
+    animations: Vec<AnimatedTexture>,
+    animation_indices: AHashMap<AnimatedTexture, u32>,
+    variations: AHashMap<(BlockState, Face), FaceVariation>,
+    /// Tiles of faces with connected textures, in the order of
+    /// [`connected::tile`](super::connected::tile).
+    connected: AHashMap<(BlockState, Face), Vec<FaceAppearance>>,
+    /// Faces multiplied by the tint of the column they're in.
+    tinted: AHashSet<(BlockState, Face)>,
+}
+
+impl Default for BlockAppearances {
+    fn default() -> Self {
+        Self {
+            size: [0, 0],
+            appearance: AHashMap::default(),
+            palette: vec![MISSING_COLOR],
+            palette_indices: AHashMap::from_iter([(MISSING_COLOR.map(f32::to_bits), 0)]),
+            animations: vec![],
+            animation_indices: AHashMap::default(),
+            variations: AHashMap::default(),
+            connected: AHashMap::default(),
+            tinted: AHashSet::default(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum FaceAppearance {
-    Texture { coords: [usize; 2] },
-    Color { r: f32, g: f32, b: f32, a: f32 },
+    /// A texture in the texture atlas, by its cell, counting from left to right then top to bottom.
+    Texture {
+        index: u32,
+    },
+    /// A layer of the texture array.
+    Layer {
+        index: u32,
+    },
+    Animated(AnimatedTexture),
+    Color {
+        r: f32,
+        g: f32,
+        b: f32,
+        a: f32,
+    },
+}
+
+/// A texture whose frames are consecutive layers of the texture array, or consecutive textures
+/// of the texture atlas from left to right then top to bottom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AnimatedTexture {
+    /// The layer, or position in the atlas, of the first frame.
+    pub first: u32,
+    pub frames: u32,
+    /// How long each frame is shown for, in milliseconds.
+    pub frame_time_ms: u32,
+}
+
+/// Appearances a face picks from by the position of its block, and how its texture may be turned.
+#[derive(Debug, Clone)]
+pub struct FaceVariation {
+    /// Appearances to pick from, each with its relative weight. The face keeps its own appearance
+    /// if there are none.
+    pub choices: Vec<(FaceAppearance, u32)>,
+    pub rotate: bool,
+    pub flip: bool,
 }
 
-pub enum FaceAppearanceTransformed {
-    Texture { coords: [[f32; 2]; 4] },
-    Color { r: f32, g: f32, b: f32, a: f32 },
+/// How a texture is turned and mirrored on a face.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct UvTransform {
+    /// Number of quarter turns, from 0 to 3.
+    pub quarter_turns: u8,
+    /// Whether the texture is mirrored left to right, before being turned.
+    pub flip: bool,
+}
+
+impl UvTransform {
+    /// Transforms the texture coordinates of a unit quad.
+    pub const fn apply(self, mut uvs: [[u32; 2]; 4]) -> [[u32; 2]; 4] {
+        let mut i = 0;
+        while i < uvs.len() {
+            let [mut u, mut v] = uvs[i];
+            if self.flip {
+                u = 1 - u;
+            }
+            let mut turns = 0;
+            while turns < self.quarter_turns % 4 {
+                (u, v) = (1 - v, u);
+                turns += 1;
+            }
+            uvs[i] = [u, v];
+            i += 1;
+        }
+        uvs
+    }
+}
+
+/// What a face is drawn with, as stored in each of its vertices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaceTexture {
+    /// A colour in the palette, by index.
+    Color { index: u32 },
+    /// A texture in the texture atlas, by its cell, counting from left to right then top to bottom.
+    Atlas { index: u32 },
+    /// A layer of the texture array.
+    Layer { index: u32 },
+    /// An animated texture, by index.
+    Animated { index: u32 },
+}
+
+impl FaceTexture {
+    /// For faces without an appearance.
+    pub const MISSING: Self = Self::Color { index: 0 };
 }
 
 impl BlockAppearances {
-    pub fn add(&mut self, id: MappedBlockID, appearance: StaticCopyMap<Face, FaceAppearance>) {
+    pub fn add(&mut self, id: BlockState, appearance: StaticCopyMap<Face, FaceAppearance>) {
         tracing::debug!(?id, ?appearance, "Recording appearance for block");
+        for face in appearance.values() {
+            self.record(*face);
+        }
         self.appearance.insert(id, appearance);
     }
 
-    pub fn get(
-        &self,
-        id: &MappedBlockID,
-        face: Face,
-        uvs: [[f32; 2]; 4],
-    ) -> Option<FaceAppearanceTransformed> {
-        let appearances = self.appearance.get(id)?;
-        match appearances[face] {
-            FaceAppearance::Texture { coords } => Some(FaceAppearanceTransformed::Texture {
-                coords: to_tex_coords_raw(uvs, coords, self.size),
-            }),
+    /// Lets a face of a block already added vary with its position.
+    pub fn add_variation(&mut self, id: BlockState, face: Face, variation: FaceVariation) {
+        tracing::debug!(?id, ?face, ?variation, "Recording variation for block face");
+        for (appearance, _) in &variation.choices {
+            self.record(*appearance);
+        }
+        self.variations.insert((id, face), variation);
+    }
+
+    /// Lets a face of a block already added join up with the same block next to it, drawing it
+    /// with one of `tiles` instead of its own appearance.
+    pub fn add_connected(&mut self, id: BlockState, face: Face, tiles: Vec<FaceAppearance>) {
+        tracing::debug!(
+            ?id,
+            ?face,
+            tiles = tiles.len(),
+            "Recording connected block face"
+        );
+        for tile in &tiles {
+            self.record(*tile);
+        }
+        self.connected.insert((id, face), tiles);
+    }
+
+    /// Tints a face of a block by the column it's in.
+    pub fn add_tinted(&mut self, id: BlockState, face: Face) {
+        tracing::debug!(?id, ?face, "Recording tinted block face");
+        self.tinted.insert((id, face));
+    }
+
+    /// Adds the colour or animation of an appearance, so that vertices can refer to it.
+    fn record(&mut self, appearance: FaceAppearance) {
+        match appearance {
             FaceAppearance::Color { r, g, b, a } => {
-                Some(FaceAppearanceTransformed::Color { r, g, b, a })
+                let color = [r, g, b, a];
+                let next = self.palette.len() as u32;
+                self.palette_indices
+                    .entry(color.map(f32::to_bits))
+                    .or_insert_with(|| {
+                        self.palette.push(color);
+                        next
+                    });
+            }
+            FaceAppearance::Animated(animation) => {
+                let next = self.animations.len() as u32;
+                self.animation_indices.entry(animation).or_insert_with(|| {
+                    self.animations.push(animation);
+                    next
+                });
             }
+            FaceAppearance::Texture { .. } | FaceAppearance::Layer { .. } => {}
         }
     }
 
-    pub fn to_color(&self, id: &MappedBlockID, face: Face) -> Option<[f32; 4]> {
+    pub fn get(&self, id: &BlockState, face: Face) -> Option<FaceTexture> {
         let appearances = self.appearance.get(id)?;
-        match appearances[face] {
-            FaceAppearance::Texture { .. } => None,
-            FaceAppearance::Color { r, g, b, a } => Some([r, g, b, a]),
+        Some(self.texture(appearances[face]))
+    }
+
+    /// The texture of a face picked by `seed` if the face varies, along with how it's turned.
+    pub fn get_varied(
+        &self,
+        id: &BlockState,
+        face: Face,
+        seed: u32,
+    ) -> Option<(FaceTexture, UvTransform)> {
+        let Some(variation) = self.variations.get(&(*id, face)) else {
+            return Some((self.get(id, face)?, UvTransform::default()));
+        };
+        // the lowest 3 bits pick the transform, and the other 29 the choice
+        let transform = UvTransform {
+            quarter_turns: if variation.rotate { seed as u8 & 3 } else { 0 },
+            flip: variation.flip && seed & 4 != 0,
+        };
+        // summed as u64 so that large weights can't overflow
+        let total: u64 = variation
+            .choices
+            .iter()
+            .map(|&(_, weight)| u64::from(weight))
+            .sum();
+        if total == 0 {
+            return Some((self.get(id, face)?, transform));
         }
+        // scaled rather than taken modulo the total, so that totals above 2^29 can reach every
+        // choice; the product can't overflow a u128
+        let mut pick = ((u128::from(seed >> 3) * u128::from(total)) >> 29) as u64;
+        for &(appearance, weight) in &variation.choices {
+            let weight = u64::from(weight);
+            if pick < weight {
+                return Some((self.texture(appearance), transform));
+            }
+            pick -= weight;
+        }
+        unreachable!("should have picked a choice within the total weight")
+    }
+
+    pub fn is_connected(&self, id: &BlockState, face: Face) -> bool {
+        self.connected.contains_key(&(*id, face))
+    }
+
+    pub fn is_tinted(&self, id: &BlockState, face: Face) -> bool {
+        self.tinted.contains(&(*id, face))
+    }
+
+    /// The tile of a connected face, or its own texture if it isn't connected.
+    pub fn get_connected(&self, id: &BlockState, face: Face, tile: u32) -> Option<FaceTexture> {
+        let Some(tiles) = self.connected.get(&(*id, face)) else {
+            return self.get(id, face);
+        };
+        Some(self.texture(*tiles.get(tile as usize)?))
+    }
+
+    fn texture(&self, appearance: FaceAppearance) -> FaceTexture {
+        match appearance {
+            FaceAppearance::Texture { index } => FaceTexture::Atlas { index },
+            FaceAppearance::Layer { index } => FaceTexture::Layer { index },
+            FaceAppearance::Animated(animation) => FaceTexture::Animated {
+                index: self.animation_indices[&animation],
+            },
+            FaceAppearance::Color { r, g, b, a } => FaceTexture::Color {
+                index: self.palette_index([r, g, b, a]),
+            },
+        }
+    }
+
+    /// The palette index of the face's colour, if it isn't textured.
+    pub fn color_index(&self, id: &BlockState, face: Face) -> Option<u32> {
+        match self.get(id, face)? {
+            FaceTexture::Color { index } => Some(index),
+            FaceTexture::Atlas { .. }
+            | FaceTexture::Layer { .. }
+            | FaceTexture::Animated { .. } => None,
+        }
+    }
+
+    /// Every colour referred to by [`FaceTexture::Color`], in index order.
+    pub fn palette(&self) -> &[[f32; 4]] {
+        &self.palette
+    }
+
+    /// Every animated texture referred to by [`FaceTexture::Animated`], in index order.
+    pub fn animations(&self) -> &[AnimatedTexture] {
+        &self.animations
+    }
+
+    fn palette_index(&self, color: [f32; 4]) -> u32 {
+        self.palette_indices
+            .get(&color.map(f32::to_bits))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use linearize::static_copy_map;
+
+    use super::*;
+
+    #[test]
+    fn colours_are_shared_in_the_palette() {
+        let red = FaceAppearance::Color {
+            r: 1.,
+            g: 0.,
+            b: 0.,
+            a: 1.,
+        };
+        let green = FaceAppearance::Color {
+            r: 0.,
+            g: 1.,
+            b: 0.,
+            a: 1.,
+        };
+        let mut appearances = BlockAppearances::default();
+        for (state, side) in [(0, red), (1, green)] {
+            let block = BlockState {
+                state,
+                ..Default::default()
+            };
+            appearances.add(
+                block,
+                static_copy_map! {
+                    Face::Top => FaceAppearance::Layer { index: 2 },
+                    Face::Bottom => red,
+                    Face::Front => side,
+                    Face::Back => side,
+                    Face::Left => side,
+                    Face::Right => side,
+                },
+            );
+        }
+
+        assert_eq!(
+            appearances.palette(),
+            [MISSING_COLOR, [1., 0., 0., 1.], [0., 1., 0., 1.]]
+        );
+        let green_block = BlockState {
+            state: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            appearances.get(&green_block, Face::Top),
+            Some(FaceTexture::Layer { index: 2 })
+        );
+        assert_eq!(appearances.color_index(&green_block, Face::Bottom), Some(1));
+        assert_eq!(appearances.color_index(&green_block, Face::Left), Some(2));
+        assert_eq!(appearances.color_index(&green_block, Face::Top), None);
+    }
+
+    #[test]
+    fn animations_are_shared() {
+        let water = AnimatedTexture {
+            first: 4,
+            frames: 8,
+            frame_time_ms: 100,
+        };
+        let lava = AnimatedTexture {
+            first: 12,
+            frames: 8,
+            frame_time_ms: 250,
+        };
+        let mut appearances = BlockAppearances::default();
+        for (state, animation) in [(0, water), (1, lava), (2, water)] {
+            let animated = FaceAppearance::Animated(animation);
+            appearances.add(
+                BlockState {
+                    state,
+                    ..Default::default()
+                },
+                static_copy_map! {
+                    Face::Top => animated,
+                    Face::Bottom => animated,
+                    Face::Front => animated,
+                    Face::Back => animated,
+                    Face::Left => animated,
+                    Face::Right => animated,
+                },
+            );
+        }
+
+        assert_eq!(appearances.animations(), [water, lava]);
+        let block = |state| BlockState {
+            state,
+            ..Default::default()
+        };
+        assert_eq!(
+            appearances.get(&block(2), Face::Left),
+            Some(FaceTexture::Animated { index: 0 })
+        );
+        assert_eq!(
+            appearances.get(&block(1), Face::Top),
+            Some(FaceTexture::Animated { index: 1 })
+        );
+        assert_eq!(appearances.color_index(&block(1), Face::Top), None);
+    }
+
+    #[test]
+    fn uv_transforms_turn_and_mirror() {
+        let uvs = [[0, 0], [1, 0], [1, 1], [0, 1]];
+        assert_eq!(UvTransform::default().apply(uvs), uvs);
+        let turned = UvTransform {
+            quarter_turns: 1,
+            flip: false,
+        };
+        assert_eq!(turned.apply(uvs), [[1, 0], [1, 1], [0, 1], [0, 0]]);
+        let flipped = UvTransform {
+            quarter_turns: 0,
+            flip: true,
+        };
+        assert_eq!(flipped.apply(uvs), [[1, 0], [0, 0], [0, 1], [1, 1]]);
+        let full_turn = UvTransform {
+            quarter_turns: 4,
+            flip: false,
+        };
+        assert_eq!(full_turn.apply(uvs), uvs);
+    }
+
+    #[test]
+    fn variations_are_picked_by_weight() {
+        let stone = FaceAppearance::Layer { index: 0 };
+        let cracked = FaceAppearance::Layer { index: 1 };
+        let block = BlockState::default();
+        let mut appearances = BlockAppearances::default();
+        appearances.add(block, static_copy_map! { _ => stone });
+        appearances.add_variation(
+            block,
+            Face::Top,
+            FaceVariation {
+                choices: vec![(stone, 3), (cracked, 1)],
+                rotate: true,
+                flip: false,
+            },
+        );
+
+        let picks: Vec<_> = (0..4)
+            .map(|choice| appearances.get_varied(&block, Face::Top, choice << 30 | 6))
+            .collect();
+        let transform = UvTransform {
+            quarter_turns: 2,
+            flip: false,
+        };
+        assert_eq!(
+            picks,
+            [
+                Some((FaceTexture::Layer { index: 0 }, transform)),
+                Some((FaceTexture::Layer { index: 0 }, transform)),
+                Some((FaceTexture::Layer { index: 0 }, transform)),
+                Some((FaceTexture::Layer { index: 1 }, transform)),
+            ]
+        );
+        // weights too large to sum in a u32 still pick by weight, and can pick every choice
+        appearances.add_variation(
+            block,
+            Face::Bottom,
+            FaceVariation {
+                choices: vec![(stone, u32::MAX), (cracked, u32::MAX)],
+                rotate: false,
+                flip: false,
+            },
+        );
+        let picks: Vec<_> = [0, (1 << 31) - 1, 1 << 31, u32::MAX]
+            .into_iter()
+            .map(|seed| appearances.get_varied(&block, Face::Bottom, seed))
+            .map(|pick| pick.map(|(texture, _)| texture))
+            .collect();
+        assert_eq!(
+            picks,
+            [
+                Some(FaceTexture::Layer { index: 0 }),
+                Some(FaceTexture::Layer { index: 0 }),
+                Some(FaceTexture::Layer { index: 1 }),
+                Some(FaceTexture::Layer { index: 1 }),
+            ]
+        );
+        // faces without a variation are never turned
+        assert_eq!(
+            appearances.get_varied(&block, Face::Front, 7),
+            Some((FaceTexture::Layer { index: 0 }, UvTransform::default()))
+        );
     }
 }
@@ -0,0 +1,141 @@
+//! The packed vertex format of chunk meshes, which is unpacked again by the voxel shader.
+//!
+//! Each vertex is three `u32`s, though the last is left out of meshes without tinted faces, so
+//! vertices take 8 or 12 bytes rather than the 48 of a `StandardMaterial` mesh:
+//!
+//! | word | bits  | field                                                          |
+//! |------|-------|----------------------------------------------------------------|
+//! | 0    | 0-17  | position within the padded chunk, 6 bits per axis              |
+//! | 0    | 18-29 | texture coordinates, 6 bits per axis                           |
+//! | 0    | 30-31 | ambient occlusion, from 0 (darkest) to 3 (unoccluded)          |
+//! | 1    | 0-2   | face direction, as a [`FaceDir`] discriminant                  |
+//! | 1    | 3-4   | kind of [`FaceTexture`]: colour, atlas, layer or animated      |
+//! | 1    | 5-31  | palette index, atlas cell, layer or animation index            |
+//! | 2    | 0-23  | sRGB tint, 8 bits each for red, green and blue                 |
+use super::quad::FaceDir;
+use super::textures::FaceTexture;
+
+const COORD_BITS: u32 = 6;
+const COORD_MASK: u32 = (1 << COORD_BITS) - 1;
+const AO_SHIFT: u32 = 30;
+const FACE_MASK: u32 = 0b111;
+const KIND_SHIFT: u32 = 3;
+const PAYLOAD_SHIFT: u32 = 5;
+
+const KIND_COLOR: u32 = 0;
+const KIND_ATLAS: u32 = 1;
+const KIND_LAYER: u32 = 2;
+const KIND_ANIMATED: u32 = 3;
+
+/// Tint of faces which aren't tinted.
+pub const NO_TINT: [u8; 3] = [u8::MAX; 3];
+
+/// The last word of vertices of faces which aren't tinted.
+pub const PACKED_NO_TINT: u32 = pack_tint(NO_TINT);
+
+/// Ambient occlusion of an unoccluded vertex.
+pub const NO_OCCLUSION: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoxelVertex {
+    pub position: [u32; 3],
+    pub uv: [u32; 2],
+    pub ao: u8,
+    pub face: FaceDir,
+    pub texture: FaceTexture,
+    /// sRGB colour which the face's colour is multiplied by.
+    pub tint: [u8; 3],
+}
+
+impl VoxelVertex {
+    pub const fn pack(&self) -> [u32; 3] {
+        let [x, y, z] = self.position;
+        let [u, v] = self.uv;
+        let geometry = (x & COORD_MASK)
+            | (y & COORD_MASK) << COORD_BITS
+            | (z & COORD_MASK) << (2 * COORD_BITS)
+            | (u & COORD_MASK) << (3 * COORD_BITS)
+            | (v & COORD_MASK) << (4 * COORD_BITS)
+            | (self.ao as u32 & 0b11) << AO_SHIFT;
+        let (kind, payload) = match self.texture {
+            FaceTexture::Color { index } => (KIND_COLOR, index),
+            FaceTexture::Atlas { index } => (KIND_ATLAS, index),
+            FaceTexture::Layer { index } => (KIND_LAYER, index),
+            FaceTexture::Animated { index } => (KIND_ANIMATED, index),
+        };
+        let appearance = self.face as u32 | kind << KIND_SHIFT | payload << PAYLOAD_SHIFT;
+        [geometry, appearance, pack_tint(self.tint)]
+    }
+
+    pub fn unpack([geometry, appearance, tint]: [u32; 3]) -> Self {
+        let coord = |axis: u32| (geometry >> (axis * COORD_BITS)) & COORD_MASK;
+        let payload = appearance >> PAYLOAD_SHIFT;
+        let texture = match (appearance >> KIND_SHIFT) & 0b11 {
+            KIND_ATLAS => FaceTexture::Atlas { index: payload },
+            KIND_LAYER => FaceTexture::Layer { index: payload },
+            KIND_ANIMATED => FaceTexture::Animated { index: payload },
+            _ => FaceTexture::Color { index: payload },
+        };
+        Self {
+            position: [coord(0), coord(1), coord(2)],
+            uv: [coord(3), coord(4)],
+            ao: (geometry >> AO_SHIFT) as u8,
+            face: FaceDir::ALL[(appearance & FACE_MASK) as usize],
+            texture,
+            tint: [tint as u8, (tint >> 8) as u8, (tint >> 16) as u8],
+        }
+    }
+}
+
+const fn pack_tint([r, g, b]: [u8; 3]) -> u32 {
+    r as u32 | (g as u32) << 8 | (b as u32) << 16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vertices_survive_packing() {
+        let vertices = [
+            VoxelVertex {
+                position: [33, 0, 17],
+                uv: [32, 1],
+                ao: 0,
+                face: FaceDir::ZPos,
+                // the last cell that fits, which would be well past 8192 pixels across
+                texture: FaceTexture::Atlas {
+                    index: (1 << 27) - 1,
+                },
+                tint: NO_TINT,
+            },
+            VoxelVertex {
+                position: [1, 33, 2],
+                uv: [0, 0],
+                ao: NO_OCCLUSION,
+                face: FaceDir::XNeg,
+                texture: FaceTexture::Layer { index: 1000 },
+                tint: [0, 128, 255],
+            },
+            VoxelVertex {
+                position: [0, 0, 0],
+                uv: [1, 1],
+                ao: 2,
+                face: FaceDir::YPos,
+                texture: FaceTexture::Color { index: 42 },
+                tint: [235, 255, 200],
+            },
+            VoxelVertex {
+                position: [32, 32, 32],
+                uv: [32, 17],
+                ao: NO_OCCLUSION,
+                face: FaceDir::ZNeg,
+                texture: FaceTexture::Animated { index: 7 },
+                tint: [0; 3],
+            },
+        ];
+        for vertex in vertices {
+            assert_eq!(VoxelVertex::unpack(vertex.pack()), vertex);
+        }
+    }
+}
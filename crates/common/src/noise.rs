@@ -1,9 +1,17 @@
-//! Seeded 2D Perlin noise and fractional Brownian motion (FBM).
+//! Seeded 2D/3D Perlin and OpenSimplex2 noise, and fractional Brownian motion (FBM).
 //!
 //! This is a small, self-contained replacement for the subset of the
-//! `noise` crate that worldgen actually uses: Perlin with a `u32`
-//! seed, FBM over Perlin with configurable octaves and persistence, and a
-//! `get([x, y])` sampling API.
+//! `noise` crate that worldgen actually uses: noise sources with a `u64`
+//! seed, FBM over any [`NoiseSource`] with configurable octaves and persistence, and
+//! `get([x, y])`/`get3([x, y, z])` sampling APIs.
+//!
+//! Perlin is cheap but has visible axis-aligned artefacts at low frequencies, which
+//! [`OpenSimplex2`] avoids.
+//!
+//! `get_grid`/`get_grid3` sample a whole grid of points at once (e.g. every column of a chunk)
+//! and give identical results. This is considerably faster than sampling each point separately,
+//! since work that depends on only one coordinate (such as Perlin's lattice cells and fade curves)
+//! is done once per row or column rather than once per point. It's plain scalar code, not SIMD.
 
 use std::f64::consts::PI;
 
@@ -13,15 +21,15 @@ pub struct Perlin {
 }
 
 impl Perlin {
-    pub fn new(seed: u32) -> Self {
+    pub fn new(seed: u64) -> Self {
         let mut perm = [0u8; 256];
         for (i, slot) in perm.iter_mut().enumerate() {
             *slot = i as u8;
         }
 
-        // Expand the 32-bit seed with a constant so seed 0 doesn't produce a
-        // degenerate shuffle on the first step.
-        let mut state = (seed as u64) ^ 0x9E37_79B9_7F4A_7C15;
+        // Mix the seed with a constant so seed 0 doesn't produce a degenerate
+        // shuffle on the first step.
+        let mut state = seed ^ 0x9E37_79B9_7F4A_7C15;
         for i in (1..256).rev() {
             let j = (splitmix64(&mut state) as usize) % (i + 1);
             perm.swap(i, j);
@@ -36,15 +44,20 @@ impl Perlin {
 
     pub fn get(&self, point: [f64; 2]) -> f64 {
         let [x, y] = point;
+        self.sample(Lattice::new(x), Lattice::new(y))
+    }
 
-        let xi = (x.floor() as i64).rem_euclid(256) as usize;
-        let yi = (y.floor() as i64).rem_euclid(256) as usize;
-
-        let xf = x - x.floor();
-        let yf = y - y.floor();
+    /// Samples [`Self::get`] at every `[xs[i], ys[j]]` into `[i][j]`, with bit-for-bit identical
+    /// results.
+    pub fn get_grid<const N: usize>(&self, xs: &[f64; N], ys: &[f64; N]) -> [[f64; N]; N] {
+        let lx = xs.map(Lattice::new);
+        let ly = ys.map(Lattice::new);
+        lx.map(|x| ly.map(|y| self.sample(x, y)))
+    }
 
-        let u = fade(xf);
-        let v = fade(yf);
+    fn sample(&self, x: Lattice, y: Lattice) -> f64 {
+        let (xi, xf, u) = (x.cell, x.offset, x.fade);
+        let (yi, yf, v) = (y.cell, y.offset, y.fade);
 
         let p = &self.permutation;
         let a = p[xi] as usize + yi;
@@ -60,12 +73,161 @@ impl Perlin {
         // Raw 2D Perlin peaks around ±√½; scale so output is roughly in [-1, 1].
         lerp(v, x1, x2) * std::f64::consts::SQRT_2
     }
+
+    pub fn get3(&self, point: [f64; 3]) -> f64 {
+        let [x, y, z] = point;
+        self.sample3(Lattice::new(x), Lattice::new(y), Lattice::new(z))
+    }
+
+    fn sample3(&self, x: Lattice, y: Lattice, z: Lattice) -> f64 {
+        let (xi, xf, u) = (x.cell, x.offset, x.fade);
+        let (yi, yf, v) = (y.cell, y.offset, y.fade);
+        let (zi, zf, w) = (z.cell, z.offset, z.fade);
+
+        let p = &self.permutation;
+        let a = p[xi] as usize + yi;
+        let aa = p[a] as usize + zi;
+        let ab = p[a + 1] as usize + zi;
+        let b = p[xi + 1] as usize + yi;
+        let ba = p[b] as usize + zi;
+        let bb = p[b + 1] as usize + zi;
+
+        let y1 = lerp(
+            v,
+            lerp(u, grad3(p[aa], xf, yf, zf), grad3(p[ba], xf - 1.0, yf, zf)),
+            lerp(
+                u,
+                grad3(p[ab], xf, yf - 1.0, zf),
+                grad3(p[bb], xf - 1.0, yf - 1.0, zf),
+            ),
+        );
+        let y2 = lerp(
+            v,
+            lerp(
+                u,
+                grad3(p[aa + 1], xf, yf, zf - 1.0),
+                grad3(p[ba + 1], xf - 1.0, yf, zf - 1.0),
+            ),
+            lerp(
+                u,
+                grad3(p[ab + 1], xf, yf - 1.0, zf - 1.0),
+                grad3(p[bb + 1], xf - 1.0, yf - 1.0, zf - 1.0),
+            ),
+        );
+
+        // Raw 3D Perlin with edge gradients already stays roughly within [-1, 1].
+        lerp(w, y1, y2)
+    }
+}
+
+/// A seeded coherent noise function, with output roughly in [-1, 1].
+pub trait NoiseSource: Clone {
+    fn from_seed(seed: u64) -> Self;
+
+    fn get(&self, point: [f64; 2]) -> f64;
+
+    fn get3(&self, point: [f64; 3]) -> f64;
+
+    /// Adds `amplitude` times the sample at every `[xs[i], ys[j]] * frequency` to `out[i][j]`,
+    /// with bit-for-bit identical results to doing so point by point with [`Self::get`].
+    fn add_grid<const N: usize>(
+        &self,
+        xs: &[f64; N],
+        ys: &[f64; N],
+        frequency: f64,
+        amplitude: f64,
+        out: &mut [[f64; N]; N],
+    ) {
+        for (row, &x) in out.iter_mut().zip(xs) {
+            for (value, &y) in row.iter_mut().zip(ys) {
+                *value = self
+                    .get([x * frequency, y * frequency])
+                    .mul_add(amplitude, *value);
+            }
+        }
+    }
+
+    /// 3D version of [`Self::add_grid`].
+    fn add_grid3<const N: usize>(
+        &self,
+        xs: &[f64; N],
+        ys: &[f64; N],
+        zs: &[f64; N],
+        frequency: f64,
+        amplitude: f64,
+        out: &mut [[[f64; N]; N]; N],
+    ) {
+        for (plane, &x) in out.iter_mut().zip(xs) {
+            for (row, &y) in plane.iter_mut().zip(ys) {
+                for (value, &z) in row.iter_mut().zip(zs) {
+                    *value = self
+                        .get3([x * frequency, y * frequency, z * frequency])
+                        .mul_add(amplitude, *value);
+                }
+            }
+        }
+    }
+}
+
+impl NoiseSource for Perlin {
+    fn from_seed(seed: u64) -> Self {
+        Self::new(seed)
+    }
+
+    fn get(&self, point: [f64; 2]) -> f64 {
+        self.get(point)
+    }
+
+    fn get3(&self, point: [f64; 3]) -> f64 {
+        self.get3(point)
+    }
+
+    // lattice cells and fade curves only need computing once per row or column
+
+    fn add_grid<const N: usize>(
+        &self,
+        xs: &[f64; N],
+        ys: &[f64; N],
+        frequency: f64,
+        amplitude: f64,
+        out: &mut [[f64; N]; N],
+    ) {
+        let lx = xs.map(|x| Lattice::new(x * frequency));
+        let ly = ys.map(|y| Lattice::new(y * frequency));
+        for (row, &x) in out.iter_mut().zip(&lx) {
+            for (value, &y) in row.iter_mut().zip(&ly) {
+                *value = self.sample(x, y).mul_add(amplitude, *value);
+            }
+        }
+    }
+
+    fn add_grid3<const N: usize>(
+        &self,
+        xs: &[f64; N],
+        ys: &[f64; N],
+        zs: &[f64; N],
+        frequency: f64,
+        amplitude: f64,
+        out: &mut [[[f64; N]; N]; N],
+    ) {
+        let lx = xs.map(|x| Lattice::new(x * frequency));
+        let ly = ys.map(|y| Lattice::new(y * frequency));
+        let lz = zs.map(|z| Lattice::new(z * frequency));
+        for (plane, &x) in out.iter_mut().zip(&lx) {
+            for (row, &y) in plane.iter_mut().zip(&ly) {
+                for (value, &z) in row.iter_mut().zip(&lz) {
+                    *value = self.sample3(x, y, z).mul_add(amplitude, *value);
+                }
+            }
+        }
+    }
 }
 
+/// Sums octaves of a noise source, Perlin unless otherwise specified.
 #[derive(Debug, Clone)]
-pub struct Fbm {
-    seed: u32,
-    sources: Vec<Perlin>,
+pub struct Fbm<S = Perlin> {
+    seed: u64,
+    sources: Vec<S>,
     pub octaves: usize,
     pub frequency: f64,
     pub lacunarity: f64,
@@ -81,20 +243,27 @@ impl Fbm {
     pub const DEFAULT_PERSISTENCE: f64 = 0.5;
     pub const MAX_OCTAVES: usize = 32;
 
-    pub fn new(seed: u32) -> Self {
+    pub fn new(seed: u64) -> Self {
+        Self::from_seed(seed)
+    }
+}
+
+impl<S: NoiseSource> Fbm<S> {
+    /// Like [`Fbm::new`], but over any noise source, e.g. `Fbm::<OpenSimplex2>::from_seed(seed)`.
+    pub fn from_seed(seed: u64) -> Self {
         Self {
             seed,
-            sources: build_sources(seed, Self::DEFAULT_OCTAVES),
-            octaves: Self::DEFAULT_OCTAVES,
-            frequency: Self::DEFAULT_FREQUENCY,
-            lacunarity: Self::DEFAULT_LACUNARITY,
-            persistence: Self::DEFAULT_PERSISTENCE,
+            sources: build_sources(seed, Fbm::DEFAULT_OCTAVES),
+            octaves: Fbm::DEFAULT_OCTAVES,
+            frequency: Fbm::DEFAULT_FREQUENCY,
+            lacunarity: Fbm::DEFAULT_LACUNARITY,
+            persistence: Fbm::DEFAULT_PERSISTENCE,
         }
     }
 
     #[must_use]
     pub fn set_octaves(mut self, octaves: usize) -> Self {
-        let octaves = octaves.clamp(1, Self::MAX_OCTAVES);
+        let octaves = octaves.clamp(1, Fbm::MAX_OCTAVES);
         if octaves != self.octaves {
             self.sources = build_sources(self.seed, octaves);
             self.octaves = octaves;
@@ -120,14 +289,378 @@ impl Fbm {
         }
         result
     }
+
+    pub fn get3(&self, point: [f64; 3]) -> f64 {
+        let mut result = 0.0;
+        let mut frequency = self.frequency;
+        let mut amplitude = 1.0;
+        for source in &self.sources {
+            let p = [
+                point[0] * frequency,
+                point[1] * frequency,
+                point[2] * frequency,
+            ];
+            result = source.get3(p).mul_add(amplitude, result);
+            frequency *= self.lacunarity;
+            amplitude *= self.persistence;
+        }
+        result
+    }
+
+    /// Samples [`Self::get`] at every `[xs[i], ys[j]]` into `[i][j]`, with bit-for-bit identical
+    /// results.
+    ///
+    /// Octaves are summed one at a time over the whole grid, in the same order as the scalar path,
+    /// so sources can share work between points (see [`NoiseSource::add_grid`]).
+    pub fn get_grid<const N: usize>(&self, xs: &[f64; N], ys: &[f64; N]) -> [[f64; N]; N] {
+        let mut result = [[0.0; N]; N];
+        let mut frequency = self.frequency;
+        let mut amplitude = 1.0;
+        for source in &self.sources {
+            source.add_grid(xs, ys, frequency, amplitude, &mut result);
+            frequency *= self.lacunarity;
+            amplitude *= self.persistence;
+        }
+        result
+    }
+
+    /// Samples [`Self::get3`] at every `[xs[i], ys[j], zs[k]]` into `[i][j][k]`, with bit-for-bit
+    /// identical results. See [`Self::get_grid`].
+    pub fn get_grid3<const N: usize>(
+        &self,
+        xs: &[f64; N],
+        ys: &[f64; N],
+        zs: &[f64; N],
+    ) -> Box<[[[f64; N]; N]; N]> {
+        // too big for the stack at chunk size
+        let mut result: Box<[[[f64; N]; N]; N]> = vec![[[0.0; N]; N]; N]
+            .into_boxed_slice()
+            .try_into()
+            .unwrap();
+        let mut frequency = self.frequency;
+        let mut amplitude = 1.0;
+        for source in &self.sources {
+            source.add_grid3(xs, ys, zs, frequency, amplitude, &mut result);
+            frequency *= self.lacunarity;
+            amplitude *= self.persistence;
+        }
+        result
+    }
+}
+
+/// A coordinate split into the lattice cell it's in and its (faded) offset within that cell.
+#[derive(Debug, Clone, Copy)]
+struct Lattice {
+    cell: usize,
+    offset: f64,
+    fade: f64,
 }
 
-fn build_sources(seed: u32, octaves: usize) -> Vec<Perlin> {
+impl Lattice {
+    fn new(t: f64) -> Self {
+        let floor = t.floor();
+        let offset = t - floor;
+        Self {
+            cell: (floor as i64).rem_euclid(256) as usize,
+            offset,
+            fade: fade(offset),
+        }
+    }
+}
+
+/// Each octave gets its own seed from a SplitMix64 sequence, so octaves don't correlate with each
+/// other or with sources built from nearby seeds. Prefixes are shared, so fewer octaves of the same
+/// seed are a smoothed version of more.
+fn build_sources<S: NoiseSource>(seed: u64, octaves: usize) -> Vec<S> {
+    let mut state = seed;
     (0..octaves)
-        .map(|i| Perlin::new(seed.wrapping_add(i as u32)))
+        .map(|_| S::from_seed(splitmix64(&mut state)))
         .collect()
 }
 
+/// OpenSimplex2 noise, after K.jpg's reference implementation
+/// (<https://github.com/KdotJPG/OpenSimplex2>), though it doesn't reproduce its exact output.
+///
+/// 2D noise is sampled on a triangular lattice, and 3D noise on two offset body-centred cubic
+/// lattices viewed along their main diagonal, so neither lines up with the world axes.
+#[derive(Debug, Clone)]
+pub struct OpenSimplex2 {
+    seed: u64,
+}
+
+impl OpenSimplex2 {
+    const PRIME_X: u64 = 0x5205_402B_9270_C86F;
+    const PRIME_Y: u64 = 0x598C_D327_0038_17B5;
+    const PRIME_Z: u64 = 0x5BCC_226E_9FA0_BACB;
+    const HASH_MULTIPLIER: u64 = 0x53A3_F72D_EEC5_46F5;
+    /// Flips the seed between the two 3D lattices.
+    const SEED_FLIP_3D: u64 = 0xAD2A_B84D_1691_29D7;
+
+    const SKEW_2D: f64 = 0.366_025_403_784_438_6;
+    const UNSKEW_2D: f64 = -0.211_324_865_405_187_13;
+    const RSQUARED_2D: f64 = 0.5;
+    const RSQUARED_3D: f64 = 0.6;
+    /// Scale output to roughly [-1, 1].
+    const NORMALIZER_2D: f64 = 0.010_016_341_213_657_12;
+    const NORMALIZER_3D: f64 = 0.079_698_376_689_353_31;
+
+    pub const fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    pub fn get(&self, point: [f64; 2]) -> f64 {
+        let [x, y] = point;
+        let s = Self::SKEW_2D * (x + y);
+        let (xs, ys) = (x + s, y + s);
+
+        let (xsb, ysb) = (xs.floor(), ys.floor());
+        let (xi, yi) = (xs - xsb, ys - ysb);
+        let xsbp = (xsb as i64 as u64).wrapping_mul(Self::PRIME_X);
+        let ysbp = (ysb as i64 as u64).wrapping_mul(Self::PRIME_Y);
+
+        let t = (xi + yi) * Self::UNSKEW_2D;
+        let (dx0, dy0) = (xi + t, yi + t);
+
+        // contributions from the three corners of the triangle containing the point
+        let mut value = 0.;
+        let a0 = dy0.mul_add(-dy0, dx0.mul_add(-dx0, Self::RSQUARED_2D));
+        if a0 > 0. {
+            value = falloff(a0).mul_add(self.grad(xsbp, ysbp, dx0, dy0), value);
+        }
+
+        let edge = 2.0f64.mul_add(Self::UNSKEW_2D, 1.);
+        let a1 =
+            (2. * edge * (1. / Self::UNSKEW_2D + 2.)).mul_add(t, (-2. * edge).mul_add(edge, a0));
+        if a1 > 0. {
+            let (dx1, dy1) = (dx0 - edge, dy0 - edge);
+            let grad = self.grad(
+                xsbp.wrapping_add(Self::PRIME_X),
+                ysbp.wrapping_add(Self::PRIME_Y),
+                dx1,
+                dy1,
+            );
+            value = falloff(a1).mul_add(grad, value);
+        }
+
+        let (dx2, dy2, xsvp, ysvp) = if dy0 > dx0 {
+            (
+                dx0 - Self::UNSKEW_2D,
+                dy0 - (Self::UNSKEW_2D + 1.),
+                xsbp,
+                ysbp.wrapping_add(Self::PRIME_Y),
+            )
+        } else {
+            (
+                dx0 - (Self::UNSKEW_2D + 1.),
+                dy0 - Self::UNSKEW_2D,
+                xsbp.wrapping_add(Self::PRIME_X),
+                ysbp,
+            )
+        };
+        let a2 = dy2.mul_add(-dy2, dx2.mul_add(-dx2, Self::RSQUARED_2D));
+        if a2 > 0. {
+            value = falloff(a2).mul_add(self.grad(xsvp, ysvp, dx2, dy2), value);
+        }
+
+        value / Self::NORMALIZER_2D
+    }
+
+    pub fn get3(&self, point: [f64; 3]) -> f64 {
+        // rotate so the lattices' main diagonal points along y
+        let [x, y, z] = point;
+        let r = (2. / 3.) * (x + y + z);
+        let rotated = [r - x, r - y, r - z];
+
+        let base = rotated.map(f64::round);
+        let mut d: [f64; 3] = std::array::from_fn(|axis| rotated[axis] - base[axis]);
+        // -1 if the offset is positive (or zero), 1 if negative
+        let mut sign = d.map(|d| if d >= 0. { -1. } else { 1. });
+        let mut abs = d.map(f64::abs);
+        let primes = [Self::PRIME_X, Self::PRIME_Y, Self::PRIME_Z];
+        let mut cell: [u64; 3] =
+            std::array::from_fn(|axis| (base[axis] as i64 as u64).wrapping_mul(primes[axis]));
+
+        let mut seed = self.seed;
+        let mut value = 0.;
+        let mut a = d[0].mul_add(-d[0], Self::RSQUARED_3D) - d[1].mul_add(d[1], d[2] * d[2]);
+        for lattice in 0..2 {
+            if a > 0. {
+                value = falloff(a).mul_add(grad3_simplex(seed, cell, d), value);
+            }
+
+            // the nearest vertex along the axis the point is furthest along
+            let axis = if abs[0] >= abs[1] && abs[0] >= abs[2] {
+                0
+            } else if abs[1] > abs[0] && abs[1] >= abs[2] {
+                1
+            } else {
+                2
+            };
+            let b = 2.0f64.mul_add(abs[axis], a) - 1.;
+            if b > 0. {
+                let mut vertex = cell;
+                let mut offset = d;
+                if sign[axis] < 0. {
+                    vertex[axis] = vertex[axis].wrapping_add(primes[axis]);
+                } else {
+                    vertex[axis] = vertex[axis].wrapping_sub(primes[axis]);
+                }
+                offset[axis] += sign[axis];
+                value = falloff(b).mul_add(grad3_simplex(seed, vertex, offset), value);
+            }
+
+            if lattice == 1 {
+                break;
+            }
+            // move on to the closest vertex of the second, offset lattice
+            abs = abs.map(|abs| 0.5 - abs);
+            d = std::array::from_fn(|axis| sign[axis] * abs[axis]);
+            for ((cell, &sign), &prime) in cell.iter_mut().zip(&sign).zip(&primes) {
+                if sign < 0. {
+                    *cell = cell.wrapping_add(prime);
+                }
+            }
+            a += (0.75 - abs[0]) - (abs[1] + abs[2]);
+            sign = sign.map(|sign| -sign);
+            seed ^= Self::SEED_FLIP_3D;
+        }
+
+        value / Self::NORMALIZER_3D
+    }
+
+    fn grad(&self, xsvp: u64, ysvp: u64, dx: f64, dy: f64) -> f64 {
+        let hash = (self.seed ^ xsvp ^ ysvp).wrapping_mul(Self::HASH_MULTIPLIER);
+        let [gx, gy] = GRADIENTS_2D[gradient_index(hash, GRADIENTS_2D.len())];
+        gx.mul_add(dx, gy * dy)
+    }
+}
+
+impl NoiseSource for OpenSimplex2 {
+    fn from_seed(seed: u64) -> Self {
+        Self::new(seed)
+    }
+
+    fn get(&self, point: [f64; 2]) -> f64 {
+        self.get(point)
+    }
+
+    fn get3(&self, point: [f64; 3]) -> f64 {
+        self.get3(point)
+    }
+}
+
+/// Weight of a simplex vertex's contribution, given `a`, the squared radius minus the squared
+/// distance to it.
+fn falloff(a: f64) -> f64 {
+    let a2 = a * a;
+    a2 * a2
+}
+
+fn grad3_simplex(seed: u64, vertex: [u64; 3], d: [f64; 3]) -> f64 {
+    let hash =
+        (seed ^ vertex[0] ^ vertex[1] ^ vertex[2]).wrapping_mul(OpenSimplex2::HASH_MULTIPLIER);
+    let [gx, gy, gz] = GRADIENTS_3D[gradient_index(hash, GRADIENTS_3D.len())];
+    gx.mul_add(d[0], gy.mul_add(d[1], gz * d[2]))
+}
+
+/// Maps the high bits of `hash` evenly onto `0..len`.
+const fn gradient_index(hash: u64, len: usize) -> usize {
+    (((hash >> 32) * len as u64) >> 32) as usize
+}
+
+/// 24 unit vectors evenly spaced around the circle, none aligned with an axis.
+const GRADIENTS_2D: [[f64; 2]; 24] = {
+    const C7: f64 = 0.991_444_861_373_810_4;
+    const S7: f64 = 0.130_526_192_220_051_57;
+    const C22: f64 = 0.923_879_532_511_286_7;
+    const S22: f64 = 0.382_683_432_365_089_8;
+    const C37: f64 = 0.793_353_340_291_235_2;
+    const S37: f64 = 0.608_761_429_008_720_7;
+    [
+        [C7, S7],
+        [C22, S22],
+        [C37, S37],
+        [S37, C37],
+        [S22, C22],
+        [S7, C7],
+        [-S7, C7],
+        [-S22, C22],
+        [-S37, C37],
+        [-C37, S37],
+        [-C22, S22],
+        [-C7, S7],
+        [-C7, -S7],
+        [-C22, -S22],
+        [-C37, -S37],
+        [-S37, -C37],
+        [-S22, -C22],
+        [-S7, -C7],
+        [S7, -C7],
+        [S22, -C22],
+        [S37, -C37],
+        [C37, -S37],
+        [C22, -S22],
+        [C7, -S7],
+    ]
+};
+
+/// 4 vectors around each of the 12 edge midpoints of a cube, all of the same length.
+const GRADIENTS_3D: [[f64; 3]; 48] = {
+    const A: f64 = 2.224_744_871_391_589;
+    const B: f64 = 3.086_266_468_797_201_7;
+    const C: f64 = 1.172_151_342_246_497_8;
+    [
+        [A, A, -1.0],
+        [A, A, 1.0],
+        [B, C, 0.0],
+        [C, B, 0.0],
+        [A, -A, -1.0],
+        [A, -A, 1.0],
+        [B, -C, 0.0],
+        [C, -B, 0.0],
+        [-A, A, -1.0],
+        [-A, A, 1.0],
+        [-B, C, 0.0],
+        [-C, B, 0.0],
+        [-A, -A, -1.0],
+        [-A, -A, 1.0],
+        [-B, -C, 0.0],
+        [-C, -B, 0.0],
+        [A, -1.0, A],
+        [A, 1.0, A],
+        [B, 0.0, C],
+        [C, 0.0, B],
+        [A, -1.0, -A],
+        [A, 1.0, -A],
+        [B, 0.0, -C],
+        [C, 0.0, -B],
+        [-A, -1.0, A],
+        [-A, 1.0, A],
+        [-B, 0.0, C],
+        [-C, 0.0, B],
+        [-A, -1.0, -A],
+        [-A, 1.0, -A],
+        [-B, 0.0, -C],
+        [-C, 0.0, -B],
+        [-1.0, A, A],
+        [1.0, A, A],
+        [0.0, B, C],
+        [0.0, C, B],
+        [-1.0, A, -A],
+        [1.0, A, -A],
+        [0.0, B, -C],
+        [0.0, C, -B],
+        [-1.0, -A, A],
+        [1.0, -A, A],
+        [0.0, -B, C],
+        [0.0, -C, B],
+        [-1.0, -A, -A],
+        [1.0, -A, -A],
+        [0.0, -B, -C],
+        [0.0, -C, -B],
+    ]
+};
+
 fn fade(t: f64) -> f64 {
     // 6t^5 - 15t^4 + 10t^3
     t * t * t * t.mul_add(t.mul_add(6.0, -15.0), 10.0)
@@ -150,7 +683,28 @@ fn grad(hash: u8, x: f64, y: f64) -> f64 {
     }
 }
 
-const fn splitmix64(state: &mut u64) -> u64 {
+/// Gradients are the 12 edge midpoints of a cube, with 4 repeated to make 16.
+fn grad3(hash: u8, x: f64, y: f64, z: f64) -> f64 {
+    match hash & 15 {
+        0 | 12 => x + y,
+        1 | 13 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x + z,
+        5 => -x + z,
+        6 => x - z,
+        7 => -x - z,
+        8 => y + z,
+        9 | 14 => -y + z,
+        10 => y - z,
+        _ => -y - z,
+    }
+}
+
+/// Advances `state` and returns the next output of the SplitMix64 generator.
+///
+/// Useful for deterministic hashing of integer coordinates in worldgen.
+pub const fn splitmix64(state: &mut u64) -> u64 {
     *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
     let mut z = *state;
     z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
@@ -158,6 +712,23 @@ const fn splitmix64(state: &mut u64) -> u64 {
     z ^ (z >> 31)
 }
 
+/// Derives an independent seed for a named feature of a world (e.g. `"rivers"`), so that noise
+/// layers built from the same world seed don't correlate.
+pub const fn derive_seed(seed: u64, feature: &str) -> u64 {
+    // FNV-1a
+    let bytes = feature.as_bytes();
+    let mut hash: u64 = 0xCBF2_9CE4_8422_2325;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01B3);
+        i += 1;
+    }
+    let mut state = seed;
+    state = splitmix64(&mut state) ^ hash;
+    splitmix64(&mut state)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -207,6 +778,93 @@ mod tests {
         assert!(max_abs < 1.5, "max |Perlin| = {max_abs} is too large");
     }
 
+    #[test]
+    fn perlin3_is_zero_at_integer_lattice_points_and_bounded() {
+        let p = Perlin::new(42);
+        for x in -3..=3 {
+            for y in -3..=3 {
+                for z in -3..=3 {
+                    let v = p.get3([x as f64, y as f64, z as f64]);
+                    assert!(v.abs() < 1e-9, "expected 0 at ({x}, {y}, {z}), got {v}");
+                }
+            }
+        }
+        let mut max_abs = 0.0f64;
+        for i in 0..50 {
+            for j in 0..50 {
+                for k in 0..50 {
+                    let v = p.get3([i as f64 * 0.13, j as f64 * 0.17, k as f64 * 0.19]);
+                    max_abs = max_abs.max(v.abs());
+                }
+            }
+        }
+        assert!(max_abs > 0.3 && max_abs < 1.5, "max |Perlin3| = {max_abs}");
+    }
+
+    #[test]
+    fn simplex_is_deterministic_and_seeded() {
+        let a = OpenSimplex2::new(1234);
+        let b = OpenSimplex2::new(1234);
+        let c = OpenSimplex2::new(1235);
+        let points =
+            (0..100).map(|i| [(i as f64).mul_add(0.37, -20.), (i as f64).mul_add(0.91, 3.)]);
+        let mut differs = false;
+        for [x, y] in points {
+            assert_eq!(a.get([x, y]), b.get([x, y]));
+            assert_eq!(a.get3([x, y, x - y]), b.get3([x, y, x - y]));
+            differs |= a.get([x, y]) != c.get([x, y]) && a.get3([x, y, 0.5]) != c.get3([x, y, 0.5]);
+        }
+        assert!(differs);
+    }
+
+    #[test]
+    fn simplex_output_roughly_bounded() {
+        let p = OpenSimplex2::new(7);
+        let mut max_abs = 0.0f64;
+        let mut max_abs3 = 0.0f64;
+        for i in -100..100 {
+            for j in -100..100 {
+                max_abs = max_abs.max(p.get([i as f64 * 0.13, j as f64 * 0.17]).abs());
+                let z = (i * j) as f64 * 0.011;
+                max_abs3 = max_abs3.max(p.get3([i as f64 * 0.13, j as f64 * 0.17, z]).abs());
+            }
+        }
+        assert!(
+            max_abs > 0.5 && max_abs < 1.1,
+            "max |OpenSimplex2| = {max_abs}"
+        );
+        assert!(
+            max_abs3 > 0.5 && max_abs3 < 1.1,
+            "max |OpenSimplex2 3D| = {max_abs3}"
+        );
+    }
+
+    #[test]
+    fn derived_seeds_differ_by_seed_and_feature() {
+        assert_eq!(derive_seed(1, "rivers"), derive_seed(1, "rivers"));
+        assert_ne!(derive_seed(1, "rivers"), derive_seed(2, "rivers"));
+        assert_ne!(derive_seed(1, "rivers"), derive_seed(1, "lakes"));
+        assert_ne!(derive_seed(0, ""), 0);
+        // the full 64 bits of the seed matter
+        assert_ne!(derive_seed(1 << 40, "rivers"), derive_seed(0, "rivers"));
+    }
+
+    #[test]
+    fn fbm_octaves_are_independent() {
+        let fbm = Fbm::new(3);
+        let seeds_match = fbm.sources.windows(2).any(|pair| {
+            (0..20).all(|i| {
+                let p = [(i as f64).mul_add(0.31, 0.5), (i as f64).mul_add(0.17, 0.5)];
+                pair[0].get(p) == pair[1].get(p)
+            })
+        });
+        assert!(!seeds_match);
+        // fewer octaves of the same seed reuse the same sources
+        let smooth = Fbm::new(3).set_octaves(2);
+        let p = [0.3, 0.7];
+        assert_eq!(smooth.sources[1].get(p), fbm.sources[1].get(p));
+    }
+
     #[test]
     fn fbm_respects_octaves_and_persistence() {
         let base = Fbm::new(1);
@@ -217,4 +875,29 @@ mod tests {
         // Different parameters should produce different samples.
         assert_ne!(base.get([0.3, 0.4]), tuned.get([0.3, 0.4]));
     }
+
+    #[test]
+    fn grids_match_scalar_sampling_exactly() {
+        let xs: [f64; 32] = std::array::from_fn(|i| (i as f64).mul_add(0.173, -3.01));
+        let ys: [f64; 32] = std::array::from_fn(|i| (i as f64).mul_add(-0.291, 257.5));
+        let zs: [f64; 32] = std::array::from_fn(|i| (i as f64).mul_add(0.047, -0.5));
+
+        let perlin = Perlin::new(5);
+        let grid = perlin.get_grid(&xs, &ys);
+        let fbm = Fbm::new(5).set_octaves(7).set_persistence(0.6);
+        let fbm_grid = fbm.get_grid(&xs, &ys);
+        let fbm_grid3 = fbm.get_grid3(&xs, &ys, &zs);
+        let simplex = Fbm::<OpenSimplex2>::from_seed(5);
+        let simplex_grid = simplex.get_grid(&xs, &ys);
+        for (i, &x) in xs.iter().enumerate() {
+            for (j, &y) in ys.iter().enumerate() {
+                assert_eq!(grid[i][j].to_bits(), perlin.get([x, y]).to_bits());
+                assert_eq!(fbm_grid[i][j].to_bits(), fbm.get([x, y]).to_bits());
+                assert_eq!(simplex_grid[i][j].to_bits(), simplex.get([x, y]).to_bits());
+                for (k, &z) in zs.iter().enumerate() {
+                    assert_eq!(fbm_grid3[i][j][k].to_bits(), fbm.get3([x, y, z]).to_bits());
+                }
+            }
+        }
+    }
 }
@@ -1,5 +1,5 @@
 use std::fmt;
-use std::num::NonZeroU8;
+use std::num::NonZeroU16;
 
 use linearize::Linearize;
 use strum::EnumIter;
@@ -7,33 +7,59 @@ use strum::EnumIter;
 use crate::chunks::{Array3Chunk, CHUNK_SIZE, CHUNK_SIZE_F32, CHUNK_SIZE_I32};
 use crate::zoom::ZoomLevel;
 
-/// Chunks work with [`MappedBlockID`]s (u8s), which correspond to [`crate::blocks::BlockID`]s (strings).
+pub mod pipeline;
+
+/// Chunks work with [`MappedBlockID`]s (u16s), which correspond to [`crate::blocks::BlockID`]s (strings).
+///
+/// Zero is never a valid ID, so `Option<MappedBlockID>` is no bigger than a `MappedBlockID`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct MappedBlockID(NonZeroU8);
+pub struct MappedBlockID(NonZeroU16);
 
 impl Default for MappedBlockID {
     fn default() -> Self {
-        Self(unsafe { NonZeroU8::new_unchecked(1) })
+        Self(NonZeroU16::MIN)
     }
 }
 
-impl TryFrom<u8> for MappedBlockID {
+impl TryFrom<u16> for MappedBlockID {
     type Error = &'static str;
 
-    fn try_from(value: u8) -> Result<Self, Self::Error> {
-        NonZeroU8::new(value)
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        NonZeroU16::new(value)
             .map(Self)
             .ok_or("mapped block ID cannot be zero")
     }
 }
 
+impl TryFrom<u8> for MappedBlockID {
+    type Error = &'static str;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Self::try_from(u16::from(value))
+    }
+}
+
 impl MappedBlockID {
     pub fn next(&self) -> Option<Self> {
-        if self.0 == NonZeroU8::MAX {
-            None
-        } else {
-            Some(Self(self.0.checked_add(1).unwrap()))
-        }
+        self.0.checked_add(1).map(Self)
+    }
+}
+
+/// Which of its block type's states a block is in (see [`crate::blocks::BlockType::states`]), where
+/// 0 is the default state.
+pub type BlockStateID = u8;
+
+/// A block in a particular state.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BlockState {
+    pub block: MappedBlockID,
+    pub state: BlockStateID,
+}
+
+impl From<MappedBlockID> for BlockState {
+    /// The block in its default state.
+    fn from(block: MappedBlockID) -> Self {
+        Self { block, state: 0 }
     }
 }
 
@@ -134,3 +160,19 @@ impl From<Direction> for [i32; 3] {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::block::VoxelBlock;
+
+    #[test]
+    fn ids_stay_compact() {
+        assert_eq!(size_of::<Option<MappedBlockID>>(), 2);
+        assert_eq!(size_of::<Option<BlockState>>(), 4);
+        assert_eq!(size_of::<VoxelBlock>(), 6);
+        let max = MappedBlockID::try_from(u16::MAX).unwrap();
+        assert_eq!(max.next(), None);
+        assert!(MappedBlockID::try_from(0u16).is_err());
+    }
+}
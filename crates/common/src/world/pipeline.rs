@@ -0,0 +1,273 @@
+//! Multi-stage world generation.
+//!
+//! A [`Pipeline`] runs a series of [`Stage`]s over a chunk, in [`StageKind`] order. Stages share
+//! a [`ChunkContext`] holding per-column data (heights, water levels, biomes), so that e.g. a
+//! decoration stage can place trees on the surface computed by a shape stage of a different
+//! generator.
+
+use std::ops::ControlFlow;
+
+use crate::chunks::{Array3Chunk, CHUNK_SIZE, CHUNK_USIZE};
+use crate::world::{ChunkPosition, MappedBlockID, WorldGen, WorldPosition};
+use crate::zoom::ZoomLevel;
+
+/// The order in which stages run. Stages of the same kind run in the order they were added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum StageKind {
+    /// Decides the overall shape of the terrain, e.g. column heights.
+    Shape,
+    /// Places blocks making up the terrain, e.g. grass on top of dirt on top of stone.
+    Surface,
+    /// Removes blocks, e.g. caves.
+    Carvers,
+    /// Small features, e.g. flowers or trees.
+    Decorations,
+    /// Large features, possibly spanning chunks.
+    Structures,
+}
+
+/// Generator-defined biome identifier.
+pub type BiomeID = u16;
+
+/// Per-column data shared between stages. Heights are in unzoomed world units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Column {
+    /// Height of the terrain surface.
+    pub height: f64,
+    /// Height of the water surface, if it is above the terrain.
+    pub water_level: f64,
+    pub biome: BiomeID,
+    /// sRGB colour that tinted faces of blocks in the column are multiplied by, e.g. to make
+    /// grass look lusher or drier.
+    pub tint: Option<[u8; 3]>,
+}
+
+impl Default for Column {
+    fn default() -> Self {
+        Self {
+            height: f64::NEG_INFINITY,
+            water_level: f64::NEG_INFINITY,
+            biome: 0,
+            tint: None,
+        }
+    }
+}
+
+/// State passed through every stage while generating a single chunk.
+#[derive(Debug, Clone)]
+pub struct ChunkContext {
+    pub position: ChunkPosition,
+    pub zoom_level: ZoomLevel,
+    /// Indexed by `[x][z]` block position within the chunk.
+    pub columns: [[Column; CHUNK_USIZE]; CHUNK_USIZE],
+    zoom: f64,
+    zoomed_offset: [f64; 3],
+}
+
+impl ChunkContext {
+    pub fn new(position: ChunkPosition, zoom_level: ZoomLevel) -> Self {
+        let zoom = zoom_level.as_f64();
+        let offset: WorldPosition = (&position).into();
+        Self {
+            position,
+            zoom_level,
+            columns: [[Column::default(); CHUNK_USIZE]; CHUNK_USIZE],
+            zoom,
+            zoomed_offset: [
+                offset.x as f64 / zoom,
+                offset.y as f64 / zoom,
+                offset.z as f64 / zoom,
+            ],
+        }
+    }
+
+    /// See [`ZoomLevel::as_f64`].
+    pub const fn zoom(&self) -> f64 {
+        self.zoom
+    }
+
+    /// Unzoomed world coordinates of the block at `[x, y, z]` within the chunk.
+    pub fn world_coords(&self, [x, y, z]: [u8; 3]) -> [f64; 3] {
+        [
+            x as f64 / self.zoom + self.zoomed_offset[0],
+            y as f64 / self.zoom + self.zoomed_offset[1],
+            z as f64 / self.zoom + self.zoomed_offset[2],
+        ]
+    }
+
+    pub const fn column(&self, x: u8, z: u8) -> &Column {
+        &self.columns[x as usize][z as usize]
+    }
+
+    pub const fn column_mut(&mut self, x: u8, z: u8) -> &mut Column {
+        &mut self.columns[x as usize][z as usize]
+    }
+
+    /// Iterates over the `(x, z)` positions of every column in the chunk.
+    pub fn column_positions() -> impl Iterator<Item = (u8, u8)> {
+        (0..CHUNK_SIZE).flat_map(|x| (0..CHUNK_SIZE).map(move |z| (x, z)))
+    }
+}
+
+/// A single step of world generation.
+pub trait Stage: Send + Sync {
+    /// Reads and/or modifies `chunk`. Returning [`ControlFlow::Break`] means the chunk is
+    /// known to be empty, and no further stages will run.
+    fn apply(&self, ctx: &mut ChunkContext, chunk: &mut Array3Chunk) -> ControlFlow<()>;
+}
+
+impl<F> Stage for F
+where
+    F: Fn(&mut ChunkContext, &mut Array3Chunk) -> ControlFlow<()> + Send + Sync,
+{
+    fn apply(&self, ctx: &mut ChunkContext, chunk: &mut Array3Chunk) -> ControlFlow<()> {
+        self(ctx, chunk)
+    }
+}
+
+/// A [`WorldGen`] made up of [`Stage`]s.
+#[derive(Default)]
+pub struct Pipeline {
+    stages: Vec<(StageKind, Box<dyn Stage>)>,
+    /// Blocks with tinted faces, as column tints are only kept in chunks with any of them.
+    tinted: Vec<MappedBlockID>,
+}
+
+impl Pipeline {
+    #[must_use]
+    pub fn with_stage(mut self, kind: StageKind, stage: impl Stage + 'static) -> Self {
+        let index = self.stages.partition_point(|(k, _)| *k <= kind);
+        self.stages.insert(index, (kind, Box::new(stage)));
+        self
+    }
+
+    /// Keeps column tints in chunks with any of the `tinted` blocks, which are otherwise dropped.
+    #[must_use]
+    pub fn with_tinted_blocks(mut self, tinted: impl IntoIterator<Item = MappedBlockID>) -> Self {
+        self.tinted.extend(tinted);
+        self
+    }
+
+    pub fn kinds(&self) -> impl Iterator<Item = StageKind> + '_ {
+        self.stages.iter().map(|(kind, _)| *kind)
+    }
+}
+
+impl WorldGen for Pipeline {
+    fn get(&self, pos: &ChunkPosition, zoom_level: ZoomLevel) -> Option<Array3Chunk> {
+        let mut ctx = ChunkContext::new(*pos, zoom_level);
+        let mut chunk = Array3Chunk::default();
+        for (_, stage) in &self.stages {
+            if stage.apply(&mut ctx, &mut chunk).is_break() {
+                return None;
+            }
+        }
+        if chunk.blocks().any(|block| self.tinted.contains(&block)) {
+            for (x, z) in ChunkContext::column_positions() {
+                if let Some(tint) = ctx.column(x, z).tint {
+                    chunk.set_tint(x, z, tint);
+                }
+            }
+        }
+        Some(chunk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::BlockPosition;
+
+    fn place(y: u8) -> impl Stage {
+        move |_: &mut ChunkContext, chunk: &mut Array3Chunk| {
+            chunk.insert(&BlockPosition { x: 0, y, z: 0 }, MappedBlockID::default());
+            ControlFlow::Continue(())
+        }
+    }
+
+    #[test]
+    fn stages_run_in_kind_order() {
+        let pipeline = Pipeline::default()
+            .with_stage(StageKind::Decorations, place(2))
+            .with_stage(StageKind::Shape, place(0))
+            .with_stage(StageKind::Surface, place(1))
+            .with_stage(StageKind::Shape, place(3));
+        assert_eq!(
+            pipeline.kinds().collect::<Vec<_>>(),
+            [
+                StageKind::Shape,
+                StageKind::Shape,
+                StageKind::Surface,
+                StageKind::Decorations
+            ]
+        );
+        let chunk = pipeline
+            .get(&ChunkPosition::default(), ZoomLevel::default())
+            .unwrap();
+        for y in 0..4 {
+            assert!(chunk.get(&BlockPosition { x: 0, y, z: 0 }).is_some());
+        }
+    }
+
+    #[test]
+    fn break_means_empty_chunk() {
+        let pipeline = Pipeline::default()
+            .with_stage(
+                StageKind::Shape,
+                |ctx: &mut ChunkContext, _: &mut Array3Chunk| {
+                    if ctx.position.y < 0 {
+                        ControlFlow::Break(())
+                    } else {
+                        ControlFlow::Continue(())
+                    }
+                },
+            )
+            .with_stage(StageKind::Surface, place(0));
+        let below = ChunkPosition { x: 0, y: -1, z: 0 };
+        assert!(pipeline.get(&below, ZoomLevel::default()).is_none());
+        assert!(
+            pipeline
+                .get(&ChunkPosition::default(), ZoomLevel::default())
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn column_tints_are_kept_in_the_chunk() {
+        let untinted = Pipeline::default().with_stage(StageKind::Surface, place(0));
+        let chunk = untinted
+            .get(&ChunkPosition::default(), ZoomLevel::default())
+            .unwrap();
+        assert!(chunk.tints().is_none());
+
+        let tinted = untinted.with_stage(
+            StageKind::Shape,
+            |ctx: &mut ChunkContext, _: &mut Array3Chunk| {
+                ctx.column_mut(1, 2).tint = Some([255, 200, 100]);
+                ControlFlow::Continue(())
+            },
+        );
+        let chunk = tinted
+            .get(&ChunkPosition::default(), ZoomLevel::default())
+            .unwrap();
+        assert!(
+            chunk.tints().is_none(),
+            "tints are dropped from chunks without tinted blocks"
+        );
+
+        let chunk = tinted
+            .with_tinted_blocks([MappedBlockID::default()])
+            .get(&ChunkPosition::default(), ZoomLevel::default())
+            .unwrap();
+        let tints = chunk.tints().unwrap();
+        assert_eq!(tints[1][2], [255, 200, 100]);
+        assert_eq!(tints[2][1], [255; 3]);
+    }
+
+    #[test]
+    fn world_coords_respect_zoom() {
+        let ctx = ChunkContext::new(ChunkPosition { x: 1, y: -1, z: 0 }, ZoomLevel(1));
+        assert_eq!(ctx.world_coords([0, 0, 0]), [16., -16., 0.]);
+        assert_eq!(ctx.world_coords([2, 4, 6]), [17., -14., 3.]);
+    }
+}
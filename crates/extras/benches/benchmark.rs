@@ -19,7 +19,7 @@ fn bench_mountain_islands(c: &mut Criterion) {
         })
         .collect();
     let palette: Palette = mapping.into();
-    let wgen = MountainIslands::new(0, palette);
+    let wgen = MountainIslands::new(0, &palette).unwrap().pipeline();
     const SIZE: i32 = 4;
     c.bench_function("mountain islands", |b| {
         b.iter(|| {
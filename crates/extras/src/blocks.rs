@@ -1,4 +1,11 @@
-use infinigen_common::blocks::{BlockType, BlockVisibility};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[cfg(test)]
+use infinigen_common::blocks::Palette;
+use infinigen_common::blocks::{
+    BlockTag, BlockType, BlockVariant, BlockVisibility, Face, MAX_LIGHT_EMISSION, MaterialMaps,
+};
+use strum::IntoEnumIterator;
 
 pub const DIRT_BLOCK_ID: &str = "infinigen:dirt";
 pub const GRASS_BLOCK_ID: &str = "infinigen:grass";
@@ -11,38 +18,63 @@ pub const STONE_BLOCK_ID: &str = "infinigen:stone";
 pub const WATER_BLOCK_ID: &str = "infinigen:water";
 pub const WOOD_BLOCK_ID: &str = "infinigen:wood";
 
+pub const FLUID_TAG: &str = "infinigen:fluid";
+pub const PLANT_TAG: &str = "infinigen:plant";
+pub const SOIL_TAG: &str = "infinigen:soil";
+/// Natural stone, as opposed to e.g. bricks.
+pub const STONE_TAG: &str = "infinigen:stone";
+
+/// Block state property of grass, `"true"` when it's covered in snow.
+pub const SNOWY_PROPERTY: &str = "snowy";
+
 pub fn block_types() -> impl Iterator<Item = BlockType> {
     [
         BlockType {
             id: STONE_BLOCK_ID.to_string(),
             color: [128, 128, 128, 255],
+            tags: tags([STONE_TAG]),
             ..Default::default()
         },
         BlockType {
             id: DIRT_BLOCK_ID.to_string(),
             color: [139, 69, 19, 255],
+            tags: tags([SOIL_TAG]),
             ..Default::default()
         },
         BlockType {
             id: GRASS_BLOCK_ID.to_string(),
             color: [34, 139, 34, 255],
+            tags: tags([SOIL_TAG]),
+            tint: BTreeSet::from([Face::Top]),
+            states: BTreeMap::from([(SNOWY_PROPERTY.to_string(), bools())]),
+            variants: vec![BlockVariant {
+                when: BTreeMap::from([(SNOWY_PROPERTY.to_string(), "true".to_string())]),
+                color: Some([255, 250, 250, 255]),
+                textures: None,
+                maps: MaterialMaps::default(),
+                tint: Some(BTreeSet::new()),
+            }],
             ..Default::default()
         },
         BlockType {
             id: WATER_BLOCK_ID.to_string(),
             visibility: BlockVisibility::Translucent,
             color: [25, 153, 230, 128],
+            tags: tags([FLUID_TAG]),
             ..Default::default()
         },
         BlockType {
             id: LAVA_BLOCK_ID.to_string(),
             visibility: BlockVisibility::Translucent,
             color: [207, 16, 32, 128],
+            light_emission: MAX_LIGHT_EMISSION,
+            tags: tags([FLUID_TAG]),
             ..Default::default()
         },
         BlockType {
             id: SAND_BLOCK_ID.to_string(),
             color: [194, 178, 128, 255],
+            tags: tags([SOIL_TAG]),
             ..Default::default()
         },
         BlockType {
@@ -53,18 +85,43 @@ pub fn block_types() -> impl Iterator<Item = BlockType> {
         BlockType {
             id: WOOD_BLOCK_ID.to_string(),
             color: [139, 69, 19, 255],
+            tags: tags([PLANT_TAG]),
             ..Default::default()
         },
         BlockType {
             id: LEAVES_BLOCK_ID.to_string(),
             color: [84, 161, 66, 255],
+            tint: Face::iter().collect(),
+            tags: tags([PLANT_TAG]),
             ..Default::default()
         },
         BlockType {
             id: GRAVEL_BLOCK_ID.to_string(),
             color: [128, 128, 128, 255],
+            tags: tags([SOIL_TAG]),
             ..Default::default()
         },
     ]
     .into_iter()
 }
+
+/// Values of a true/false state property, defaulting to false.
+fn bools() -> Vec<String> {
+    vec!["false".to_string(), "true".to_string()]
+}
+
+fn tags<const N: usize>(tags: [&str; N]) -> BTreeSet<BlockTag> {
+    tags.into_iter().map(str::to_string).collect()
+}
+
+/// Palette mapping every block in [`block_types`], in order.
+#[cfg(test)]
+pub(crate) fn test_palette() -> Palette {
+    use infinigen_common::world::MappedBlockID;
+
+    let mut palette = Palette::default();
+    for (i, block_type) in block_types().enumerate() {
+        palette.insert(&block_type, MappedBlockID::try_from(1 + i as u8).unwrap());
+    }
+    palette
+}
@@ -7,7 +7,7 @@ use std::str::FromStr;
 
 use bevy::prelude::*;
 use infinigen_plugins::assets::DefaultBlockTypes;
-use infinigen_plugins::world::WorldInitializer;
+use infinigen_plugins::world::{WorldInitializer, WorldSettings};
 use worldgen::WorldGenTypes;
 
 pub mod blocks;
@@ -20,9 +20,9 @@ impl Plugin for ExtrasPlugin {
         tracing::info!("Initializing extras plugin");
         app.insert_resource(DefaultBlockTypes(crate::blocks::block_types().collect()))
             .insert_resource(WorldInitializer(Box::new(
-                move |world_gen_name: &str, seed, palette| {
-                    let world_gen_type = WorldGenTypes::from_str(world_gen_name)?;
-                    Ok(world_gen_type.as_world_gen(seed, palette))
+                move |settings: &WorldSettings, palette| {
+                    let world_gen_type = WorldGenTypes::from_str(&settings.world_gen_name)?;
+                    Ok(world_gen_type.as_world_gen(settings, palette)?)
                 },
             )));
     }
@@ -1,11 +1,15 @@
+use std::fmt;
 use std::sync::Arc;
 
 use infinigen_common::blocks::Palette;
-use infinigen_common::world::WorldGen;
+use infinigen_common::world::{MappedBlockID, WorldGen};
+use infinigen_plugins::world::WorldSettings;
 use serde::{Deserialize, Serialize};
 use strum::{Display, EnumString};
 
 pub mod flat;
+pub mod floating_islands;
+pub mod heightmap_image;
 pub mod mountain_islands;
 pub mod single_block;
 
@@ -14,23 +18,127 @@ pub mod single_block;
 )]
 pub enum WorldGenTypes {
     Flat,
+    FloatingIslands,
+    HeightmapImage,
     #[default]
     MountainIslands,
     SingleBlock,
 }
 
+/// Why a world generator couldn't be constructed.
+#[derive(Debug)]
+pub enum WorldGenError {
+    /// IDs of blocks the generator needs which aren't in the palette.
+    MissingBlocks(Vec<String>),
+    Flat(flat::FlatError),
+    Heightmap(heightmap_image::HeightmapError),
+}
+
+impl fmt::Display for WorldGenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingBlocks(block_ids) => {
+                write!(f, "missing required blocks: {}", block_ids.join(", "))
+            }
+            Self::Flat(err) => write!(f, "invalid flat world settings: {err}"),
+            Self::Heightmap(err) => write!(f, "invalid heightmap settings: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for WorldGenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::MissingBlocks(_) => None,
+            Self::Flat(err) => Some(err),
+            Self::Heightmap(err) => Some(err),
+        }
+    }
+}
+
+impl From<flat::FlatError> for WorldGenError {
+    fn from(err: flat::FlatError) -> Self {
+        Self::Flat(err)
+    }
+}
+
+impl From<heightmap_image::HeightmapError> for WorldGenError {
+    fn from(err: heightmap_image::HeightmapError) -> Self {
+        Self::Heightmap(err)
+    }
+}
+
+/// Checks that every one of `block_ids` is in `palette`, failing with all of those that aren't.
+pub fn check_blocks<'a>(
+    palette: &Palette,
+    block_ids: impl IntoIterator<Item = &'a str>,
+) -> Result<(), WorldGenError> {
+    let mut missing: Vec<String> = vec![];
+    for block_id in block_ids {
+        if !palette.inner.contains_key(block_id) && !missing.iter().any(|id| id == block_id) {
+            missing.push(block_id.to_string());
+        }
+    }
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(WorldGenError::MissingBlocks(missing))
+    }
+}
+
+/// Looks up every one of `block_ids` in `palette`, failing with all of those that are missing.
+pub fn required_blocks<const N: usize>(
+    palette: &Palette,
+    block_ids: [&str; N],
+) -> Result<[MappedBlockID; N], WorldGenError> {
+    check_blocks(palette, block_ids)?;
+    Ok(block_ids.map(|block_id| palette.inner[block_id]))
+}
+
 impl WorldGenTypes {
     pub(crate) fn as_world_gen(
         &self,
-        seed: u32,
+        settings: &WorldSettings,
         palette: Palette,
-    ) -> Arc<dyn WorldGen + Send + Sync> {
-        match self {
-            Self::Flat => Arc::new(flat::Flat::from(palette)),
-            Self::MountainIslands => {
-                Arc::new(mountain_islands::MountainIslands::new(seed, palette))
+    ) -> Result<Arc<dyn WorldGen + Send + Sync>, WorldGenError> {
+        Ok(match self {
+            Self::Flat => Arc::new(flat::Flat::new(&settings.flat, &palette)?.pipeline()),
+            Self::FloatingIslands => Arc::new(
+                floating_islands::FloatingIslands::new(settings.seed, &palette)?.pipeline(),
+            ),
+            Self::HeightmapImage => {
+                let settings = settings
+                    .heightmap
+                    .as_ref()
+                    .ok_or(heightmap_image::HeightmapError::MissingSettings)?;
+                Arc::new(heightmap_image::HeightmapImage::load(settings, &palette)?.pipeline())
             }
-            Self::SingleBlock => Arc::new(single_block::SingleBlock::from(palette)),
-        }
+            Self::MountainIslands => Arc::new(
+                mountain_islands::MountainIslands::new(settings.seed, &palette)?.pipeline(),
+            ),
+            Self::SingleBlock => Arc::new(single_block::SingleBlock::new(&palette)?.pipeline()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{GRASS_BLOCK_ID, SNOW_BLOCK_ID, test_palette};
+
+    #[test]
+    fn every_missing_block_is_listed() {
+        let mut palette = test_palette();
+        palette.inner.remove(SNOW_BLOCK_ID);
+        palette.inner.remove(GRASS_BLOCK_ID);
+        let err = mountain_islands::MountainIslands::new(0, &palette).unwrap_err();
+        assert!(
+            matches!(&err, WorldGenError::MissingBlocks(ids) if ids == &[SNOW_BLOCK_ID, GRASS_BLOCK_ID]),
+            "{err}"
+        );
+        assert_eq!(
+            err.to_string(),
+            "missing required blocks: infinigen:snow, infinigen:grass"
+        );
     }
 }
@@ -1,32 +1,228 @@
+use std::fmt;
+use std::ops::ControlFlow;
+
 use infinigen_common::blocks::Palette;
-use infinigen_common::chunks::Array3Chunk;
-use infinigen_common::world::{ChunkPosition, MappedBlockID, WorldGen};
-use infinigen_common::zoom::ZoomLevel;
+use infinigen_common::chunks::{Array3Chunk, CHUNK_SIZE};
+use infinigen_common::world::pipeline::{ChunkContext, Pipeline, Stage, StageKind};
+use infinigen_common::world::{BlockPosition, MappedBlockID};
+use infinigen_plugins::settings::FlatSettings;
+
+use super::{WorldGenError, check_blocks};
+
+/// Namespace assumed for block IDs in a layer spec which don't have one.
+const DEFAULT_NAMESPACE: &str = "infinigen";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FlatError {
+    /// A layer couldn't be parsed.
+    InvalidLayer(String),
+}
+
+impl fmt::Display for FlatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLayer(layer) => write!(
+                f,
+                "invalid layer '{layer}', expected a block ID optionally followed by `*` and a count, e.g. 'stone*3'"
+            ),
+        }
+    }
+}
 
-use crate::blocks::DIRT_BLOCK_ID;
+impl std::error::Error for FlatError {}
 
-/// Generates a completely flat world of dirt at y=0.
-#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+/// Parses a layer spec like `"stone*3,dirt*2,grass"` into block IDs and counts, from the bottom up.
+pub fn parse_layers(spec: &str) -> Result<Vec<(String, u32)>, FlatError> {
+    spec.split(',')
+        .map(|layer| {
+            let invalid = || FlatError::InvalidLayer(layer.to_string());
+            let (block_id, count) = match layer.split_once('*') {
+                Some((block_id, count)) => {
+                    let count = count.trim().parse().map_err(|_| invalid())?;
+                    (block_id.trim(), count)
+                }
+                None => (layer.trim(), 1),
+            };
+            if block_id.is_empty() || count == 0 {
+                return Err(invalid());
+            }
+            let block_id = if block_id.contains(':') {
+                block_id.to_string()
+            } else {
+                format!("{DEFAULT_NAMESPACE}:{block_id}")
+            };
+            Ok((block_id, count))
+        })
+        .collect()
+}
+
+/// Generates a completely flat world made of horizontal layers of blocks.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Flat {
-    dirt: MappedBlockID,
+    base_height: i64,
+    /// Each layer's block and the world height it extends up to (exclusive), from the bottom up.
+    layers: Vec<(MappedBlockID, i64)>,
 }
 
-#[allow(clippy::fallible_impl_from)]
-impl From<Palette> for Flat {
-    fn from(palette: Palette) -> Self {
-        Self {
-            dirt: *palette.inner.get(DIRT_BLOCK_ID).unwrap(),
+impl Flat {
+    pub fn new(settings: &FlatSettings, palette: &Palette) -> Result<Self, WorldGenError> {
+        let layers = parse_layers(&settings.layers)?;
+        check_blocks(
+            palette,
+            layers.iter().map(|(block_id, _)| block_id.as_str()),
+        )?;
+        let mut top = settings.base_height;
+        let layers = layers
+            .into_iter()
+            .map(|(block_id, count)| {
+                top += count as i64;
+                (palette.inner[&block_id], top)
+            })
+            .collect();
+        Ok(Self {
+            base_height: settings.base_height,
+            layers,
+        })
+    }
+
+    pub fn pipeline(self) -> Pipeline {
+        Pipeline::default().with_stage(StageKind::Shape, self)
+    }
+
+    /// World height just above the topmost layer.
+    fn top(&self) -> i64 {
+        self.layers.last().map_or(self.base_height, |(_, top)| *top)
+    }
+
+    fn block_at(&self, wy: i64) -> Option<MappedBlockID> {
+        if wy < self.base_height {
+            return None;
         }
+        let layer = self.layers.partition_point(|(_, top)| *top <= wy);
+        self.layers.get(layer).map(|(block, _)| *block)
     }
 }
 
-impl WorldGen for Flat {
-    fn get(&self, pos: &ChunkPosition, _zoom_level: ZoomLevel) -> Option<Array3Chunk> {
+impl Stage for Flat {
+    fn apply(&self, ctx: &mut ChunkContext, chunk: &mut Array3Chunk) -> ControlFlow<()> {
         // zoom level does not change anything
-        if pos.y == -1 {
-            Some(infinigen_common::chunks::top_chunk(self.dirt))
-        } else {
-            None
+        let height = (self.top() - 1) as f64;
+        for (x, z) in ChunkContext::column_positions() {
+            ctx.column_mut(x, z).height = height;
         }
+        let bottom = ctx.position.y as i64 * CHUNK_SIZE as i64;
+        if bottom >= self.top() || bottom + (CHUNK_SIZE as i64) <= self.base_height {
+            return ControlFlow::Break(());
+        }
+        for y in 0..CHUNK_SIZE {
+            let Some(block) = self.block_at(bottom + y as i64) else {
+                continue;
+            };
+            for (x, z) in ChunkContext::column_positions() {
+                chunk.insert(&BlockPosition { x, y, z }, block);
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use infinigen_common::world::{ChunkPosition, WorldGen};
+    use infinigen_common::zoom::ZoomLevel;
+
+    use super::*;
+    use crate::blocks::{DIRT_BLOCK_ID, GRASS_BLOCK_ID, STONE_BLOCK_ID, test_palette};
+
+    fn settings(layers: &str, base_height: i64) -> FlatSettings {
+        FlatSettings {
+            layers: layers.to_string(),
+            base_height,
+        }
+    }
+
+    #[test]
+    fn parses_layer_specs() {
+        assert_eq!(
+            parse_layers("stone*3, dirt*2,infinigen:grass").unwrap(),
+            vec![
+                (STONE_BLOCK_ID.to_string(), 3),
+                (DIRT_BLOCK_ID.to_string(), 2),
+                (GRASS_BLOCK_ID.to_string(), 1),
+            ]
+        );
+        for invalid in ["", "stone,,dirt", "stone*", "stone*0", "stone*-1", "*3"] {
+            assert!(
+                matches!(parse_layers(invalid), Err(FlatError::InvalidLayer(_))),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_blocks_are_errors() {
+        let err = Flat::new(
+            &settings("stone,marble*2,dirt,ns:slate,marble", 0),
+            &test_palette(),
+        )
+        .unwrap_err();
+        assert!(
+            matches!(&err, WorldGenError::MissingBlocks(ids) if ids == &["infinigen:marble", "ns:slate"]),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn default_is_dirt_at_minus_one() {
+        let palette = test_palette();
+        let dirt = *palette.inner.get(DIRT_BLOCK_ID).unwrap();
+        let wgen = Flat::new(&FlatSettings::default(), &palette)
+            .unwrap()
+            .pipeline();
+        let zoom = ZoomLevel::default();
+        assert!(
+            wgen.get(&ChunkPosition { x: 0, y: 0, z: 0 }, zoom)
+                .is_none()
+        );
+        assert!(
+            wgen.get(&ChunkPosition { x: 0, y: -2, z: 0 }, zoom)
+                .is_none()
+        );
+        let chunk = wgen
+            .get(&ChunkPosition { x: 3, y: -1, z: -7 }, zoom)
+            .unwrap();
+        let top = CHUNK_SIZE - 1;
+        assert_eq!(chunk.get(&BlockPosition { x: 5, y: top, z: 9 }), Some(dirt));
+        assert_eq!(
+            chunk.get(&BlockPosition {
+                x: 5,
+                y: top - 1,
+                z: 9
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn layers_span_chunks() {
+        let palette = test_palette();
+        let stone = *palette.inner.get(STONE_BLOCK_ID).unwrap();
+        let grass = *palette.inner.get(GRASS_BLOCK_ID).unwrap();
+        let wgen = Flat::new(&settings("stone*50,grass", -40), &palette)
+            .unwrap()
+            .pipeline();
+        let zoom = ZoomLevel::default();
+        let block = |cy: i32, y: u8| {
+            wgen.get(&ChunkPosition { x: 0, y: cy, z: 0 }, zoom)
+                .and_then(|chunk| chunk.get(&BlockPosition { x: 0, y, z: 0 }))
+        };
+        // stone spans y = -40..10, with grass at y = 10
+        assert_eq!(block(-2, 23), None);
+        assert_eq!(block(-2, 24), Some(stone));
+        assert_eq!(block(-1, 0), Some(stone));
+        assert_eq!(block(0, 9), Some(stone));
+        assert_eq!(block(0, 10), Some(grass));
+        assert_eq!(block(0, 11), None);
+        assert_eq!(block(1, 0), None);
     }
 }
@@ -0,0 +1,435 @@
+use std::ops::ControlFlow;
+use std::sync::{Arc, Mutex};
+
+use ahash::AHashMap;
+use infinigen_common::blocks::Palette;
+use infinigen_common::chunks::{Array3Chunk, CHUNK_SIZE, CHUNK_SIZE_F64, CHUNK_USIZE};
+use infinigen_common::noise::{Fbm, derive_seed, splitmix64};
+use infinigen_common::world::pipeline::{ChunkContext, Pipeline, StageKind};
+use infinigen_common::world::{BlockPosition, MappedBlockID};
+
+use super::{WorldGenError, required_blocks};
+use crate::blocks::{DIRT_BLOCK_ID, GRASS_BLOCK_ID, STONE_BLOCK_ID, WATER_BLOCK_ID};
+
+/// Islands float in the sky at several altitudes, with nothing below them.
+#[derive(Debug, Clone)]
+pub struct FloatingIslands {
+    seed: u64,
+    /// Where islands are, horizontally, for each of [`LAYERS`]
+    masks: [Fbm; LAYERS.len()],
+    /// Roughens the shape of islands in 3D
+    detail: Fbm,
+    waterfalls: bool,
+
+    water: MappedBlockID,
+    dirt: MappedBlockID,
+    grass: MappedBlockID,
+    stone: MappedBlockID,
+}
+
+/// A horizontal band of the sky which may contain islands.
+#[derive(Debug, Clone, Copy)]
+struct Layer {
+    /// Islands are thickest at this height
+    altitude: f64,
+    /// How far tops of islands may rise above `altitude`
+    above: f64,
+    /// How far undersides of islands may hang below `altitude`
+    below: f64,
+}
+
+impl Layer {
+    const fn bottom(&self) -> f64 {
+        self.altitude - self.below
+    }
+
+    const fn top(&self) -> f64 {
+        self.altitude + self.above
+    }
+
+    /// How far `wy` is from `altitude`, from 0 at `altitude` to 1 at either extent of the layer.
+    fn falloff(&self, wy: f64) -> f64 {
+        let dy = wy - self.altitude;
+        if dy >= 0. {
+            dy / self.above
+        } else {
+            -dy / self.below
+        }
+    }
+}
+
+// Tops are kept thin relative to undersides, so islands are flat on top and taper underneath.
+const LAYERS: [Layer; 3] = [
+    Layer {
+        altitude: 96.,
+        above: 12.,
+        below: 48.,
+    },
+    Layer {
+        altitude: 256.,
+        above: 16.,
+        below: 64.,
+    },
+    Layer {
+        altitude: 448.,
+        above: 10.,
+        below: 40.,
+    },
+];
+
+/// Horizontal size of the noise deciding where islands are.
+const ISLAND_SCALE: f64 = 192.;
+/// Size of the noise roughening the surfaces of islands.
+const DETAIL_SCALE: f64 = 40.;
+const DETAIL_STRENGTH: f64 = 0.3;
+/// Positions with a density above this are solid.
+const THRESHOLD: f64 = 0.3;
+/// Number of blocks of dirt and grass on top of islands.
+const TOPSOIL_DEPTH: usize = 3;
+
+const WATERFALL_CELL_SIZE: f64 = 96.;
+const WATERFALL_CHANCE: f64 = 0.5;
+/// How far a waterfall may fall before disappearing into mist.
+const WATERFALL_LENGTH: f64 = 160.;
+/// How many cells' waterfalls are kept for other chunks to reuse.
+const WATERFALL_CACHE_CELLS: usize = 4096;
+
+impl FloatingIslands {
+    pub fn new(seed: u64, palette: &Palette) -> Result<Self, WorldGenError> {
+        let [water, dirt, grass, stone] = required_blocks(
+            palette,
+            [
+                WATER_BLOCK_ID,
+                DIRT_BLOCK_ID,
+                GRASS_BLOCK_ID,
+                STONE_BLOCK_ID,
+            ],
+        )?;
+        Ok(Self {
+            seed,
+            masks: std::array::from_fn(|i| {
+                Fbm::new(derive_seed(seed, &format!("islands{i}"))).set_octaves(4)
+            }),
+            detail: Fbm::new(derive_seed(seed, "detail")).set_octaves(3),
+            waterfalls: true,
+
+            water,
+            dirt,
+            grass,
+            stone,
+        })
+    }
+
+    /// Whether water pours from the undersides of some islands.
+    #[must_use]
+    pub const fn set_waterfalls(mut self, waterfalls: bool) -> Self {
+        self.waterfalls = waterfalls;
+        self
+    }
+
+    pub fn pipeline(self) -> Pipeline {
+        let wgen = Arc::new(self);
+        let waterfalls = wgen.waterfalls;
+        let terrain = Arc::clone(&wgen);
+        let pipeline = Pipeline::default().with_stage(
+            StageKind::Shape,
+            move |ctx: &mut ChunkContext, chunk: &mut Array3Chunk| terrain.terrain(ctx, chunk),
+        );
+        if waterfalls {
+            let cache = WaterfallCache::default();
+            pipeline.with_stage(
+                StageKind::Decorations,
+                move |ctx: &mut ChunkContext, chunk: &mut Array3Chunk| {
+                    wgen.waterfalls(ctx, chunk, &cache)
+                },
+            )
+        } else {
+            pipeline
+        }
+    }
+
+    fn mask(&self, layer: usize, wx: f64, wz: f64) -> f64 {
+        self.masks[layer].get([wx / ISLAND_SCALE, wz / ISLAND_SCALE])
+    }
+
+    /// [`Self::mask`] of every layer.
+    fn masks(&self, wx: f64, wz: f64) -> [f64; LAYERS.len()] {
+        std::array::from_fn(|layer| self.mask(layer, wx, wz))
+    }
+
+    fn density(&self, layer: usize, mask: f64, [wx, wy, wz]: [f64; 3]) -> f64 {
+        let falloff = LAYERS[layer].falloff(wy);
+        let detail = self
+            .detail
+            .get3([wx / DETAIL_SCALE, wy / DETAIL_SCALE, wz / DETAIL_SCALE]);
+        falloff.mul_add(-falloff, detail.mul_add(DETAIL_STRENGTH, mask))
+    }
+
+    /// `masks` are the results of [`Self::mask`] for each layer at `wx` and `wz`, though only
+    /// those of `layers` are used.
+    fn is_solid(
+        &self,
+        layers: &[usize],
+        masks: &[f64; LAYERS.len()],
+        [wx, wy, wz]: [f64; 3],
+    ) -> bool {
+        layers.iter().any(|&layer| {
+            let mask = masks[layer];
+            wy > LAYERS[layer].bottom()
+                && wy < LAYERS[layer].top()
+                && mask + DETAIL_STRENGTH > THRESHOLD
+                && self.density(layer, mask, [wx, wy, wz]) > THRESHOLD
+        })
+    }
+
+    fn terrain(&self, ctx: &ChunkContext, chunk: &mut Array3Chunk) -> ControlFlow<()> {
+        let step = 1. / ctx.zoom();
+        let [_, bottom, _] = ctx.world_coords([0, 0, 0]);
+        let top = CHUNK_SIZE_F64.mul_add(step, bottom);
+        let reach = if self.waterfalls {
+            WATERFALL_LENGTH
+        } else {
+            0.
+        };
+        if !LAYERS
+            .iter()
+            .any(|layer| layer.bottom() - reach < top && bottom < layer.top())
+        {
+            return ControlFlow::Break(());
+        }
+
+        // Deciding what's on the surface needs a block below and a few blocks above the chunk
+        let sample_bottom = bottom - step;
+        let sample_top = (TOPSOIL_DEPTH as f64).mul_add(step, top);
+        let layers: Vec<usize> = (0..LAYERS.len())
+            .filter(|&i| LAYERS[i].bottom() < sample_top && sample_bottom < LAYERS[i].top())
+            .collect();
+        if layers.is_empty() {
+            return if self.waterfalls {
+                ControlFlow::Continue(())
+            } else {
+                ControlFlow::Break(())
+            };
+        }
+
+        let mut is_empty = true;
+        let mut solid = [false; CHUNK_USIZE + TOPSOIL_DEPTH + 1];
+        let mut masks = [0.; LAYERS.len()];
+        for (x, z) in ChunkContext::column_positions() {
+            let [wx, _, wz] = ctx.world_coords([x, 0, z]);
+            for &i in &layers {
+                masks[i] = self.mask(i, wx, wz);
+            }
+            for (i, solid) in solid.iter_mut().enumerate() {
+                let wy = (i as f64).mul_add(step, sample_bottom);
+                *solid = self.is_solid(&layers, &masks, [wx, wy, wz]);
+            }
+            for y in 0..CHUNK_SIZE {
+                let i = y as usize + 1;
+                if !solid[i] {
+                    continue;
+                }
+                let depth = solid[i + 1..]
+                    .iter()
+                    .take(TOPSOIL_DEPTH)
+                    .take_while(|solid| **solid)
+                    .count();
+                let block = if depth == 0 {
+                    self.grass
+                } else if depth < TOPSOIL_DEPTH && solid[i - 1] {
+                    self.dirt
+                } else {
+                    self.stone
+                };
+                is_empty = false;
+                chunk.insert(&BlockPosition { x, y, z }, block);
+            }
+        }
+        if is_empty && !self.waterfalls {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+
+    fn waterfalls(
+        &self,
+        ctx: &ChunkContext,
+        chunk: &mut Array3Chunk,
+        cache: &WaterfallCache,
+    ) -> ControlFlow<()> {
+        let step = 1. / ctx.zoom();
+        let [min_x, bottom, min_z] = ctx.world_coords([0, 0, 0]);
+        let [max_x, top, max_z] = ctx.world_coords([CHUNK_SIZE, CHUNK_SIZE, CHUNK_SIZE]);
+        let cell = |w: f64| (w / WATERFALL_CELL_SIZE).floor() as i64;
+        for cx in cell(min_x)..=cell(max_x) {
+            for cz in cell(min_z)..=cell(max_z) {
+                let Some(waterfall) = cache.get([cx, cz], |cell| self.waterfall(cell)) else {
+                    continue;
+                };
+                if waterfall.bottom >= top || waterfall.top <= bottom {
+                    continue;
+                }
+                for (x, z) in ChunkContext::column_positions() {
+                    let [wx, _, wz] = ctx.world_coords([x, 0, z]);
+                    // the waterfall is one block wide, which may be less or more than a column
+                    if wx >= waterfall.x + 1. || waterfall.x >= wx + step {
+                        continue;
+                    }
+                    if wz >= waterfall.z + 1. || waterfall.z >= wz + step {
+                        continue;
+                    }
+                    for y in 0..CHUNK_SIZE {
+                        let [_, wy, _] = ctx.world_coords([x, y, z]);
+                        if wy >= waterfall.bottom && wy < waterfall.top {
+                            chunk.insert_if_free(&BlockPosition { x, y, z }, self.water);
+                        }
+                    }
+                }
+            }
+        }
+        if chunk.is_empty() {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+
+    /// The waterfall for a cell of the [`WATERFALL_CELL_SIZE`] grid, if there is one. Waterfalls
+    /// pour from the underside of the highest island above their position, until they hit
+    /// another island or have fallen [`WATERFALL_LENGTH`].
+    fn waterfall(&self, cell: [i64; 2]) -> Option<Waterfall> {
+        let mut state = derive_seed(self.seed, "waterfalls");
+        state = splitmix64(&mut state) ^ cell[0] as u64;
+        state = splitmix64(&mut state) ^ cell[1] as u64;
+        let mut unit = || (splitmix64(&mut state) >> 11) as f64 / (1u64 << 53) as f64;
+        if unit() >= WATERFALL_CHANCE {
+            return None;
+        }
+        let x = ((cell[0] as f64 + unit()) * WATERFALL_CELL_SIZE).floor();
+        let z = ((cell[1] as f64 + unit()) * WATERFALL_CELL_SIZE).floor();
+
+        let layers: [usize; LAYERS.len()] = std::array::from_fn(|i| i);
+        let masks = self.masks(x, z);
+        let top = layers.iter().rev().find_map(|&i| {
+            let heights = LAYERS[i].bottom().ceil() as i64..LAYERS[i].top().ceil() as i64;
+            heights
+                .map(|wy| wy as f64)
+                .find(|&wy| self.is_solid(&[i], &masks, [x, wy, z]))
+        })?;
+        let bottom = (1..WATERFALL_LENGTH as i64)
+            .map(|fallen| top - fallen as f64)
+            .find(|&wy| self.is_solid(&layers, &masks, [x, wy, z]))
+            .map_or(top - WATERFALL_LENGTH, |wy| wy + 1.);
+        Some(Waterfall { x, z, top, bottom })
+    }
+}
+
+/// Waterfalls of the cells that chunks have needed recently. Every chunk below a cell within
+/// [`WATERFALL_LENGTH`] needs its waterfall, which takes hundreds of noise samples to find.
+#[derive(Debug, Default)]
+struct WaterfallCache {
+    cells: Mutex<AHashMap<[i64; 2], Option<Waterfall>>>,
+}
+
+impl WaterfallCache {
+    fn get(
+        &self,
+        cell: [i64; 2],
+        waterfall: impl FnOnce([i64; 2]) -> Option<Waterfall>,
+    ) -> Option<Waterfall> {
+        if let Some(waterfall) = self.cells.lock().unwrap().get(&cell) {
+            return *waterfall;
+        }
+        // found without holding the lock, so chunks being generated in parallel don't wait on it
+        let waterfall = waterfall(cell);
+        let mut cells = self.cells.lock().unwrap();
+        // rather than tracking which cells were used least recently, start again once full
+        if cells.len() >= WATERFALL_CACHE_CELLS {
+            cells.clear();
+        }
+        cells.insert(cell, waterfall);
+        waterfall
+    }
+}
+
+/// A single block wide column of water at `x` and `z`, from `bottom` up to but excluding `top`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Waterfall {
+    x: f64,
+    z: f64,
+    top: f64,
+    bottom: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use infinigen_common::world::{ChunkPosition, WorldGen};
+    use infinigen_common::zoom::ZoomLevel;
+
+    use super::*;
+    use crate::blocks::test_palette;
+
+    #[test]
+    fn sky_between_layers_is_empty() {
+        let wgen = FloatingIslands::new(0, &test_palette())
+            .unwrap()
+            .set_waterfalls(false)
+            .pipeline();
+        // y = 4 spans 128..160, between the first and second layers
+        for x in -4..4 {
+            for z in -4..4 {
+                let pos = ChunkPosition { x, y: 4, z };
+                assert!(wgen.get(&pos, ZoomLevel::default()).is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn every_layer_has_islands() {
+        let wgen = FloatingIslands::new(0, &test_palette()).unwrap();
+        for (i, layer) in LAYERS.iter().enumerate() {
+            let has_islands = (-20..20).any(|x| {
+                (-20..20).any(|z| {
+                    let [wx, wz] = [x as f64 * 24., z as f64 * 24.];
+                    wgen.is_solid(&[i], &wgen.masks(wx, wz), [wx, layer.altitude, wz])
+                })
+            });
+            assert!(has_islands, "no islands at {}", layer.altitude);
+        }
+    }
+
+    #[test]
+    fn waterfalls_pour_from_island_undersides() {
+        let wgen = FloatingIslands::new(0, &test_palette()).unwrap();
+        let layers: Vec<usize> = (0..LAYERS.len()).collect();
+        let waterfalls: Vec<_> = (-8..8)
+            .flat_map(|x| (-8..8).map(move |z| [x, z]))
+            .filter_map(|cell| wgen.waterfall(cell))
+            .collect();
+        assert!(!waterfalls.is_empty());
+        for waterfall in waterfalls {
+            let Waterfall { x, z, top, bottom } = waterfall;
+            let masks = wgen.masks(x, z);
+            assert!(wgen.is_solid(&layers, &masks, [x, top, z]));
+            assert!(!wgen.is_solid(&layers, &masks, [x, top - 1., z]));
+            assert!(bottom < top && top - bottom <= WATERFALL_LENGTH);
+        }
+    }
+
+    #[test]
+    fn waterfalls_are_found_once_per_cell() {
+        let wgen = FloatingIslands::new(0, &test_palette()).unwrap();
+        let cache = WaterfallCache::default();
+        let cells = (-8..8).map(|x| [x, 3]);
+        for cell in cells.clone() {
+            assert_eq!(
+                cache.get(cell, |cell| wgen.waterfall(cell)),
+                wgen.waterfall(cell)
+            );
+        }
+        for cell in cells {
+            cache.get(cell, |_| panic!("{cell:?} should have been cached"));
+        }
+    }
+}
@@ -0,0 +1,561 @@
+use std::fmt;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use image::{ImageBuffer, Luma, RgbImage};
+use infinigen_common::blocks::Palette;
+use infinigen_common::chunks::{Array3Chunk, CHUNK_SIZE};
+use infinigen_common::world::pipeline::{ChunkContext, Column, Pipeline, StageKind};
+use infinigen_common::world::{BlockPosition, MappedBlockID};
+use infinigen_plugins::settings::{HeightmapEdges, HeightmapSettings};
+
+use super::{WorldGenError, required_blocks};
+use crate::blocks::{
+    DIRT_BLOCK_ID, GRASS_BLOCK_ID, GRAVEL_BLOCK_ID, SAND_BLOCK_ID, STONE_BLOCK_ID, WATER_BLOCK_ID,
+};
+
+const SEA_LEVEL: f64 = 0.;
+/// How far below the height of black pixels the terrain extends.
+const FLOOR_DEPTH: f64 = 16.;
+const TOPSOIL_DEPTH: f64 = 3.;
+
+pub type HeightImage = ImageBuffer<Luma<u16>, Vec<u16>>;
+
+#[derive(Debug)]
+pub enum HeightmapError {
+    /// The `heightmap` section of the settings is missing.
+    MissingSettings,
+    Image {
+        path: String,
+        source: image::ImageError,
+    },
+    MismatchedMaterialMap {
+        heightmap: [u32; 2],
+        material_map: [u32; 2],
+    },
+    /// A material map was given, but there are no opaque blocks to match its colours against.
+    NoMaterials,
+}
+
+impl fmt::Display for HeightmapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingSettings => write!(f, "no `heightmap` settings were provided"),
+            Self::Image { path, .. } => write!(f, "couldn't load image '{path}'"),
+            Self::MismatchedMaterialMap {
+                heightmap,
+                material_map,
+            } => write!(
+                f,
+                "material map is {}x{} but heightmap is {}x{}",
+                material_map[0], material_map[1], heightmap[0], heightmap[1]
+            ),
+            Self::NoMaterials => write!(
+                f,
+                "material map was given, but there are no opaque blocks for it to use"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HeightmapError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Image { source, .. } => Some(source),
+            Self::MissingSettings | Self::MismatchedMaterialMap { .. } | Self::NoMaterials => None,
+        }
+    }
+}
+
+/// Row-major 2D grid of pixel values.
+#[derive(Debug, Clone)]
+struct Grid<T> {
+    width: u32,
+    height: u32,
+    values: Vec<T>,
+}
+
+impl<T: Copy> Grid<T> {
+    fn get(&self, x: i64, z: i64, edges: HeightmapEdges) -> T {
+        let x = wrap(x, self.width, edges);
+        let z = wrap(z, self.height, edges);
+        self.values[z * self.width as usize + x]
+    }
+}
+
+impl Grid<f64> {
+    /// Halves the resolution by averaging 2x2 blocks of pixels.
+    fn downsample(&self) -> Self {
+        let width = self.width.div_ceil(2);
+        let height = self.height.div_ceil(2);
+        let mut values = Vec::with_capacity((width * height) as usize);
+        for z in 0..height as i64 {
+            for x in 0..width as i64 {
+                let sum: f64 = [(0, 0), (1, 0), (0, 1), (1, 1)]
+                    .into_iter()
+                    .map(|(dx, dz)| self.get(2 * x + dx, 2 * z + dz, HeightmapEdges::Clamp))
+                    .sum();
+                values.push(sum / 4.);
+            }
+        }
+        Self {
+            width,
+            height,
+            values,
+        }
+    }
+
+    /// Bilinearly interpolates at `[u, v]`, in pixels from the top left of the image.
+    fn sample(&self, [u, v]: [f64; 2], edges: HeightmapEdges) -> f64 {
+        // pixel centres are at +0.5
+        let (u, v) = (u - 0.5, v - 0.5);
+        let (x0, z0) = (u.floor(), v.floor());
+        let (tx, tz) = (u - x0, v - z0);
+        let (x0, z0) = (x0 as i64, z0 as i64);
+        let top = lerp(self.get(x0, z0, edges), self.get(x0 + 1, z0, edges), tx);
+        let bottom = lerp(
+            self.get(x0, z0 + 1, edges),
+            self.get(x0 + 1, z0 + 1, edges),
+            tx,
+        );
+        lerp(top, bottom, tz)
+    }
+}
+
+fn wrap(i: i64, size: u32, edges: HeightmapEdges) -> usize {
+    match edges {
+        HeightmapEdges::Clamp => i.clamp(0, size as i64 - 1) as usize,
+        HeightmapEdges::Tile => i.rem_euclid(size as i64) as usize,
+    }
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    t.mul_add(b - a, a)
+}
+
+/// Generates terrain from a grayscale heightmap image, centred on the world origin.
+#[derive(Debug, Clone)]
+pub struct HeightmapImage {
+    /// Normalized heights, followed by successively halved versions for zoomed out views.
+    levels: Vec<Grid<f64>>,
+    materials: Option<Grid<MappedBlockID>>,
+    horizontal_scale: f64,
+    vertical_scale: f64,
+    base_height: f64,
+    edges: HeightmapEdges,
+
+    water: MappedBlockID,
+    gravel: MappedBlockID,
+    sand: MappedBlockID,
+    dirt: MappedBlockID,
+    grass: MappedBlockID,
+    stone: MappedBlockID,
+}
+
+impl HeightmapImage {
+    /// Loads the images referenced by `settings` from disk.
+    pub fn load(settings: &HeightmapSettings, palette: &Palette) -> Result<Self, WorldGenError> {
+        let open = |path: &str| {
+            image::open(path).map_err(|source| HeightmapError::Image {
+                path: path.to_owned(),
+                source,
+            })
+        };
+        let heights = open(&settings.path)?.to_luma16();
+        let materials = settings
+            .material_map
+            .as_deref()
+            .map(|path| open(path).map(|image| image.to_rgb8()))
+            .transpose()?;
+        tracing::info!(
+            path = settings.path,
+            width = heights.width(),
+            height = heights.height(),
+            "Loaded heightmap"
+        );
+        Self::new(settings, &heights, materials.as_ref(), palette)
+    }
+
+    pub fn new(
+        settings: &HeightmapSettings,
+        heights: &HeightImage,
+        materials: Option<&RgbImage>,
+        palette: &Palette,
+    ) -> Result<Self, WorldGenError> {
+        let [water, gravel, sand, dirt, grass, stone] = required_blocks(
+            palette,
+            [
+                WATER_BLOCK_ID,
+                GRAVEL_BLOCK_ID,
+                SAND_BLOCK_ID,
+                DIRT_BLOCK_ID,
+                GRASS_BLOCK_ID,
+                STONE_BLOCK_ID,
+            ],
+        )?;
+        let base = Grid {
+            width: heights.width(),
+            height: heights.height(),
+            values: heights
+                .pixels()
+                .map(|Luma([value])| *value as f64 / u16::MAX as f64)
+                .collect(),
+        };
+        let mut levels = vec![base];
+        while let Some(last) = levels.last()
+            && (last.width > 1 || last.height > 1)
+        {
+            let next = last.downsample();
+            levels.push(next);
+        }
+
+        let materials = materials
+            .map(|materials| {
+                if materials.dimensions() != heights.dimensions() {
+                    return Err(HeightmapError::MismatchedMaterialMap {
+                        heightmap: heights.dimensions().into(),
+                        material_map: materials.dimensions().into(),
+                    });
+                }
+                material_grid(materials, palette)
+            })
+            .transpose()?;
+
+        Ok(Self {
+            levels,
+            materials,
+            horizontal_scale: settings.horizontal_scale,
+            vertical_scale: settings.vertical_scale,
+            base_height: settings.base_height,
+            edges: settings.edges,
+
+            water,
+            gravel,
+            sand,
+            dirt,
+            grass,
+            stone,
+        })
+    }
+
+    pub fn pipeline(self) -> Pipeline {
+        let wgen = Arc::new(self);
+        let shape = Arc::clone(&wgen);
+        Pipeline::default()
+            .with_stage(
+                StageKind::Shape,
+                move |ctx: &mut ChunkContext, _: &mut Array3Chunk| shape.shape(ctx),
+            )
+            .with_stage(
+                StageKind::Surface,
+                move |ctx: &mut ChunkContext, chunk: &mut Array3Chunk| wgen.surface(ctx, chunk),
+            )
+    }
+
+    /// Converts world coordinates to pixels from the top left of the full resolution image.
+    fn to_pixels(&self, wx: f64, wz: f64) -> [f64; 2] {
+        let base = &self.levels[0];
+        [
+            wx / self.horizontal_scale + base.width as f64 / 2.,
+            wz / self.horizontal_scale + base.height as f64 / 2.,
+        ]
+    }
+
+    /// Picks the level whose pixels are closest to the size of a block, so that zoomed out views
+    /// are averaged rather than aliased.
+    fn level_for(&self, zoom: f64) -> usize {
+        let pixels_per_block = 1. / (zoom * self.horizontal_scale);
+        let level = pixels_per_block.log2().floor().max(0.) as usize;
+        level.min(self.levels.len() - 1)
+    }
+
+    fn height_at(&self, level: usize, wx: f64, wz: f64) -> f64 {
+        let [u, v] = self.to_pixels(wx, wz);
+        let scale = (1u64 << level) as f64;
+        let value = self.levels[level].sample([u / scale, v / scale], self.edges);
+        value.mul_add(self.vertical_scale, self.base_height)
+    }
+
+    fn material_at(&self, wx: f64, wz: f64) -> Option<MappedBlockID> {
+        let materials = self.materials.as_ref()?;
+        let [u, v] = self.to_pixels(wx, wz);
+        Some(materials.get(u.floor() as i64, v.floor() as i64, self.edges))
+    }
+
+    fn shape(&self, ctx: &mut ChunkContext) -> ControlFlow<()> {
+        let [_, bottom, _] = ctx.world_coords([0, 0, 0]);
+        let [_, top, _] = ctx.world_coords([0, CHUNK_SIZE - 1, 0]);
+        if top < self.base_height - FLOOR_DEPTH {
+            return ControlFlow::Break(());
+        }
+        let level = self.level_for(ctx.zoom());
+        let mut is_empty = true;
+        for (x, z) in ChunkContext::column_positions() {
+            let [wx, _, wz] = ctx.world_coords([x, 0, z]);
+            let height = self.height_at(level, wx, wz);
+            if bottom <= height || bottom <= SEA_LEVEL {
+                is_empty = false;
+            }
+            *ctx.column_mut(x, z) = Column {
+                height,
+                water_level: SEA_LEVEL,
+                ..Default::default()
+            };
+        }
+        if is_empty {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+
+    fn surface(&self, ctx: &ChunkContext, chunk: &mut Array3Chunk) -> ControlFlow<()> {
+        let floor = self.base_height - FLOOR_DEPTH;
+        let block_size = 1. / ctx.zoom();
+        let mut is_empty = true;
+        for (x, z) in ChunkContext::column_positions() {
+            let column = *ctx.column(x, z);
+            let [wx, _, wz] = ctx.world_coords([x, 0, z]);
+            let underwater = column.height < column.water_level;
+            let top = self.material_at(wx, wz).unwrap_or(if underwater {
+                self.gravel
+            } else if column.height < column.water_level + block_size {
+                self.sand
+            } else {
+                self.grass
+            });
+            for y in 0..CHUNK_SIZE {
+                let [_, wy, _] = ctx.world_coords([x, y, z]);
+                if wy < floor {
+                    continue;
+                }
+                let block = if wy <= column.height {
+                    let depth = column.height - wy;
+                    if depth < block_size {
+                        top
+                    } else if depth < TOPSOIL_DEPTH.max(block_size * 2.) {
+                        if underwater { self.gravel } else { self.dirt }
+                    } else {
+                        self.stone
+                    }
+                } else if wy <= column.water_level {
+                    self.water
+                } else {
+                    continue;
+                };
+                is_empty = false;
+                chunk.insert(&BlockPosition { x, y, z }, block);
+            }
+        }
+        if is_empty {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+}
+
+/// Maps each pixel to the opaque block whose definition has the closest colour.
+fn material_grid(
+    materials: &RgbImage,
+    palette: &Palette,
+) -> Result<Grid<MappedBlockID>, HeightmapError> {
+    let mut candidates: Vec<_> = palette
+        .opaque_colors()
+        .map(|(mapped_id, color)| (color, mapped_id))
+        .collect();
+    if candidates.is_empty() {
+        return Err(HeightmapError::NoMaterials);
+    }
+    // ties go to the lowest ID, rather than whichever the palette happens to yield first
+    candidates.sort_by_key(|(_, mapped_id)| *mapped_id);
+    let mut closest = AHashMap::new();
+    let values = materials
+        .pixels()
+        .map(|pixel| {
+            *closest.entry(pixel.0).or_insert_with(|| {
+                candidates
+                    .iter()
+                    .min_by_key(|([r, g, b, _], _)| {
+                        [*r, *g, *b]
+                            .into_iter()
+                            .zip(pixel.0)
+                            .map(|(a, b)| (a as i32 - b as i32).pow(2))
+                            .sum::<i32>()
+                    })
+                    .map(|(_, mapped_id)| *mapped_id)
+                    .unwrap()
+            })
+        })
+        .collect();
+    Ok(Grid {
+        width: materials.width(),
+        height: materials.height(),
+        values,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgb;
+    use infinigen_common::blocks::{BlockType, BlockVisibility};
+
+    use super::*;
+    use crate::blocks::{block_types, test_palette};
+
+    fn settings(edges: HeightmapEdges) -> HeightmapSettings {
+        HeightmapSettings {
+            path: String::new(),
+            material_map: None,
+            horizontal_scale: 1.,
+            vertical_scale: 100.,
+            base_height: 0.,
+            edges,
+        }
+    }
+
+    fn gradient() -> HeightImage {
+        ImageBuffer::from_fn(2, 1, |x, _| Luma([if x == 0 { 0 } else { u16::MAX }]))
+    }
+
+    #[test]
+    fn interpolates_between_pixels() {
+        let wgen = HeightmapImage::new(
+            &settings(HeightmapEdges::Clamp),
+            &gradient(),
+            None,
+            &test_palette(),
+        )
+        .unwrap();
+        // the image spans x in [-1, 1), with pixel centres at -0.5 and 0.5
+        assert_eq!(wgen.height_at(0, -0.5, 0.), 0.);
+        assert_eq!(wgen.height_at(0, 0., 0.), 50.);
+        assert_eq!(wgen.height_at(0, 0.5, 0.), 100.);
+    }
+
+    #[test]
+    fn edges_clamp_or_tile() {
+        let clamped = HeightmapImage::new(
+            &settings(HeightmapEdges::Clamp),
+            &gradient(),
+            None,
+            &test_palette(),
+        )
+        .unwrap();
+        let tiled = HeightmapImage::new(
+            &settings(HeightmapEdges::Tile),
+            &gradient(),
+            None,
+            &test_palette(),
+        )
+        .unwrap();
+        assert_eq!(clamped.height_at(0, 1., 0.), 100.);
+        assert_eq!(clamped.height_at(0, 100.5, 0.), 100.);
+        assert_eq!(tiled.height_at(0, 1., 0.), 50.);
+        assert_eq!(tiled.height_at(0, 2.5, 0.), tiled.height_at(0, 0.5, 0.));
+    }
+
+    #[test]
+    fn zooming_out_averages_pixels() {
+        let checkerboard: HeightImage = ImageBuffer::from_fn(4, 4, |x, z| {
+            Luma([if (x + z) % 2 == 0 { 0 } else { u16::MAX }])
+        });
+        let wgen = HeightmapImage::new(
+            &settings(HeightmapEdges::Clamp),
+            &checkerboard,
+            None,
+            &test_palette(),
+        )
+        .unwrap();
+        assert_eq!(wgen.levels.len(), 3);
+        assert_eq!(wgen.level_for(1.), 0);
+        assert_eq!(wgen.level_for(0.5), 1);
+        assert_eq!(wgen.level_for(1. / 64.), 2);
+        assert_eq!(wgen.height_at(1, 0., 0.), 50.);
+    }
+
+    #[test]
+    fn material_map_picks_closest_colour() {
+        let palette = test_palette();
+        let stone = *palette.inner.get(STONE_BLOCK_ID).unwrap();
+        let sand = *palette.inner.get(SAND_BLOCK_ID).unwrap();
+        let materials: RgbImage = ImageBuffer::from_fn(2, 1, |x, _| {
+            if x == 0 {
+                Rgb([120, 130, 125])
+            } else {
+                Rgb([200, 170, 120])
+            }
+        });
+        let wgen = HeightmapImage::new(
+            &settings(HeightmapEdges::Clamp),
+            &gradient(),
+            Some(&materials),
+            &palette,
+        )
+        .unwrap();
+        assert_eq!(wgen.material_at(-0.5, 0.), Some(stone));
+        assert_eq!(wgen.material_at(0.5, 0.), Some(sand));
+
+        // colours come from the definitions, which resource packs may change
+        let mut repainted = Palette::default();
+        for block_type in block_types() {
+            let color = match block_type.id.as_str() {
+                SAND_BLOCK_ID => [120, 130, 125, 255],
+                STONE_BLOCK_ID => [200, 170, 120, 255],
+                _ => block_type.color,
+            };
+            let mapped_id = *palette.inner.get(&block_type.id).unwrap();
+            repainted.insert(
+                &BlockType {
+                    color,
+                    ..block_type
+                },
+                mapped_id,
+            );
+        }
+        let wgen = HeightmapImage::new(
+            &settings(HeightmapEdges::Clamp),
+            &gradient(),
+            Some(&materials),
+            &repainted,
+        )
+        .unwrap();
+        assert_eq!(wgen.material_at(-0.5, 0.), Some(sand));
+        assert_eq!(wgen.material_at(0.5, 0.), Some(stone));
+
+        let mismatched: RgbImage = ImageBuffer::new(3, 3);
+        assert!(matches!(
+            HeightmapImage::new(
+                &settings(HeightmapEdges::Clamp),
+                &gradient(),
+                Some(&mismatched),
+                &test_palette(),
+            ),
+            Err(WorldGenError::Heightmap(
+                HeightmapError::MismatchedMaterialMap { .. }
+            ))
+        ));
+
+        let mut transparent = Palette::default();
+        for block_type in block_types() {
+            let mapped_id = *palette.inner.get(&block_type.id).unwrap();
+            transparent.insert(
+                &BlockType {
+                    visibility: BlockVisibility::Translucent,
+                    ..block_type
+                },
+                mapped_id,
+            );
+        }
+        assert!(matches!(
+            HeightmapImage::new(
+                &settings(HeightmapEdges::Clamp),
+                &gradient(),
+                Some(&materials),
+                &transparent,
+            ),
+            Err(WorldGenError::Heightmap(HeightmapError::NoMaterials))
+        ));
+    }
+}
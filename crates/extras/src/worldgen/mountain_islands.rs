@@ -1,12 +1,14 @@
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
 use infinigen_common::blocks::Palette;
 use infinigen_common::chunks::{Array3Chunk, CHUNK_SIZE, CHUNK_SIZE_F64, CHUNK_USIZE};
-use infinigen_common::noise::{Fbm, Perlin};
-use infinigen_common::world::{
-    BlockPosition, ChunkPosition, MappedBlockID, WorldGen, WorldPosition,
-};
-use infinigen_common::zoom::ZoomLevel;
+use infinigen_common::noise::{Fbm, OpenSimplex2, Perlin, derive_seed, splitmix64};
+use infinigen_common::world::pipeline::{ChunkContext, Column, Pipeline, StageKind};
+use infinigen_common::world::{BlockPosition, MappedBlockID};
 use splines::{Interpolation, Key, Spline};
 
+use super::{WorldGenError, required_blocks};
 use crate::blocks::{
     DIRT_BLOCK_ID, GRASS_BLOCK_ID, GRAVEL_BLOCK_ID, SAND_BLOCK_ID, SNOW_BLOCK_ID, STONE_BLOCK_ID,
     WATER_BLOCK_ID,
@@ -14,10 +16,17 @@ use crate::blocks::{
 
 #[derive(Debug, Clone)]
 pub struct MountainIslands {
-    /// The world height at any given (x, z)
-    heightmap: Fbm,
+    seed: u64,
+    /// The world height at any given (x, z), from simplex noise to avoid grid-aligned ridges
+    heightmap: Fbm<OpenSimplex2>,
+    /// Low-octave version of `heightmap`, so river surfaces stay level across a channel.
+    river_heightmap: Fbm<OpenSimplex2>,
+    /// Rivers follow the zero crossings of this noise.
+    rivers: Fbm,
     verticality: Perlin,
     terrain_variance: Fbm,
+    /// How dry the grass and leaves of each column look, along with altitude.
+    dryness: Fbm,
     vspline: Spline<f64, f64>,
     /// max mountain size without zoom is roughly double this value
     vertical_scale: f64,
@@ -30,10 +39,24 @@ pub struct MountainIslands {
     dirt: MappedBlockID,
     grass: MappedBlockID,
     stone: MappedBlockID,
+    /// Blocks with tinted faces, so that only chunks with them keep column tints.
+    tinted: Vec<MappedBlockID>,
 }
 
 impl MountainIslands {
-    pub fn new(seed: u32, palette: Palette) -> Self {
+    pub fn new(seed: u64, palette: &Palette) -> Result<Self, WorldGenError> {
+        let [water, snow, gravel, sand, dirt, grass, stone] = required_blocks(
+            palette,
+            [
+                WATER_BLOCK_ID,
+                SNOW_BLOCK_ID,
+                GRAVEL_BLOCK_ID,
+                SAND_BLOCK_ID,
+                DIRT_BLOCK_ID,
+                GRASS_BLOCK_ID,
+                STONE_BLOCK_ID,
+            ],
+        )?;
         let vspline = Spline::from_vec(vec![
             Key::new(-1., 0.6, Interpolation::Cosine),
             Key::new(-0.9, 0.7, Interpolation::Cosine),
@@ -45,48 +68,323 @@ impl MountainIslands {
         ]);
 
         let wgen = Self {
-            heightmap: default_heightmap(seed),
-            verticality: Perlin::new(seed),
-            terrain_variance: default_terrain_variance(seed),
+            seed,
+            heightmap: default_heightmap(derive_seed(seed, "heightmap")),
+            river_heightmap: default_heightmap(derive_seed(seed, "heightmap")).set_octaves(2),
+            rivers: Fbm::new(derive_seed(seed, "rivers")).set_octaves(3),
+            verticality: Perlin::new(derive_seed(seed, "verticality")),
+            terrain_variance: default_terrain_variance(derive_seed(seed, "terrain_variance")),
+            dryness: Fbm::new(derive_seed(seed, "dryness")).set_octaves(3),
             vspline,
             vertical_scale: CHUNK_SIZE_F64 * 4.,
             horizontal_smoothness: CHUNK_SIZE_F64 * 0.1,
 
-            water: *palette.inner.get(WATER_BLOCK_ID).unwrap(),
-            snow: *palette.inner.get(SNOW_BLOCK_ID).unwrap(),
-            gravel: *palette.inner.get(GRAVEL_BLOCK_ID).unwrap(),
-            sand: *palette.inner.get(SAND_BLOCK_ID).unwrap(),
-            dirt: *palette.inner.get(DIRT_BLOCK_ID).unwrap(),
-            grass: *palette.inner.get(GRASS_BLOCK_ID).unwrap(),
-            stone: *palette.inner.get(STONE_BLOCK_ID).unwrap(),
+            water,
+            snow,
+            gravel,
+            sand,
+            dirt,
+            grass,
+            stone,
+            tinted: palette.tinted().collect(),
         };
         tracing::debug!(?wgen.heightmap.octaves, wgen.heightmap.frequency, wgen.heightmap.lacunarity, wgen.heightmap.persistence, "MountainIslands initialized");
-        wgen
+        Ok(wgen)
+    }
+
+    fn noise_coord(&self, w: f64) -> f64 {
+        w / (self.horizontal_smoothness * self.vertical_scale)
+    }
+
+    fn noise_coords(&self, wx: f64, wz: f64) -> (f64, f64) {
+        (self.noise_coord(wx), self.noise_coord(wz))
+    }
+
+    /// Multiplier applied to raw heightmap noise, given the `verticality` noise at the same point.
+    fn height_scale(&self, verticality: f64) -> f64 {
+        self.vertical_scale * self.vspline.clamped_sample(verticality).unwrap()
+    }
+
+    /// Noise samples for every column of a chunk, indexed `[x][z]`.
+    fn chunk_noise(&self, ctx: &ChunkContext) -> [[ColumnNoise; CHUNK_USIZE]; CHUNK_USIZE] {
+        let nxs: [f64; CHUNK_USIZE] =
+            std::array::from_fn(|x| self.noise_coord(ctx.world_coords([x as u8, 0, 0])[0]));
+        let nzs: [f64; CHUNK_USIZE] =
+            std::array::from_fn(|z| self.noise_coord(ctx.world_coords([0, 0, z as u8])[2]));
+        let heights = self.heightmap.get_grid(&nxs, &nzs);
+        let verticalities = self.verticality.get_grid(&nxs, &nzs);
+        let rivers = self.rivers.get_grid(
+            &nxs.map(|nx| nx * RIVER_FREQUENCY),
+            &nzs.map(|nz| nz * RIVER_FREQUENCY),
+        );
+        let drynesses = self.dryness.get_grid(
+            &nxs.map(|nx| nx * DRYNESS_FREQUENCY),
+            &nzs.map(|nz| nz * DRYNESS_FREQUENCY),
+        );
+        std::array::from_fn(|x| {
+            std::array::from_fn(|z| ColumnNoise {
+                height: heights[x][z],
+                verticality: verticalities[x][z],
+                river: rivers[x][z],
+                dryness: drynesses[x][z],
+            })
+        })
+    }
+
+    fn column_from_noise(
+        &self,
+        noise: ColumnNoise,
+        wx: f64,
+        wz: f64,
+        lakes: &mut LakeCache,
+    ) -> Column {
+        let height_scale = self.height_scale(noise.verticality);
+        let mut column = Column {
+            height: noise.height * height_scale,
+            water_level: SEA_LEVEL,
+            ..Default::default()
+        };
+        let (nx, nz) = self.noise_coords(wx, wz);
+        self.carve_river(&mut column, noise.river.abs(), nx, nz, height_scale);
+        if let Some(lake) = lakes.get(self, wx, wz) {
+            lake.carve(&mut column, wx, wz);
+        }
+        column.tint = Some(tint(noise.dryness, column.height));
+        column
     }
+
+    /// Carves a valley and water-filled channel along the zero crossings of `rivers`, given the
+    /// `distance` from one in units of river noise.
+    fn carve_river(&self, column: &mut Column, distance: f64, nx: f64, nz: f64, height_scale: f64) {
+        if distance >= RIVER_VALLEY_WIDTH {
+            return;
+        }
+        let surface = (self.river_heightmap.get([nx, nz]) * height_scale)
+            .max(0.)
+            .mul_add(RIVER_SURFACE_FLATTEN, SEA_LEVEL)
+            .floor();
+        if column.height < surface {
+            // don't flood ground that is already lower than the river
+            return;
+        }
+        let valley = smoothstep(
+            (RIVER_VALLEY_WIDTH - distance) / (RIVER_VALLEY_WIDTH - RIVER_CHANNEL_WIDTH),
+        );
+        column.height = lerp(column.height, surface, valley);
+        if distance < RIVER_CHANNEL_WIDTH {
+            let bed = RIVER_DEPTH.mul_add(-(1. - distance / RIVER_CHANNEL_WIDTH), surface);
+            column.height = column.height.min(bed);
+            column.water_level = column.water_level.max(surface);
+        }
+    }
+
+    /// Returns the lake in `cell`, if it has one.
+    fn lake(&self, cell: [i64; 2]) -> Option<Lake> {
+        let mut state = derive_seed(self.seed, "lakes");
+        state = splitmix64(&mut state) ^ cell[0] as u64;
+        state = splitmix64(&mut state) ^ cell[1] as u64;
+        let mut unit = || (splitmix64(&mut state) >> 11) as f64 / (1u64 << 53) as f64;
+
+        if unit() >= LAKE_CHANCE {
+            return None;
+        }
+        let centre = [
+            (unit() - 0.5).mul_add(LAKE_JITTER, cell[0] as f64 + 0.5) * LAKE_CELL_SIZE,
+            (unit() - 0.5).mul_add(LAKE_JITTER, cell[1] as f64 + 0.5) * LAKE_CELL_SIZE,
+        ];
+        let radius = lerp(LAKE_MIN_RADIUS, LAKE_MAX_RADIUS, unit());
+
+        let (nx, nz) = self.noise_coords(centre[0], centre[1]);
+        let level = self
+            .heightmap
+            .get([nx, nz])
+            .mul_add(
+                self.height_scale(self.verticality.get([nx, nz])),
+                -LAKE_SINK,
+            )
+            .floor();
+        if level < SEA_LEVEL + LAKE_MIN_ALTITUDE {
+            // too close to the sea to be worth a separate body of water
+            return None;
+        }
+        Some(Lake {
+            centre,
+            radius,
+            level,
+            depth: radius * LAKE_DEPTH_RATIO,
+        })
+    }
+}
+
+/// Raw noise samples which shape a single column.
+#[derive(Debug, Clone, Copy)]
+struct ColumnNoise {
+    height: f64,
+    verticality: f64,
+    river: f64,
+    dryness: f64,
 }
 
-fn default_heightmap(seed: u32) -> Fbm {
-    Fbm::new(seed).set_octaves(6)
+/// A round lake basin, which always lies within a single [`LAKE_CELL_SIZE`] cell.
+#[derive(Debug, Clone, Copy)]
+struct Lake {
+    centre: [f64; 2],
+    radius: f64,
+    level: f64,
+    depth: f64,
 }
 
-pub fn default_terrain_variance(seed: u32) -> Fbm {
+impl Lake {
+    fn carve(&self, column: &mut Column, wx: f64, wz: f64) {
+        let q = (wx - self.centre[0]).hypot(wz - self.centre[1]) / self.radius;
+        if q >= 1. + LAKE_SHORE_WIDTH {
+            return;
+        }
+        if q < 1. {
+            let bed = self.depth.mul_add(-q.mul_add(-q, 1.), self.level);
+            column.height = column.height.min(bed);
+            // rivers flowing in settle at the lake's level
+            column.water_level = self.level;
+        } else if column.height > self.level {
+            // slope higher ground down to the shoreline
+            let shore = smoothstep((1. + LAKE_SHORE_WIDTH - q) / LAKE_SHORE_WIDTH);
+            column.height = lerp(column.height, self.level, shore);
+        } else {
+            // raise lower ground up to it, so that the shore holds the water in
+            column.height = self.level;
+        }
+    }
+}
+
+/// Lakes of the cells touched by a single chunk, so each is only computed once.
+#[derive(Debug, Default)]
+struct LakeCache {
+    cells: Vec<([i64; 2], Option<Lake>)>,
+}
+
+impl LakeCache {
+    fn get(&mut self, wgen: &MountainIslands, wx: f64, wz: f64) -> Option<Lake> {
+        let cell = [
+            (wx / LAKE_CELL_SIZE).floor() as i64,
+            (wz / LAKE_CELL_SIZE).floor() as i64,
+        ];
+        if let Some((_, lake)) = self.cells.iter().find(|(c, _)| *c == cell) {
+            return *lake;
+        }
+        let lake = wgen.lake(cell);
+        self.cells.push((cell, lake));
+        lake
+    }
+}
+
+/// Tint of grass and leaves given `dryness` noise, which gets drier higher up.
+fn tint(dryness: f64, height: f64) -> [u8; 3] {
+    let t = smoothstep(0.5 + dryness + (height - SEA_LEVEL) / DRY_ALTITUDE);
+    std::array::from_fn(|i| lerp(LUSH_TINT[i] as f64, DRY_TINT[i] as f64, t).round() as u8)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    t.mul_add(b - a, a)
+}
+
+fn smoothstep(t: f64) -> f64 {
+    let t = t.clamp(0., 1.);
+    t * t * 2.0f64.mul_add(-t, 3.)
+}
+
+fn default_heightmap(seed: u64) -> Fbm<OpenSimplex2> {
+    Fbm::from_seed(seed).set_octaves(6)
+}
+
+pub fn default_terrain_variance(seed: u64) -> Fbm {
     Fbm::new(seed).set_octaves(8).set_persistence(0.7)
 }
 
 const SEA_LEVEL: f64 = 0.;
 
+/// Relative to the heightmap, so river networks are much sparser than mountains.
+const RIVER_FREQUENCY: f64 = 0.5;
+/// Half-widths in units of river noise, where 0.01 is roughly 5 blocks.
+const RIVER_CHANNEL_WIDTH: f64 = 0.02;
+const RIVER_VALLEY_WIDTH: f64 = 0.08;
+const RIVER_DEPTH: f64 = 6.;
+/// How much of the surrounding terrain's (smoothed) height a river surface keeps.
+const RIVER_SURFACE_FLATTEN: f64 = 0.5;
+
+const LAKE_CELL_SIZE: f64 = 768.;
+/// Probability that any one cell contains a lake.
+const LAKE_CHANCE: f64 = 0.35;
+/// Fraction of a cell the lake centre may drift from the middle of it.
+const LAKE_JITTER: f64 = 0.2;
+const LAKE_MIN_RADIUS: f64 = 48.;
+const LAKE_MAX_RADIUS: f64 = 160.;
+/// Shoreline slope width, as a fraction of the lake radius.
+const LAKE_SHORE_WIDTH: f64 = 0.6;
+const LAKE_DEPTH_RATIO: f64 = 0.12;
+/// How far below the ground at its centre a lake's surface sits.
+const LAKE_SINK: f64 = 2.;
+const LAKE_MIN_ALTITUDE: f64 = 4.;
+
+/// Relative to the heightmap, so that dry and lush areas span several mountains.
+const DRYNESS_FREQUENCY: f64 = 0.5;
+/// Height above sea level that makes grass and leaves as much drier as the whole range of
+/// dryness noise does.
+const DRY_ALTITUDE: f64 = 400.;
+/// Textures are already green, so tints only shift them slightly.
+const LUSH_TINT: [u8; 3] = [225, 255, 215];
+const DRY_TINT: [u8; 3] = [255, 230, 150];
+
 // we still bound the worldgen on the Y axis to improve performance
 // for an infinitely deep world, we would not have a MIN_Y_HEIGHT maybe
 const MIN_Y_HEIGHT: i32 = -6;
 
 /// Based on <https://www.youtube.com/watch?v=CSa5O6knuwI>
-impl WorldGen for MountainIslands {
-    fn get(&self, pos: &ChunkPosition, zoom_level: ZoomLevel) -> Option<Array3Chunk> {
-        if pos.y < MIN_Y_HEIGHT {
-            return None;
+impl MountainIslands {
+    pub fn pipeline(self) -> Pipeline {
+        let wgen = Arc::new(self);
+        let shape = Arc::clone(&wgen);
+        Pipeline::default()
+            .with_tinted_blocks(wgen.tinted.iter().copied())
+            .with_stage(
+                StageKind::Shape,
+                move |ctx: &mut ChunkContext, _: &mut Array3Chunk| shape.shape(ctx),
+            )
+            .with_stage(
+                StageKind::Surface,
+                move |ctx: &mut ChunkContext, chunk: &mut Array3Chunk| wgen.surface(ctx, chunk),
+            )
+    }
+
+    /// Computes the height and water level of every column.
+    fn shape(&self, ctx: &mut ChunkContext) -> ControlFlow<()> {
+        if ctx.position.y < MIN_Y_HEIGHT {
+            return ControlFlow::Break(());
+        }
+        let mut lakes = LakeCache::default();
+        let noise = self.chunk_noise(ctx);
+        let mut is_empty = true;
+        for (x, z) in ChunkContext::column_positions() {
+            let [wx, wy, wz] = ctx.world_coords([x, 0, z]);
+            let column = self.column_from_noise(noise[x as usize][z as usize], wx, wz, &mut lakes);
+
+            // short circuit if bottom-most layer (y=0) is empty as this world doesn't have things in the sky
+            if wy <= column.height || wy <= column.water_level {
+                is_empty = false;
+            }
+
+            *ctx.column_mut(x, z) = column;
+        }
+        if is_empty {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
         }
-        let zoom = zoom_level.as_f64();
-        let sand_level = (SEA_LEVEL + (1. / zoom)).floor();
+    }
+
+    /// Fills every column up to its height, with bands of blocks depending on altitude.
+    fn surface(&self, ctx: &ChunkContext, chunk: &mut Array3Chunk) -> ControlFlow<()> {
+        let _span = tracing::debug_span!("worldgen{stage = terrain}").entered();
+        let zoom = ctx.zoom();
         // let snow_level: f64 = (SEA_LEVEL + self.vertical_scale) * zoom;
 
         let block_ranges = [
@@ -97,105 +395,185 @@ impl WorldGen for MountainIslands {
             (f64::INFINITY, self.snow),
         ];
 
-        let mut chunk = Array3Chunk::default();
-        let offset: WorldPosition = pos.into();
-        let zoomed_offset = [
-            offset.x as f64 / zoom,
-            offset.y as f64 / zoom,
-            offset.z as f64 / zoom,
-        ];
+        let mut terrain_variances = [[None::<f64>; CHUNK_USIZE]; CHUNK_USIZE];
+        let mut is_empty = true;
 
-        // needed for every column
-        let mut world_heights = [[0.; CHUNK_USIZE]; CHUNK_USIZE];
-        let mut nxzs = [[(0., 0.); CHUNK_USIZE]; CHUNK_USIZE];
+        for (x, z) in ChunkContext::column_positions() {
+            let Column {
+                height: world_height,
+                water_level,
+                ..
+            } = *ctx.column(x, z);
+            let sand_level = (water_level + (1. / zoom)).floor();
+            for y in 0..CHUNK_SIZE {
+                let [wx, wy, wz] = ctx.world_coords([x, y, z]);
 
-        let mut is_empty = true;
+                // world_height is sunken, so we're in a body of water
+                if world_height <= wy && wy <= water_level {
+                    is_empty = false;
+                    chunk.insert(&BlockPosition { x, y, z }, self.water);
+                    continue;
+                }
 
-        for x in 0..CHUNK_SIZE {
-            for z in 0..CHUNK_SIZE {
-                let wx = x as f64 / zoom + zoomed_offset[0];
-                let wz = z as f64 / zoom + zoomed_offset[2];
+                // ensure we fill blocks up to the world_height
+                if wy <= world_height {
+                    is_empty = false;
+                    if wy < water_level {
+                        // always gravel under water
+                        chunk.insert(&BlockPosition { x, y, z }, self.gravel);
+                        continue;
+                    } else if wy.floor() <= sand_level {
+                        // sand always borders water
+                        chunk.insert(&BlockPosition { x, y, z }, self.sand);
+                        continue;
+                    }
 
-                let nx = wx / (self.horizontal_smoothness * self.vertical_scale);
-                let nz = wz / (self.horizontal_smoothness * self.vertical_scale);
+                    let next_band_chance = {
+                        let slot = &mut terrain_variances[x as usize][z as usize];
+                        let value = slot.get_or_insert_with(|| {
+                            let (nx, nz) = self.noise_coords(wx, wz);
+                            self.terrain_variance.get([nx, nz]) / 2.0
+                        });
+                        *value
+                    };
 
-                let mut world_height = self.heightmap.get([nx, nz]);
-                let verticality = self.verticality.get([nx, nz]);
-                world_height *= self.vertical_scale * self.vspline.sample(verticality).unwrap();
+                    // Assign block type based on the height and noise.
+                    let mut block_id = block_ranges[0].1;
+                    for &(threshold, id) in &block_ranges {
+                        if next_band_chance.mul_add(self.vertical_scale, wy) < threshold {
+                            block_id = id;
+                            break;
+                        }
+                    }
 
-                // short circuit if bottom-most layer (y=0) is empty as this world doesn't have things in the sky
-                let wy = zoomed_offset[1];
-                if wy <= world_height || wy <= SEA_LEVEL {
-                    is_empty = false;
+                    chunk.insert(&BlockPosition { x, y, z }, block_id);
                 }
-
-                world_heights[x as usize][z as usize] = world_height;
-                nxzs[x as usize][z as usize] = (nx, nz);
             }
         }
+
         if is_empty {
-            return None;
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
         }
+    }
+}
 
-        let mut terrain_variances = [[None::<f64>; CHUNK_USIZE]; CHUNK_USIZE];
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::test_palette;
 
-        {
-            let _span = tracing::debug_span!("worldgen{stage = terrain}").entered();
-            for x in 0..CHUNK_SIZE {
-                for z in 0..CHUNK_SIZE {
-                    let world_height = world_heights[x as usize][z as usize];
-                    for y in 0..CHUNK_SIZE {
-                        let wy = y as f64 / zoom + zoomed_offset[1];
-
-                        // world_height is sunken, so we're in a body of water
-                        if world_height <= wy && wy <= SEA_LEVEL {
-                            is_empty = false;
-                            chunk.insert(&BlockPosition { x, y, z }, self.water);
-                            continue;
-                        }
+    impl MountainIslands {
+        /// Terrain height, water surface and tint of the column at world (wx, wz), after carving,
+        /// sampling its noise on its own rather than for a whole chunk.
+        fn column(&self, wx: f64, wz: f64, lakes: &mut LakeCache) -> Column {
+            let (nx, nz) = self.noise_coords(wx, wz);
+            let noise = ColumnNoise {
+                height: self.heightmap.get([nx, nz]),
+                verticality: self.verticality.get([nx, nz]),
+                river: self
+                    .rivers
+                    .get([nx * RIVER_FREQUENCY, nz * RIVER_FREQUENCY]),
+                dryness: self
+                    .dryness
+                    .get([nx * DRYNESS_FREQUENCY, nz * DRYNESS_FREQUENCY]),
+            };
+            self.column_from_noise(noise, wx, wz, lakes)
+        }
+    }
 
-                        // ensure we fill blocks up to the world_height
-                        if wy <= world_height {
-                            is_empty = false;
-                            if wy < SEA_LEVEL {
-                                // always gravel under sea
-                                chunk.insert(&BlockPosition { x, y, z }, self.gravel);
-                                continue;
-                            } else if wy.floor() <= sand_level {
-                                // sand always borders water
-                                chunk.insert(&BlockPosition { x, y, z }, self.sand);
-                                continue;
-                            }
-
-                            let next_band_chance = {
-                                let slot = &mut terrain_variances[x as usize][z as usize];
-                                let value = slot.get_or_insert_with(|| {
-                                    let (nx, nz) = nxzs[x as usize][z as usize];
-                                    self.terrain_variance.get([nx, nz]) / 2.0
-                                });
-                                *value
-                            };
-
-                            // Assign block type based on the height and noise.
-                            let mut block_id = block_ranges[0].1;
-                            for &(threshold, id) in &block_ranges {
-                                if next_band_chance.mul_add(self.vertical_scale, wy) < threshold {
-                                    block_id = id;
-                                    break;
-                                }
-                            }
-
-                            chunk.insert(&BlockPosition { x, y, z }, block_id);
-                        }
+    #[test]
+    fn water_never_below_sea_level() {
+        let wgen = MountainIslands::new(0, &test_palette()).unwrap();
+        let mut lakes = LakeCache::default();
+        for x in -40..40 {
+            for z in -40..40 {
+                let column = wgen.column(x as f64 * 37., z as f64 * 37., &mut lakes);
+                assert!(column.water_level >= SEA_LEVEL);
+            }
+        }
+    }
+
+    #[test]
+    fn has_inland_water_above_sea_level() {
+        let wgen = MountainIslands::new(0, &test_palette()).unwrap();
+        let mut lakes = LakeCache::default();
+        let inland = (-200..200)
+            .flat_map(|x| (-200..200).map(move |z| (x as f64 * 16., z as f64 * 16.)))
+            .map(|(wx, wz)| wgen.column(wx, wz, &mut lakes))
+            .filter(|column| column.water_level > SEA_LEVEL && column.height < column.water_level)
+            .count();
+        assert!(inland > 0, "expected some rivers or lakes above sea level");
+    }
+
+    #[test]
+    fn tints_dry_out_with_altitude() {
+        assert_eq!(tint(-1., SEA_LEVEL), LUSH_TINT);
+        assert_eq!(tint(1., SEA_LEVEL), DRY_TINT);
+        let [_, lowland, _] = tint(0., SEA_LEVEL);
+        let [_, highland, _] = tint(0., SEA_LEVEL + 100.);
+        assert!(highland < lowland);
+
+        let wgen = MountainIslands::new(0, &test_palette()).unwrap();
+        let column = wgen.column(0., 0., &mut LakeCache::default());
+        assert!(column.tint.is_some());
+    }
+
+    #[test]
+    fn lakes_are_held_in_by_their_shores() {
+        let wgen = MountainIslands::new(7, &test_palette()).unwrap();
+        let lakes = (-10..10)
+            .flat_map(|cx| (-10..10).map(move |cz| [cx, cz]))
+            .filter_map(|cell| wgen.lake(cell))
+            .take(4);
+        for lake in lakes {
+            let mut cache = LakeCache::default();
+            let mut column = |wx: i64, wz: i64| wgen.column(wx as f64, wz as f64, &mut cache);
+            let q = |wx: i64, wz: i64| {
+                (wx as f64 - lake.centre[0]).hypot(wz as f64 - lake.centre[1]) / lake.radius
+            };
+            let [cx, cz] = lake.centre.map(|c| c as i64);
+            let reach = lake.radius as i64 + 1;
+            for wx in cx - reach..=cx + reach {
+                for wz in cz - reach..=cz + reach {
+                    let neighbours = [(wx - 1, wz), (wx + 1, wz), (wx, wz - 1), (wx, wz + 1)];
+                    if q(wx, wz) >= 1. || neighbours.iter().all(|&(x, z)| q(x, z) < 1.) {
+                        continue;
+                    }
+                    // the water's edge
+                    let water_level = column(wx, wz).water_level;
+                    for (x, z) in neighbours {
+                        let neighbour = column(x, z);
+                        assert!(
+                            neighbour.water_level >= water_level || neighbour.height >= water_level,
+                            "lake water at ({wx}, {wz}) stands over {neighbour:?} at ({x}, {z})"
+                        );
                     }
                 }
             }
+        }
+    }
 
-            if is_empty {
-                return None;
+    #[test]
+    fn lakes_are_deterministic_and_within_their_cell() {
+        let a = MountainIslands::new(7, &test_palette()).unwrap();
+        let b = MountainIslands::new(7, &test_palette()).unwrap();
+        for cx in -20..20 {
+            for cz in -20..20 {
+                let (Some(lake), Some(same)) = (a.lake([cx, cz]), b.lake([cx, cz])) else {
+                    assert!(a.lake([cx, cz]).is_none() && b.lake([cx, cz]).is_none());
+                    continue;
+                };
+                assert_eq!(lake.centre, same.centre);
+                assert_eq!(lake.level, same.level);
+                let reach = lake.radius * (1. + LAKE_SHORE_WIDTH);
+                for (axis, c) in [cx, cz].into_iter().enumerate() {
+                    let min = c as f64 * LAKE_CELL_SIZE;
+                    assert!(lake.centre[axis] - reach >= min);
+                    assert!(lake.centre[axis] + reach <= min + LAKE_CELL_SIZE);
+                }
             }
         }
-
-        Some(chunk)
     }
 }
@@ -1,8 +1,11 @@
+use std::ops::ControlFlow;
+
 use infinigen_common::blocks::Palette;
 use infinigen_common::chunks::{Array3Chunk, CHUNK_SIZE};
-use infinigen_common::world::{BlockPosition, ChunkPosition, MappedBlockID, WorldGen};
-use infinigen_common::zoom::ZoomLevel;
+use infinigen_common::world::pipeline::{ChunkContext, Pipeline, Stage, StageKind};
+use infinigen_common::world::{BlockPosition, MappedBlockID};
 
+use super::{WorldGenError, required_blocks};
 use crate::blocks::GRASS_BLOCK_ID;
 
 /// Generates a single block in the middle of every chunk.
@@ -11,19 +14,20 @@ pub struct SingleBlock {
     grass: MappedBlockID,
 }
 
-#[allow(clippy::fallible_impl_from)]
-impl From<Palette> for SingleBlock {
-    fn from(palette: Palette) -> Self {
-        Self {
-            grass: *palette.inner.get(GRASS_BLOCK_ID).unwrap(),
-        }
+impl SingleBlock {
+    pub fn new(palette: &Palette) -> Result<Self, WorldGenError> {
+        let [grass] = required_blocks(palette, [GRASS_BLOCK_ID])?;
+        Ok(Self { grass })
+    }
+
+    pub fn pipeline(self) -> Pipeline {
+        Pipeline::default().with_stage(StageKind::Shape, self)
     }
 }
 
-impl WorldGen for SingleBlock {
-    fn get(&self, _pos: &ChunkPosition, _zoom_level: ZoomLevel) -> Option<Array3Chunk> {
+impl Stage for SingleBlock {
+    fn apply(&self, _ctx: &mut ChunkContext, chunk: &mut Array3Chunk) -> ControlFlow<()> {
         // TODO: implement zoom?
-        let mut chunk = Array3Chunk::default();
         chunk.insert(
             &BlockPosition {
                 x: CHUNK_SIZE / 2,
@@ -32,6 +36,6 @@ impl WorldGen for SingleBlock {
             },
             self.grass,
         );
-        Some(chunk)
+        ControlFlow::Continue(())
     }
 }
@@ -0,0 +1,13 @@
+//! Checks the block definitions and textures that ship with the app, as `validate-assets` would.
+
+use std::path::Path;
+
+use infinigen_plugins::assets::packs::ResourcePacks;
+use infinigen_plugins::assets::validation::validate_dir;
+
+#[test]
+fn bundled_assets_are_valid() {
+    let assets = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+    let issues = validate_dir(&assets, &ResourcePacks::default()).unwrap();
+    assert!(issues.iter().all(|issue| !issue.is_error()), "{issues:#?}");
+}
@@ -0,0 +1,223 @@
+//! Pins the output of the world generators, so that changes to noise, splines or constants don't
+//! silently change every world.
+//!
+//! Each case hashes the chunks generated for a fixed set of positions and zoom levels, and compares
+//! them to the values in `tests/golden/worldgen.txt`. If a change to world generation is
+//! intentional, run with `INFINIGEN_BLESS=1` to overwrite the golden values, and commit the
+//! result.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use ahash::AHashMap;
+use image::{ImageBuffer, Luma};
+use infinigen_common::blocks::{BlockType, Palette};
+use infinigen_common::chunks::{Array3Chunk, CHUNK_SIZE};
+use infinigen_common::world::{BlockPosition, BlockState, ChunkPosition, MappedBlockID, WorldGen};
+use infinigen_common::zoom::ZoomLevel;
+use infinigen_extras::blocks::block_types;
+use infinigen_extras::worldgen::flat::Flat;
+use infinigen_extras::worldgen::floating_islands::FloatingIslands;
+use infinigen_extras::worldgen::heightmap_image::HeightmapImage;
+use infinigen_extras::worldgen::mountain_islands::MountainIslands;
+use infinigen_extras::worldgen::single_block::SingleBlock;
+use infinigen_plugins::settings::{FlatSettings, HeightmapEdges, HeightmapSettings};
+
+const BLESS_ENV_VAR: &str = "INFINIGEN_BLESS";
+
+const SEEDS: [u64; 3] = [0, 42, u64::MAX];
+const ZOOM_LEVELS: [i8; 3] = [-2, 0, 2];
+const POSITIONS: [[i32; 3]; 8] = [
+    [0, 0, 0],
+    [0, -1, 0],
+    [-40, 0, -55],
+    [-40, 3, -55],
+    [3, 1, -7],
+    [12, -2, 9],
+    [-6, 8, 4],
+    [5, 14, -3],
+];
+
+fn palette() -> Palette {
+    let mut palette = Palette::default();
+    for (i, block_type) in block_types().enumerate() {
+        palette.insert(&block_type, MappedBlockID::try_from(1 + i as u8).unwrap());
+    }
+    palette
+}
+
+/// Numbers blocks by their position in [`block_types`], so hashes don't depend on how
+/// [`MappedBlockID`]s are represented.
+fn block_numbers(palette: &Palette) -> AHashMap<MappedBlockID, u64> {
+    block_types()
+        .enumerate()
+        .map(|(i, BlockType { id, .. })| (palette.inner[&id], 1 + i as u64))
+        .collect()
+}
+
+fn generators(seed: u64) -> Vec<(&'static str, Box<dyn WorldGen>)> {
+    let palette = palette();
+    let heightmap: ImageBuffer<Luma<u16>, Vec<u16>> = ImageBuffer::from_fn(64, 48, |x, z| {
+        Luma([((x * 997 + z * 7919) % u16::MAX as u32) as u16])
+    });
+    vec![
+        (
+            "Flat",
+            Box::new(
+                Flat::new(&FlatSettings::default(), &palette)
+                    .unwrap()
+                    .pipeline(),
+            ),
+        ),
+        (
+            "FlatLayers",
+            Box::new(
+                Flat::new(
+                    &FlatSettings {
+                        layers: "stone*40,dirt*2,grass".to_string(),
+                        base_height: -30,
+                    },
+                    &palette,
+                )
+                .unwrap()
+                .pipeline(),
+            ),
+        ),
+        (
+            "FloatingIslands",
+            Box::new(FloatingIslands::new(seed, &palette).unwrap().pipeline()),
+        ),
+        (
+            "HeightmapImage",
+            Box::new(
+                HeightmapImage::new(
+                    &HeightmapSettings {
+                        path: String::new(),
+                        material_map: None,
+                        horizontal_scale: 4.,
+                        vertical_scale: 64.,
+                        base_height: -16.,
+                        edges: HeightmapEdges::Tile,
+                    },
+                    &heightmap,
+                    None,
+                    &palette,
+                )
+                .unwrap()
+                .pipeline(),
+            ),
+        ),
+        (
+            "MountainIslands",
+            Box::new(MountainIslands::new(seed, &palette).unwrap().pipeline()),
+        ),
+        (
+            "SingleBlock",
+            Box::new(SingleBlock::new(&palette).unwrap().pipeline()),
+        ),
+    ]
+}
+
+/// 64-bit FNV-1a over every block in the chunk along with its state, and then the tint of every
+/// column, which is stable across platforms and Rust versions.
+fn hash_chunk(chunk: Option<&Array3Chunk>, block_numbers: &AHashMap<MappedBlockID, u64>) -> String {
+    let Some(chunk) = chunk else {
+        return "empty".to_string();
+    };
+    let mut hash: u64 = 0xCBF2_9CE4_8422_2325;
+    let mut add = |value: u64| {
+        hash ^= value;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01B3);
+    };
+    for x in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                // the state goes in the upper bits, so blocks in their default state hash as before
+                add(chunk
+                    .get_state(&BlockPosition { x, y, z })
+                    .map_or(0, |BlockState { block, state }| {
+                        block_numbers[&block] | u64::from(state) << 32
+                    }));
+            }
+        }
+    }
+    // chunks without tints aren't hashed any differently, so their hashes stay the same
+    for tint in chunk.tints().into_iter().flatten().flatten() {
+        add(u64::from(u32::from_le_bytes([
+            tint[0], tint[1], tint[2], 0,
+        ])));
+    }
+    format!("{hash:016x}")
+}
+
+/// Maps `"<generator> <seed> <x> <y> <z> <zoom>"` to the hash of that chunk.
+fn generate() -> BTreeMap<String, String> {
+    let block_numbers = block_numbers(&palette());
+    let mut hashes = BTreeMap::new();
+    for seed in SEEDS {
+        for (name, wgen) in generators(seed) {
+            for [x, y, z] in POSITIONS {
+                for zoom in ZOOM_LEVELS {
+                    let chunk = wgen.get(&ChunkPosition { x, y, z }, ZoomLevel(zoom));
+                    hashes.insert(
+                        format!("{name} {seed} {x} {y} {z} {zoom}"),
+                        hash_chunk(chunk.as_ref(), &block_numbers),
+                    );
+                }
+            }
+        }
+    }
+    hashes
+}
+
+fn golden_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/worldgen.txt")
+}
+
+fn parse(golden: &str) -> BTreeMap<String, String> {
+    golden
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.rsplit_once(' '))
+        .map(|(key, hash)| (key.to_string(), hash.to_string()))
+        .collect()
+}
+
+fn serialize(hashes: &BTreeMap<String, String>) -> String {
+    let mut golden =
+        format!("# Generated by tests/worldgen_golden.rs, run with {BLESS_ENV_VAR}=1 to update\n");
+    golden.push_str("# generator seed x y z zoom hash\n");
+    for (key, hash) in hashes {
+        writeln!(golden, "{key} {hash}").unwrap();
+    }
+    golden
+}
+
+#[test]
+fn worldgen_matches_golden_hashes() {
+    let actual = generate();
+    let path = golden_path();
+    if std::env::var_os(BLESS_ENV_VAR).is_some_and(|bless| bless != "0") {
+        std::fs::write(&path, serialize(&actual)).unwrap();
+        return;
+    }
+
+    let expected = parse(&std::fs::read_to_string(&path).unwrap_or_default());
+    let mut report = String::new();
+    for (key, hash) in &actual {
+        match expected.get(key) {
+            Some(expected) if expected == hash => {}
+            Some(expected) => writeln!(report, "  changed: {key} ({expected} -> {hash})").unwrap(),
+            None => writeln!(report, "  new: {key} ({hash})").unwrap(),
+        }
+    }
+    for key in expected.keys().filter(|key| !actual.contains_key(*key)) {
+        writeln!(report, "  removed: {key}").unwrap();
+    }
+    assert!(
+        report.is_empty(),
+        "generated chunks differ from {} (run with {BLESS_ENV_VAR}=1 if this is intentional):\n{report}",
+        path.display()
+    );
+}
@@ -12,7 +12,12 @@ use bevy::window::{ExitCondition, Window, WindowPlugin};
 use bevy::winit::WinitPlugin;
 use clap::Parser;
 use config::Config;
-use infinigen_plugins::{AppPlugin, RuntimeOptions};
+#[cfg(not(target_family = "wasm"))]
+use infinigen_plugins::assets::packs::ResourcePacks;
+#[cfg(not(target_family = "wasm"))]
+use infinigen_plugins::assets::validation::validate_dir;
+use infinigen_plugins::bookmark::Bookmark;
+use infinigen_plugins::{AppPlugin, RuntimeOptions, exit_codes};
 #[cfg(all(
     feature = "jemalloc",
     not(target_env = "msvc"),
@@ -43,6 +48,64 @@ struct Cli {
         help = "Run without creating a window or requiring a display server"
     )]
     headless: bool,
+    #[arg(
+        long,
+        help = "Start at a bookmarked view of a world, as copied from the debug UI"
+    )]
+    bookmark: Option<Bookmark>,
+    #[cfg(not(target_family = "wasm"))]
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[cfg(not(target_family = "wasm"))]
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Check block definitions and textures for problems, along with those of the configured
+    /// resource packs, without starting the app
+    ValidateAssets {
+        #[arg(
+            long,
+            help = "Path to the assets directory, defaults to the one the app would load from"
+        )]
+        assets: Option<std::path::PathBuf>,
+    },
+}
+
+/// Prints any problems with the assets as layered with `packs`, failing if any of them are errors.
+#[cfg(not(target_family = "wasm"))]
+fn validate_assets(assets: Option<std::path::PathBuf>, packs: &ResourcePacks) -> ExitCode {
+    let assets = assets.unwrap_or_else(|| {
+        bevy::asset::io::file::FileAssetReader::get_base_path()
+            .join(AssetPlugin::default().file_path)
+    });
+    let issues = match validate_dir(&assets, packs) {
+        Ok(issues) => issues,
+        Err(err) => {
+            eprintln!("Couldn't read assets in {}: {err}", assets.display());
+            return ExitCode::from(exit_codes::UNREADABLE_ASSETS);
+        }
+    };
+    let errors = issues.iter().filter(|issue| issue.is_error()).count();
+    for issue in &issues {
+        let severity = if issue.is_error() { "error" } else { "warning" };
+        println!("{severity}: {issue}");
+    }
+    let packs = if packs.0.is_empty() {
+        String::new()
+    } else {
+        format!(" with resource packs {}", packs.0.join(", "))
+    };
+    println!(
+        "{}{packs} has {errors} error(s) and {} warning(s)",
+        assets.display(),
+        issues.len() - errors
+    );
+    if errors > 0 {
+        ExitCode::from(exit_codes::INVALID_ASSETS)
+    } else {
+        ExitCode::SUCCESS
+    }
 }
 
 fn main() -> ExitCode {
@@ -56,21 +119,30 @@ fn main() -> ExitCode {
         config::Environment::with_prefix(CONFIG_PREFIX)
             .prefix_separator("_")
             .separator("__")
-            .try_parsing(true),
+            .try_parsing(true)
+            .list_separator(",")
+            .with_list_parse_key("resource_packs"),
     );
-    let cfg: infinigen_plugins::settings::AppSettings = match cfg.build() {
+    let mut cfg: infinigen_plugins::settings::AppSettings = match cfg.build() {
         Ok(settings) => match settings.try_deserialize() {
             Ok(cfg) => cfg,
             Err(err) => {
                 eprintln!("Couldn't parse settings: {err}");
-                return ExitCode::from(78);
+                return ExitCode::from(exit_codes::INVALID_SETTINGS);
             }
         },
         Err(err) => {
             eprintln!("Couldn't load settings: {err}");
-            return ExitCode::from(78);
+            return ExitCode::from(exit_codes::INVALID_SETTINGS);
         }
     };
+    #[cfg(not(target_family = "wasm"))]
+    if let Some(Command::ValidateAssets { assets }) = cli.command {
+        return validate_assets(assets, &ResourcePacks(cfg.resource_packs));
+    }
+    if let Some(bookmark) = &cli.bookmark {
+        bookmark.apply(&mut cfg);
+    }
     let runtime = RuntimeOptions {
         headless: cli.headless,
     };
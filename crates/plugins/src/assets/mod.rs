@@ -4,30 +4,57 @@ use bevy_common_assets::ron::RonAssetPlugin;
 use infinigen_common::blocks::BlockType;
 
 use crate::AppState;
+use crate::assets::packs::ResourcePacks;
+#[cfg(not(target_family = "wasm"))]
+use crate::assets::validation::{BLOCK_TEXTURES_DIR, BLOCK_TYPES_DIR};
 use crate::registry::BlockDefinition;
+use crate::settings::TextureBackend;
 
+pub mod packs;
+mod reload;
 mod setup;
+pub mod texture_array;
+pub mod validation;
 
 pub struct AssetsPlugin;
 
 #[derive(Resource)]
 pub struct DefaultBlockTypes(pub Vec<BlockType>);
 
+/// How block textures are passed to the GPU.
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct BlockTextureBackend(pub TextureBackend);
+
 #[derive(AssetCollection, Resource, Default)]
 pub struct BlockAssets {
-    #[asset(path = "blocks/types", collection(typed))]
+    /// Definitions from the built-in assets, then each resource pack in order.
+    #[asset(key = "blocks.types", collection(typed))]
     pub block_definitions: Vec<Handle<BlockDefinition>>,
-    #[asset(path = "blocks/textures", collection(typed))]
+    /// Textures from the built-in assets, then each resource pack in order.
+    #[asset(key = "blocks.textures", collection(typed))]
     pub block_textures: Vec<Handle<Image>>,
 }
 
 impl Plugin for AssetsPlugin {
     fn build(&self, app: &mut App) {
         tracing::info!("Initializing assets plugin");
-        app.add_plugins((RonAssetPlugin::<BlockDefinition>::new(&["block.ron"]),))
+        app.init_resource::<ResourcePacks>()
+            .init_resource::<BlockTextureBackend>()
+            .add_plugins((RonAssetPlugin::<BlockDefinition>::new(&["block.ron"]),))
+            .add_message::<reload::ReloadBlockAssetsMessage>()
             .add_systems(
                 OnEnter(AppState::InitializingRegistry),
                 setup::initialize_block_assets,
+            )
+            .add_systems(
+                Update,
+                (
+                    reload::detect_block_asset_changes,
+                    reload::reload_block_assets
+                        .run_if(on_message::<reload::ReloadBlockAssetsMessage>),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::MainGame)),
             );
 
         register_loading_flow(app);
@@ -42,6 +69,25 @@ fn register_loading_flow(app: &mut App) {
             .on_failure_continue_to_state(AppState::InitializingRegistry)
             .load_collection::<BlockAssets>(),
     );
+
+    let packs = app.world().resource::<ResourcePacks>().clone();
+    let file_path = app.get_added_plugins::<AssetPlugin>().first().map_or_else(
+        || AssetPlugin::default().file_path,
+        |plugin| plugin.file_path.clone(),
+    );
+    let root = bevy::asset::io::file::FileAssetReader::new(file_path)
+        .root_path()
+        .clone();
+    let exists = |dir: &std::path::Path| root.join(dir).is_dir();
+    let mut dynamic_assets = app.world_mut().resource_mut::<DynamicAssets>();
+    dynamic_assets.register_asset(
+        "blocks.types",
+        Box::new(packs.layered(BLOCK_TYPES_DIR, exists)),
+    );
+    dynamic_assets.register_asset(
+        "blocks.textures",
+        Box::new(packs.layered(BLOCK_TEXTURES_DIR, exists)),
+    );
 }
 
 #[cfg(target_family = "wasm")]
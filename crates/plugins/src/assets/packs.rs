@@ -0,0 +1,195 @@
+//! Resource packs are directories within the assets directory that are laid out like it, and are
+//! loaded in order after the built-in block assets so that they can override them.
+use std::path::Path;
+
+use ahash::AHashMap;
+use bevy::asset::LoadedFolder;
+use bevy::ecs::system::SystemState;
+use bevy::prelude::*;
+use bevy_asset_loader::prelude::*;
+
+use crate::registry::BlockDefinition;
+
+/// Ordered resource pack directories, relative to the assets directory.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct ResourcePacks(pub Vec<String>);
+
+impl ResourcePacks {
+    /// The pack that the asset at `path` was loaded from, or `None` for built-in assets.
+    pub fn pack_of(&self, path: &Path) -> Option<&str> {
+        self.position_of(path)
+            .map(|position| self.0[position].as_str())
+    }
+
+    /// Position of the pack that the asset at `path` was loaded from, where built-in assets come
+    /// before any pack.
+    fn position_of(&self, path: &Path) -> Option<usize> {
+        self.0.iter().rposition(|pack| path.starts_with(pack))
+    }
+
+    /// `dir` within the built-in assets, followed by `dir` within each pack that has it.
+    pub fn layered(&self, dir: &str, exists: impl Fn(&Path) -> bool) -> LayeredFolder {
+        let mut folders = vec![dir.to_owned()];
+        for pack in &self.0 {
+            let folder = Path::new(pack).join(dir);
+            if exists(&folder) {
+                folders.push(folder.to_string_lossy().into_owned());
+            } else {
+                tracing::debug!(pack, dir, "Resource pack doesn't have directory");
+            }
+        }
+        LayeredFolder { folders }
+    }
+
+    /// Drops block definitions that a later pack overrides with the same ID, where each definition
+    /// is paired with the path it was loaded from.
+    pub fn without_overridden(
+        &self,
+        definitions: Vec<(String, BlockDefinition)>,
+    ) -> Vec<(String, BlockDefinition)> {
+        let mut winning_packs = AHashMap::new();
+        for (source, definition) in &definitions {
+            let position = self.position_of(Path::new(source));
+            winning_packs
+                .entry(definition.0.id.clone())
+                .and_modify(|winner: &mut Option<usize>| *winner = (*winner).max(position))
+                .or_insert(position);
+        }
+        definitions
+            .into_iter()
+            .filter(|(source, definition)| {
+                let kept = winning_packs[&definition.0.id] == self.position_of(Path::new(source));
+                if !kept {
+                    tracing::debug!(
+                        block_id = ?definition.0.id,
+                        source,
+                        "Block definition is overridden by a later resource pack"
+                    );
+                }
+                kept
+            })
+            .collect()
+    }
+}
+
+/// Every asset in a list of folders, in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayeredFolder {
+    folders: Vec<String>,
+}
+
+impl LayeredFolder {
+    pub fn folders(&self) -> &[String] {
+        &self.folders
+    }
+}
+
+impl DynamicAsset for LayeredFolder {
+    fn load(&self, asset_server: &AssetServer) -> Vec<UntypedHandle> {
+        self.folders
+            .iter()
+            .map(|folder| asset_server.load_folder(folder).untyped())
+            .collect()
+    }
+
+    fn build(&self, world: &mut World) -> Result<DynamicAssetType, anyhow::Error> {
+        let mut system_state =
+            SystemState::<(Res<Assets<LoadedFolder>>, Res<AssetServer>)>::new(world);
+        let (loaded_folders, asset_server) = system_state.get(world);
+        let mut handles = vec![];
+        for folder in &self.folders {
+            let loaded = asset_server
+                .get_handle::<LoadedFolder>(folder)
+                .and_then(|handle| loaded_folders.get(&handle))
+                .ok_or_else(|| anyhow::anyhow!("{folder} wasn't loaded"))?;
+            handles.extend(loaded.handles.iter().cloned());
+        }
+        Ok(DynamicAssetType::Collection(handles))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use infinigen_common::blocks::BlockType;
+
+    use super::*;
+
+    #[test]
+    fn later_packs_take_precedence() {
+        let packs = ResourcePacks(vec!["packs/hd".to_owned(), "packs/hd/extra".to_owned()]);
+        let pack_of = |path: &str| packs.pack_of(Path::new(path));
+        assert_eq!(pack_of("blocks/types/stone.block.ron"), None);
+        assert_eq!(
+            pack_of("packs/hd/blocks/types/stone.block.ron"),
+            Some("packs/hd")
+        );
+        assert_eq!(
+            pack_of("packs/hd/extra/blocks/textures/stone.png"),
+            Some("packs/hd/extra")
+        );
+        assert_eq!(pack_of("packs/hdr/blocks/textures/stone.png"), None);
+    }
+
+    #[test]
+    fn later_packs_override_definitions_by_id() {
+        let packs = ResourcePacks(vec!["packs/a".to_owned(), "packs/b".to_owned()]);
+        let definition = |id: &str, light_emission| {
+            BlockDefinition(BlockType {
+                id: id.to_owned(),
+                light_emission,
+                ..Default::default()
+            })
+        };
+        let kept = packs.without_overridden(vec![
+            (
+                "blocks/types/lava.block.ron".to_owned(),
+                definition("infinigen:lava", 15),
+            ),
+            (
+                "blocks/types/stone.block.ron".to_owned(),
+                definition("infinigen:stone", 0),
+            ),
+            (
+                "packs/b/blocks/types/lava.block.ron".to_owned(),
+                definition("infinigen:lava", 7),
+            ),
+            (
+                "packs/a/blocks/types/lava.block.ron".to_owned(),
+                definition("infinigen:lava", 3),
+            ),
+            (
+                "packs/a/blocks/types/stone.block.ron".to_owned(),
+                definition("infinigen:stone", 1),
+            ),
+            (
+                "packs/a/blocks/types/stone2.block.ron".to_owned(),
+                definition("infinigen:stone", 2),
+            ),
+        ]);
+        let kept: Vec<_> = kept
+            .iter()
+            .map(|(source, definition)| (source.as_str(), definition.0.light_emission))
+            .collect();
+        // duplicates within the same pack are left for validation to report
+        assert_eq!(
+            kept,
+            [
+                ("packs/b/blocks/types/lava.block.ron", 7),
+                ("packs/a/blocks/types/stone.block.ron", 1),
+                ("packs/a/blocks/types/stone2.block.ron", 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_pack_directories_are_skipped() {
+        let packs = ResourcePacks(vec![
+            "packs/textures_only".to_owned(),
+            "packs/all".to_owned(),
+        ]);
+        let layered = packs.layered("blocks/types", |path| {
+            path != Path::new("packs/textures_only/blocks/types")
+        });
+        assert_eq!(layered.folders, ["blocks/types", "packs/all/blocks/types"]);
+    }
+}
@@ -0,0 +1,91 @@
+//! Reloads block definitions and textures when their files change, which requires Bevy's
+//! `file_watcher` feature (enabled by the app's `hot_reload` feature).
+use bevy::prelude::*;
+use bevy::render::renderer::RenderAdapterInfo;
+
+use crate::assets::packs::ResourcePacks;
+use crate::assets::setup::load_block_assets;
+use crate::assets::{BlockAssets, BlockTextureBackend, DefaultBlockTypes};
+use crate::mesh::Meshes;
+use crate::mesh::material::VoxelMaterial;
+use crate::registry::{BlockDefinition, BlockRegistry};
+use crate::scene::ReloadAllChunksMessage;
+
+#[derive(Message)]
+pub struct ReloadBlockAssetsMessage;
+
+pub fn detect_block_asset_changes(
+    block_assets: Option<Res<BlockAssets>>,
+    mut definition_events: MessageReader<AssetEvent<BlockDefinition>>,
+    mut texture_events: MessageReader<AssetEvent<Image>>,
+    mut reload_msgs: MessageWriter<ReloadBlockAssetsMessage>,
+) {
+    let Some(block_assets) = block_assets else {
+        definition_events.clear();
+        texture_events.clear();
+        return;
+    };
+    // read every event, so that none are left over to trigger another reload
+    let definitions_changed = definition_events
+        .read()
+        .filter(|event| {
+            block_assets
+                .block_definitions
+                .iter()
+                .any(|handle| event.is_modified(handle))
+        })
+        .count();
+    let textures_changed = texture_events
+        .read()
+        .filter(|event| {
+            block_assets
+                .block_textures
+                .iter()
+                .any(|handle| event.is_modified(handle))
+        })
+        .count();
+    if definitions_changed + textures_changed > 0 {
+        tracing::info!(
+            definitions_changed,
+            textures_changed,
+            "Block assets changed on disk"
+        );
+        reload_msgs.write(ReloadBlockAssetsMessage);
+    }
+}
+
+/// Rebuilds the registry from the changed assets, then remeshes all chunks. Chunks aren't
+/// regenerated, as block IDs stay the same.
+pub fn reload_block_assets(
+    mut reload_msgs: MessageReader<ReloadBlockAssetsMessage>,
+    mut registry: ResMut<BlockRegistry>,
+    block_assets: Option<Res<BlockAssets>>,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<VoxelMaterial>>,
+    mut textures: ResMut<Assets<Image>>,
+    settings: Res<DefaultBlockTypes>,
+    block_definitions: Res<Assets<BlockDefinition>>,
+    packs: Res<ResourcePacks>,
+    backend: Res<BlockTextureBackend>,
+    adapter: Option<Res<RenderAdapterInfo>>,
+    mut meshes: ResMut<Meshes>,
+    mut reload_chunks_msgs: MessageWriter<ReloadAllChunksMessage>,
+) -> Result {
+    reload_msgs.clear();
+    tracing::info!("Reloading block assets");
+    load_block_assets(
+        registry.as_mut(),
+        block_assets.as_deref(),
+        asset_server.as_ref(),
+        materials.as_mut(),
+        textures.as_mut(),
+        settings.as_ref(),
+        block_definitions.as_ref(),
+        packs.as_ref(),
+        backend.0,
+        adapter.as_deref(),
+    )?;
+    meshes.meshes.clear();
+    reload_chunks_msgs.write(ReloadAllChunksMessage);
+    Ok(())
+}
@@ -1,140 +1,419 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
 use ahash::AHashMap;
 use bevy::asset::{AssetPath, AssetServer, Assets};
 use bevy::prelude::{
     AlphaMode, BevyError, Color, Handle, Image, NextState, Res, ResMut, Result, StandardMaterial,
-    TextureAtlasBuilder, TextureAtlasLayout, TextureAtlasSources, default,
+    UVec2, default,
+};
+use bevy::render::renderer::RenderAdapterInfo;
+use bevy::render::settings::Backends;
+use infinigen_common::blocks::{
+    Animation, BlockColor, BlockVisibility, Face, MAX_BLOCK_STATES, MaterialMap, MaterialMaps,
+    TextureVariation,
 };
-use infinigen_common::blocks::{BlockVisibility, Face};
-use infinigen_common::mesh::textures::{BlockAppearances, FaceAppearance};
+use infinigen_common::mesh::connected::{CONNECTED_COLUMNS, CONNECTED_TILES};
+use infinigen_common::mesh::textures::{
+    AnimatedTexture, BlockAppearances, FaceAppearance, FaceVariation,
+};
+use infinigen_common::world::{BlockState, BlockStateID};
 use linearize::{StaticCopyMap, static_copy_map};
 use strum::IntoEnumIterator;
 
 use crate::AppState;
-use crate::assets::{BlockAssets, DefaultBlockTypes};
+use crate::assets::packs::ResourcePacks;
+use crate::assets::texture_array::TextureArrayBuilder;
+use crate::assets::{BlockAssets, BlockTextureBackend, DefaultBlockTypes, validation};
+use crate::mesh::material::{
+    ROUGHNESS, VoxelExtension, VoxelMaterial, VoxelSettings, animations_image, default_map_pixel,
+    is_srgb, palette_image,
+};
 use crate::registry::{BlockDefinition, BlockRegistry};
+use crate::settings::TextureBackend;
 
 pub fn initialize_block_assets(
     mut next_state: ResMut<NextState<AppState>>,
     mut registry: ResMut<BlockRegistry>,
     block_assets: Option<Res<BlockAssets>>,
     asset_server: Res<AssetServer>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut materials: ResMut<Assets<VoxelMaterial>>,
     mut textures: ResMut<Assets<Image>>,
     settings: Res<DefaultBlockTypes>,
     block_definitions: Res<Assets<BlockDefinition>>,
+    packs: Res<ResourcePacks>,
+    backend: Res<BlockTextureBackend>,
+    adapter: Option<Res<RenderAdapterInfo>>,
 ) -> Result {
-    let block_assets = block_assets.as_deref();
-    let prepared_textures =
-        prepare_block_textures(block_assets, asset_server.as_ref(), textures.as_mut())?;
-
-    let mut block_textures = BlockAppearances::default();
-    if let Some(prepared) = prepared_textures.as_ref() {
-        block_textures.size = [
-            prepared.atlas_layout.size.x as usize,
-            prepared.atlas_layout.size.y as usize,
-        ];
-    }
+    load_block_assets(
+        registry.as_mut(),
+        block_assets.as_deref(),
+        asset_server.as_ref(),
+        materials.as_mut(),
+        textures.as_mut(),
+        settings.as_ref(),
+        block_definitions.as_ref(),
+        packs.as_ref(),
+        backend.0,
+        adapter.as_deref(),
+    )?;
 
-    let block_definitions =
-        load_block_definitions(block_assets, block_definitions.as_ref(), settings.as_ref());
+    next_state.set(AppState::InitializingWorld);
+    Ok(())
+}
 
-    register_blocks(
+/// Fills the registry with block definitions, their appearances and materials from the loaded
+/// block assets. Can be called again once assets have changed, in which case blocks already in
+/// the registry keep their [`MappedBlockID`]s.
+pub(super) fn load_block_assets(
+    registry: &mut BlockRegistry,
+    block_assets: Option<&BlockAssets>,
+    asset_server: &AssetServer,
+    materials: &mut Assets<VoxelMaterial>,
+    textures: &mut Assets<Image>,
+    settings: &DefaultBlockTypes,
+    block_definitions: &Assets<BlockDefinition>,
+    packs: &ResourcePacks,
+    backend: TextureBackend,
+    adapter: Option<&RenderAdapterInfo>,
+) -> Result {
+    // GL treats textures with a single layer as plain 2D textures rather than arrays
+    let pad_single_layer =
+        adapter.is_some_and(|adapter| Backends::from(adapter.backend) == Backends::GL);
+    let textures_by_name = collect_block_textures(block_assets, asset_server);
+
+    let block_definitions = load_block_definitions(
+        block_assets,
+        asset_server,
         block_definitions,
-        registry.as_mut(),
-        &mut block_textures,
-        prepared_textures.as_ref(),
+        settings,
+        packs,
+        textures_by_name.as_ref(),
+        textures,
     );
+    register_block_definitions(block_definitions, registry);
+
+    let block_textures = match textures_by_name {
+        Some(textures_by_name) => build_block_textures(
+            &texture_slots(registry),
+            &textures_by_name,
+            asset_server,
+            textures,
+            backend,
+            pad_single_layer,
+        )?,
+        None => None,
+    };
 
-    registry.appearances = block_textures;
+    let mut appearances = BlockAppearances::default();
+    if let Some(atlas_size) = block_textures.as_ref().and_then(|built| built.atlas_size) {
+        appearances.size = [atlas_size.x as usize, atlas_size.y as usize];
+    }
+    register_block_appearances(registry, &mut appearances, block_textures.as_ref());
+    registry.appearances = appearances;
     tracing::debug!("Registered all block textures: {:#?}", registry.appearances);
 
-    configure_materials(
-        registry.as_mut(),
-        materials.as_mut(),
-        prepared_textures
-            .as_ref()
-            .map(|prepared| prepared.atlas_texture.clone()),
-    );
-
-    next_state.set(AppState::InitializingWorld);
+    configure_materials(registry, materials, textures, block_textures.as_ref());
     Ok(())
 }
 
-#[derive(Debug)]
-struct PreparedTextures {
-    handles_by_name: AHashMap<String, Handle<Image>>,
-    atlas_sources: TextureAtlasSources,
-    atlas_layout: TextureAtlasLayout,
-    atlas_texture: Handle<Image>,
+/// The texture a face is drawn with, along with its material maps and how it's animated or
+/// connected. Each slot is at the same place in the block textures and in each kind of material
+/// map.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct TextureSlot {
+    texture: String,
+    maps: BTreeMap<MaterialMap, String>,
+    animation: Option<Animation>,
+    /// Whether the texture is a strip of connected tiles, in which case it isn't animated.
+    connected: bool,
 }
 
-impl PreparedTextures {
-    fn coords_for_name(&self, texture_name: &str) -> Option<[usize; 2]> {
-        let handle = self.handles_by_name.get(texture_name)?;
-        let index = self.atlas_sources.texture_index(handle)?;
-        let rect = self.atlas_layout.textures.get(index)?;
-        Some([rect.min.x as usize, rect.min.y as usize])
-    }
+#[derive(Debug)]
+struct BlockTextures {
+    appearances: AHashMap<TextureSlot, FaceAppearance>,
+    /// Each tile of connected slots, whose appearance is that of their first tile.
+    connected_tiles: AHashMap<TextureSlot, Vec<FaceAppearance>>,
+    textures: Handle<Image>,
+    maps: BTreeMap<MaterialMap, Handle<Image>>,
+    /// Size of each texture in pixels.
+    texture_size: UVec2,
+    /// Size of the texture atlas in pixels, if textures are in one rather than a texture array.
+    atlas_size: Option<UVec2>,
 }
 
-fn prepare_block_textures(
+/// The loaded block textures by name, or `None` if there aren't any.
+fn collect_block_textures(
     block_assets: Option<&BlockAssets>,
     asset_server: &AssetServer,
-    textures: &mut Assets<Image>,
-) -> Result<Option<PreparedTextures>> {
+) -> Option<AHashMap<String, Handle<Image>>> {
     let Some(block_assets) = block_assets else {
         tracing::warn!("Block textures were not loaded, falling back to colours");
-        return Ok(None);
+        return None;
     };
 
-    if block_assets.block_textures.is_empty() {
-        tracing::warn!("Block textures were not loaded, falling back to colours");
-        return Ok(None);
-    }
-
-    let mut handles_by_name = AHashMap::with_capacity(block_assets.block_textures.len());
-    let mut atlas_builder = TextureAtlasBuilder::default();
-
+    // textures from later resource packs replace earlier ones with the same name
+    let mut textures_by_name = AHashMap::with_capacity(block_assets.block_textures.len());
     for handle in &block_assets.block_textures {
         let Some(asset_path) = asset_server.get_path(handle.id()) else {
             tracing::warn!(?handle, "Skipping texture without a known path");
             continue;
         };
 
-        let Some(texture) = textures.get(handle) else {
-            return Err(BevyError::from(format!(
-                "{asset_path:?} did not resolve to an `Image` asset.",
-            )));
-        };
-
         let Some(texture_name) = texture_name_from_path(&asset_path) else {
             tracing::warn!(path = ?asset_path, "Skipping texture without a valid filename");
             continue;
         };
 
         tracing::debug!(path = ?asset_path, "Texture found");
-        handles_by_name.insert(texture_name, handle.clone());
-        atlas_builder.add_texture(Some(handle.id()), texture);
+        if textures_by_name
+            .insert(texture_name, handle.clone())
+            .is_some()
+        {
+            tracing::debug!(path = ?asset_path, "Texture overrides an earlier one");
+        }
     }
 
-    if handles_by_name.is_empty() {
+    if textures_by_name.is_empty() {
         tracing::warn!("Block textures were not loaded, falling back to colours");
+        return None;
+    }
+    Some(textures_by_name)
+}
+
+/// Every texture slot used by a registered block.
+fn texture_slots(registry: &BlockRegistry) -> BTreeSet<TextureSlot> {
+    let mut slots = BTreeSet::new();
+    for (_, block_definition) in registry.definitions.iter() {
+        for state in 0..block_definition.0.state_count().min(MAX_BLOCK_STATES) {
+            let state = state as BlockStateID;
+            slots.extend(face_slots(block_definition, state).map(|(_, slot)| slot));
+            for (_, _, choices) in face_variations(block_definition, state) {
+                slots.extend(choices.into_iter().map(|(slot, _)| slot));
+            }
+        }
+    }
+    slots
+}
+
+/// The texture slot of each face of `state` which has a texture.
+fn face_slots(
+    block_definition: &BlockDefinition,
+    state: BlockStateID,
+) -> impl Iterator<Item = (Face, TextureSlot)> {
+    let (_, textures) = block_definition.0.appearance(state);
+    let maps = block_definition.0.maps(state);
+    textures
+        .into_iter()
+        .flatten()
+        .map(move |(face, texture)| (face, texture_slot(block_definition, &maps, face, texture)))
+}
+
+/// How each face of `state` with a varying texture varies, along with the slot and weight of
+/// each texture it picks from.
+fn face_variations(
+    block_definition: &BlockDefinition,
+    state: BlockStateID,
+) -> impl Iterator<Item = (Face, &TextureVariation, Vec<(TextureSlot, u32)>)> {
+    let (_, textures) = block_definition.0.appearance(state);
+    let maps = block_definition.0.maps(state);
+    textures
+        .into_iter()
+        .flatten()
+        .filter_map(move |(face, texture)| {
+            let variation = block_definition.0.variation.get(&texture)?;
+            let choices = variation
+                .textures
+                .iter()
+                .map(|(choice, weight)| {
+                    let slot = texture_slot(block_definition, &maps, face, choice.clone());
+                    (slot, *weight)
+                })
+                .collect();
+            Some((face, variation, choices))
+        })
+}
+
+/// The slot of `texture` on a face, with the face's material maps.
+fn texture_slot(
+    block_definition: &BlockDefinition,
+    maps: &MaterialMaps,
+    face: Face,
+    texture: String,
+) -> TextureSlot {
+    let maps = MaterialMap::iter()
+        .filter_map(|map| Some((map, maps.get(map).get(&face)?.clone())))
+        .collect();
+    let animation = block_definition.0.animations.get(&texture).copied();
+    let connected = block_definition.0.connected.contains(&texture);
+    TextureSlot {
+        texture,
+        maps,
+        animation,
+        connected,
+    }
+}
+
+/// Puts the textures and material maps of each slot into texture atlases or texture arrays,
+/// depending on `backend`, with a layer for each frame of animated textures and each tile of
+/// connected ones. Slots whose texture can't be used are left out, and material maps which
+/// can't be used are treated as missing. Returns `None` if no slot could be used.
+fn build_block_textures(
+    slots: &BTreeSet<TextureSlot>,
+    textures_by_name: &AHashMap<String, Handle<Image>>,
+    asset_server: &AssetServer,
+    textures: &mut Assets<Image>,
+    backend: TextureBackend,
+    pad_single_layer: bool,
+) -> Result<Option<BlockTextures>> {
+    let image = |name: &str| -> Result<Option<&Image>> {
+        let Some(handle) = textures_by_name.get(name) else {
+            return Ok(None);
+        };
+        let Some(image) = textures.get(handle) else {
+            return Err(BevyError::from(format!(
+                "{:?} did not resolve to an `Image` asset.",
+                asset_server.get_path(handle.id())
+            )));
+        };
+        Ok(Some(image))
+    };
+
+    let mut texture_builder = TextureArrayBuilder::default().pad_single_layer(pad_single_layer);
+    let mut map_builders: BTreeMap<_, _> = MaterialMap::iter()
+        .map(|map| {
+            let builder = if is_srgb(map) {
+                TextureArrayBuilder::default()
+            } else {
+                TextureArrayBuilder::linear()
+            };
+            (map, builder.pad_single_layer(pad_single_layer))
+        })
+        .collect();
+    // slots along with the layer of their first frame or tile
+    let mut added = Vec::with_capacity(slots.len());
+    let mut texture_size = UVec2::ZERO;
+    for slot in slots {
+        let Some(texture) = image(&slot.texture)? else {
+            continue;
+        };
+        // animations are strips of frames, and connected textures grids of tiles
+        let (columns, layers) = if slot.connected {
+            (CONNECTED_COLUMNS, CONNECTED_TILES)
+        } else {
+            (1, slot.animation.map_or(1, |animation| animation.frames))
+        };
+        let add = |builder: &mut TextureArrayBuilder, texture| {
+            if slot.connected {
+                builder.add_tiles(texture, columns, layers)
+            } else {
+                builder.add_frames(texture, layers)
+            }
+        };
+        let first = match add(&mut texture_builder, texture) {
+            Ok(first) => first,
+            Err(err) => {
+                tracing::warn!(texture = slot.texture, "Skipping texture: {err}");
+                continue;
+            }
+        };
+        texture_size = texture.size() / UVec2::new(columns, layers.div_ceil(columns));
+        for (map, builder) in &mut map_builders {
+            let map_texture = match slot.maps.get(map) {
+                Some(name) => image(name)?,
+                None => None,
+            };
+            let added = map_texture.is_some_and(|map_texture| {
+                add(builder, map_texture)
+                    .inspect_err(|err| {
+                        tracing::warn!(texture = slot.maps[map], "Skipping {map:?} map: {err}");
+                    })
+                    .is_ok()
+            });
+            if !added {
+                for _ in 0..layers {
+                    builder.add_solid(texture_size, default_map_pixel(*map))?;
+                }
+            }
+        }
+        added.push((slot, first));
+    }
+
+    if added.is_empty() {
+        tracing::warn!("No block textures could be used, falling back to colours");
         return Ok(None);
     }
 
-    let (atlas_layout, atlas_sources, atlas_image) = atlas_builder.build()?;
-    tracing::debug!(
-        size = ?atlas_layout.size,
-        textures = ?atlas_layout.textures,
-        "Stitched texture atlas"
-    );
-    let atlas_texture = textures.add(atlas_image);
+    let (texture, layer_appearances, atlas_size): (_, Vec<_>, _) = match backend {
+        TextureBackend::Atlas => {
+            let atlas = texture_builder
+                .build_atlas()
+                .expect("should have had textures to build");
+            tracing::debug!(size = ?atlas.size(), "Stitched texture atlas");
+            let cells = (atlas.size() / texture_size).element_product();
+            let appearances = (0..cells)
+                .map(|index| FaceAppearance::Texture { index })
+                .collect();
+            let atlas_size = atlas.size();
+            (atlas, appearances, Some(atlas_size))
+        }
+        TextureBackend::Array => {
+            let array = texture_builder
+                .build()
+                .expect("should have had textures to build");
+            tracing::debug!(
+                size = ?array.texture_descriptor.size,
+                mip_levels = array.texture_descriptor.mip_level_count,
+                "Built texture array"
+            );
+            let appearances = (0..array.texture_descriptor.size.depth_or_array_layers)
+                .map(|index| FaceAppearance::Layer { index })
+                .collect();
+            (array, appearances, None)
+        }
+    };
 
-    Ok(Some(PreparedTextures {
-        handles_by_name,
-        atlas_sources,
-        atlas_layout,
-        atlas_texture,
+    let maps = map_builders
+        .into_iter()
+        .map(|(map, builder)| {
+            let image = match backend {
+                TextureBackend::Atlas => builder.build_atlas(),
+                TextureBackend::Array => builder.build(),
+            };
+            (map, image.expect("should have had a map for each texture"))
+        })
+        .map(|(map, image)| (map, textures.add(image)))
+        .collect();
+
+    let connected_tiles = added
+        .iter()
+        .filter(|(slot, _)| slot.connected)
+        .map(|&(slot, first)| {
+            let tiles = first as usize..(first + CONNECTED_TILES) as usize;
+            (slot.clone(), layer_appearances[tiles].to_vec())
+        })
+        .collect();
+    let appearances = added
+        .into_iter()
+        .map(|(slot, first)| {
+            let appearance = match slot.animation {
+                Some(animation) if !slot.connected => FaceAppearance::Animated(AnimatedTexture {
+                    first,
+                    frames: animation.frames,
+                    frame_time_ms: animation.frame_time_ms,
+                }),
+                _ => layer_appearances[first as usize],
+            };
+            (slot.clone(), appearance)
+        })
+        .collect();
+    Ok(Some(BlockTextures {
+        appearances,
+        connected_tiles,
+        textures: textures.add(texture),
+        maps,
+        texture_size,
+        atlas_size,
     }))
 }
 
@@ -145,13 +424,19 @@ fn texture_name_from_path(path: &AssetPath) -> Option<String> {
         .map(|name| name.trim_end_matches(".png").to_owned())
 }
 
+/// Block definitions to register, each with the resource pack it came from if any.
 fn load_block_definitions(
     block_assets: Option<&BlockAssets>,
+    asset_server: &AssetServer,
     block_definitions: &Assets<BlockDefinition>,
     defaults: &DefaultBlockTypes,
-) -> Vec<BlockDefinition> {
+    packs: &ResourcePacks,
+    textures_by_name: Option<&AHashMap<String, Handle<Image>>>,
+    textures: &Assets<Image>,
+) -> Vec<(Option<String>, BlockDefinition)> {
     let mut loaded = block_assets
-        .map(|assets| collect_block_definitions(assets, block_definitions))
+        .map(|assets| collect_block_definitions(assets, asset_server, block_definitions))
+        .map(|loaded| packs.without_overridden(loaded))
         .unwrap_or_default();
 
     if loaded.is_empty() {
@@ -160,64 +445,163 @@ fn load_block_definitions(
             .0
             .iter()
             .cloned()
-            .map(BlockDefinition::from)
+            .map(|block_type| ("defaults".to_owned(), BlockDefinition::from(block_type)))
             .collect();
     }
 
-    loaded.sort();
+    let texture_sizes = textures_by_name.map(|textures_by_name| {
+        textures_by_name
+            .iter()
+            .filter_map(|(name, handle)| {
+                Some((name.clone(), textures.get(handle)?.size().to_array()))
+            })
+            .collect::<BTreeMap<_, _>>()
+    });
+    let issues = validation::validate(
+        loaded
+            .iter()
+            .map(|(source, definition)| (source.as_str(), &definition.0)),
+        texture_sizes.as_ref(),
+    );
+    for issue in issues {
+        if issue.is_error() {
+            tracing::error!("{issue}");
+        } else {
+            tracing::warn!("{issue}");
+        }
+    }
+
+    let mut loaded: Vec<_> = loaded
+        .into_iter()
+        .map(|(source, definition)| {
+            let pack = packs.pack_of(Path::new(&source)).map(str::to_owned);
+            (pack, definition)
+        })
+        .collect();
+    // the sort is stable, so only the first loaded of any definitions sharing an ID is registered
+    loaded.sort_by(|(_, a), (_, b)| a.0.id.cmp(&b.0.id));
+    loaded.dedup_by(|(_, later), (_, first)| later.0.id == first.0.id);
     loaded
 }
 
+/// Loaded block definitions, along with the paths they were loaded from.
 fn collect_block_definitions(
     block_assets: &BlockAssets,
+    asset_server: &AssetServer,
     block_definitions: &Assets<BlockDefinition>,
-) -> Vec<BlockDefinition> {
+) -> Vec<(String, BlockDefinition)> {
     let mut definitions = Vec::with_capacity(block_assets.block_definitions.len());
     for handle in &block_assets.block_definitions {
         match block_definitions.get(handle) {
-            Some(definition) => definitions.push(definition.clone()),
+            Some(definition) => {
+                let source = asset_server
+                    .get_path(handle.id())
+                    .map_or_else(|| format!("{handle:?}"), |path| path.to_string());
+                definitions.push((source, definition.clone()));
+            }
             None => tracing::warn!(?handle, "Skipping missing block definition"),
         }
     }
     definitions
 }
 
-fn register_blocks(
-    block_definitions: Vec<BlockDefinition>,
+/// Registers the block definitions, keeping the mapped IDs of blocks already registered.
+fn register_block_definitions(
+    block_definitions: Vec<(Option<String>, BlockDefinition)>,
     registry: &mut BlockRegistry,
-    block_textures: &mut BlockAppearances,
-    prepared_textures: Option<&PreparedTextures>,
 ) {
-    for block_definition in block_definitions {
+    for (pack, block_definition) in block_definitions {
         tracing::debug!(?block_definition, "Block definition found");
-        let appearances = build_face_appearances(&block_definition, prepared_textures);
+        let state_count = block_definition.0.state_count();
+        if state_count > MAX_BLOCK_STATES {
+            tracing::error!(
+                block_id = ?block_definition.0.id,
+                state_count,
+                "Block has too many states, only the first {MAX_BLOCK_STATES} will be shown"
+            );
+        }
+        if let Some(mapped_id) = registry.definitions.mapped_id(&block_definition.0.id)
+            && registry.definitions.get(&mapped_id).0.visibility != block_definition.0.visibility
+        {
+            tracing::warn!(
+                block_id = ?block_definition.0.id,
+                "Block visibility changed, this only applies to chunks generated from now on"
+            );
+        }
         let mapped_id = registry
             .definitions
-            .add(block_definition)
+            .set(block_definition)
             .expect("should have been able to map all block IDs");
-        block_textures.add(mapped_id, appearances);
+        registry.definitions.set_pack(mapped_id, pack);
+    }
+}
+
+/// Records the appearances of every registered block, including any no longer defined since an
+/// earlier load.
+fn register_block_appearances(
+    registry: &BlockRegistry,
+    appearances: &mut BlockAppearances,
+    block_textures: Option<&BlockTextures>,
+) {
+    for (mapped_id, block_definition) in registry.definitions.iter() {
+        for state in 0..block_definition.0.state_count().min(MAX_BLOCK_STATES) {
+            let state = state as BlockStateID;
+            let appearance = build_face_appearances(block_definition, state, block_textures);
+            let block = BlockState {
+                block: mapped_id,
+                state,
+            };
+            appearances.add(block, appearance);
+            for face in block_definition.0.tint(state) {
+                appearances.add_tinted(block, face);
+            }
+            let Some(block_textures) = block_textures else {
+                continue;
+            };
+            for (face, variation, choices) in face_variations(block_definition, state) {
+                let choices = choices
+                    .iter()
+                    .filter_map(|(slot, weight)| {
+                        Some((*block_textures.appearances.get(slot)?, *weight))
+                    })
+                    .collect();
+                appearances.add_variation(
+                    block,
+                    face,
+                    FaceVariation {
+                        choices,
+                        rotate: variation.rotate,
+                        flip: variation.flip,
+                    },
+                );
+            }
+            for (face, slot) in face_slots(block_definition, state) {
+                if let Some(tiles) = block_textures.connected_tiles.get(&slot) {
+                    appearances.add_connected(block, face, tiles.clone());
+                }
+            }
+        }
     }
 }
 
 fn build_face_appearances(
     block_definition: &BlockDefinition,
-    prepared_textures: Option<&PreparedTextures>,
+    state: BlockStateID,
+    block_textures: Option<&BlockTextures>,
 ) -> StaticCopyMap<Face, FaceAppearance> {
-    let mut appearances = default_face_appearances(block_definition);
+    let (color, _) = block_definition.0.appearance(state);
+    let mut appearances = default_face_appearances(color);
 
-    if let (Some(textures), Some(texture_paths)) =
-        (prepared_textures, block_definition.0.textures.as_ref())
-    {
-        for face in Face::iter() {
-            if let Some(texture_name) = texture_paths.get(&face)
-                && let Some(coords) = textures.coords_for_name(texture_name.as_str())
-            {
+    if let Some(block_textures) = block_textures {
+        for (face, slot) in face_slots(block_definition, state) {
+            if let Some(&appearance) = block_textures.appearances.get(&slot) {
                 tracing::debug!(
                     ?face,
                     block_id = ?block_definition.0.id,
+                    state,
                     "Found specific texture"
                 );
-                appearances[face] = FaceAppearance::Texture { coords };
+                appearances[face] = appearance;
             }
         }
     }
@@ -225,10 +609,8 @@ fn build_face_appearances(
     appearances
 }
 
-fn default_face_appearances(
-    block_definition: &BlockDefinition,
-) -> StaticCopyMap<Face, FaceAppearance> {
-    let color = block_color(block_definition);
+fn default_face_appearances(color: BlockColor) -> StaticCopyMap<Face, FaceAppearance> {
+    let color = block_color(color);
     static_copy_map! {
         Face::Top => color,
         Face::Bottom => color,
@@ -239,31 +621,73 @@ fn default_face_appearances(
     }
 }
 
-fn block_color(block_definition: &BlockDefinition) -> FaceAppearance {
+fn block_color(color: BlockColor) -> FaceAppearance {
     FaceAppearance::Color {
-        r: block_definition.0.color[0] as f32 / 255.,
-        g: block_definition.0.color[1] as f32 / 255.,
-        b: block_definition.0.color[2] as f32 / 255.,
-        a: block_definition.0.color[3] as f32 / 255.,
+        r: color[0] as f32 / 255.,
+        g: color[1] as f32 / 255.,
+        b: color[2] as f32 / 255.,
+        a: color[3] as f32 / 255.,
     }
 }
 
+/// Creates the block materials, or points the existing ones at the new textures and palette so
+/// that already spawned chunks pick them up.
 fn configure_materials(
     registry: &mut BlockRegistry,
-    materials: &mut Assets<StandardMaterial>,
-    texture_atlas: Option<Handle<Image>>,
+    materials: &mut Assets<VoxelMaterial>,
+    textures: &mut Assets<Image>,
+    block_textures: Option<&BlockTextures>,
 ) {
-    registry.materials[BlockVisibility::Opaque as usize] = materials.add(StandardMaterial {
-        base_color: Color::WHITE,
-        perceptual_roughness: 0.75,
-        reflectance: 0.25,
-        base_color_texture: texture_atlas,
+    let mut extension = VoxelExtension {
+        palette: Some(textures.add(palette_image(registry.appearances.palette()))),
+        animations: Some(textures.add(animations_image(registry.appearances.animations()))),
         ..default()
-    });
+    };
+    if let Some(block_textures) = block_textures {
+        extension.textures = Some(block_textures.textures.clone());
+        extension.normal_maps = block_textures.maps.get(&MaterialMap::Normal).cloned();
+        extension.metallic_roughness_maps = block_textures
+            .maps
+            .get(&MaterialMap::MetallicRoughness)
+            .cloned();
+        extension.emissive_maps = block_textures.maps.get(&MaterialMap::Emissive).cloned();
+        extension.settings = VoxelSettings {
+            atlas_size: block_textures.atlas_size.unwrap_or_default().as_vec2(),
+            texture_size: block_textures.texture_size.as_vec2(),
+        };
+    }
 
-    registry.materials[BlockVisibility::Translucent as usize] = materials.add(StandardMaterial {
-        base_color: Color::WHITE,
-        alpha_mode: AlphaMode::Blend,
-        ..default()
-    });
+    let opaque = &mut registry.materials[BlockVisibility::Opaque as usize];
+    // the default handles point at Bevy's placeholder material, which mustn't be changed
+    if opaque.is_strong()
+        && let Some(material) = materials.get_mut(&*opaque)
+    {
+        material.extension = extension.clone();
+    } else {
+        *opaque = materials.add(VoxelMaterial {
+            base: StandardMaterial {
+                base_color: Color::WHITE,
+                perceptual_roughness: ROUGHNESS,
+                reflectance: 0.25,
+                ..default()
+            },
+            extension: extension.clone(),
+        });
+    }
+
+    let translucent = &mut registry.materials[BlockVisibility::Translucent as usize];
+    if translucent.is_strong()
+        && let Some(material) = materials.get_mut(&*translucent)
+    {
+        material.extension = extension;
+    } else {
+        *translucent = materials.add(VoxelMaterial {
+            base: StandardMaterial {
+                base_color: Color::WHITE,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            },
+            extension,
+        });
+    }
 }
@@ -0,0 +1,524 @@
+//! Builds a texture array with a layer per block texture, or a texture atlas with the same
+//! textures laid out in a grid.
+//!
+//! Each layer of an array has its own mipmaps, so unlike the atlas, textures can be mipmapped
+//! without bleeding into each other.
+use std::fmt;
+
+use bevy::asset::RenderAssetUsages;
+use bevy::image::{ImageFilterMode, ImageSampler, ImageSamplerDescriptor};
+use bevy::prelude::*;
+use bevy::render::render_resource::{
+    Extent3d, TextureDimension, TextureFormat, TextureViewDescriptor, TextureViewDimension,
+};
+
+/// Textures are read as this format, whatever the colour space of the array.
+const SOURCE_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+const BYTES_PER_PIXEL: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextureArrayError {
+    /// The texture couldn't be converted to RGBA.
+    UnsupportedFormat(TextureFormat),
+    /// The texture isn't the same size as the textures already in the array.
+    SizeMismatch { expected: UVec2, actual: UVec2 },
+    /// The texture can't be split into the given number of frames of equal height.
+    UnevenFrames { height: u32, frames: u32 },
+    /// The texture can't be split into a grid of tiles of equal size.
+    UnevenTiles {
+        size: UVec2,
+        columns: u32,
+        rows: u32,
+    },
+}
+
+impl fmt::Display for TextureArrayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedFormat(format) => {
+                write!(f, "textures of format {format:?} aren't supported")
+            }
+            Self::SizeMismatch { expected, actual } => write!(
+                f,
+                "texture is {}x{}, but other textures are {}x{}",
+                actual.x, actual.y, expected.x, expected.y
+            ),
+            Self::UnevenFrames { height, frames } => write!(
+                f,
+                "texture is {height} pixels tall, which can't be split into {frames} frames"
+            ),
+            Self::UnevenTiles {
+                size,
+                columns,
+                rows,
+            } => write!(
+                f,
+                "texture is {}x{}, which can't be split into {columns}x{rows} tiles",
+                size.x, size.y
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TextureArrayError {}
+
+#[derive(Debug)]
+pub struct TextureArrayBuilder {
+    /// Whether textures hold colours in sRGB, rather than linear data such as normals.
+    srgb: bool,
+    /// Whether built textures with a single layer get an empty second one.
+    pad_single_layer: bool,
+    size: Option<UVec2>,
+    layers: Vec<Vec<u8>>,
+}
+
+impl Default for TextureArrayBuilder {
+    fn default() -> Self {
+        Self {
+            srgb: true,
+            pad_single_layer: false,
+            size: None,
+            layers: vec![],
+        }
+    }
+}
+
+impl TextureArrayBuilder {
+    /// A builder for textures holding linear data rather than colours, such as normal maps.
+    pub fn linear() -> Self {
+        Self {
+            srgb: false,
+            ..default()
+        }
+    }
+
+    /// Gives built textures with a single layer an empty second one if `pad` is true, as the GL
+    /// backend treats textures with a single layer as plain 2D textures, which can't be bound in
+    /// place of an array.
+    #[must_use]
+    pub const fn pad_single_layer(mut self, pad: bool) -> Self {
+        self.pad_single_layer = pad;
+        self
+    }
+
+    /// Adds a texture as the next layer, returning its index.
+    pub fn add(&mut self, texture: &Image) -> Result<u32, TextureArrayError> {
+        self.add_frames(texture, 1)
+    }
+
+    /// Splits a texture into `frames` stacked from top to bottom, adding each as the next layer.
+    /// Returns the index of the first.
+    pub fn add_frames(&mut self, texture: &Image, frames: u32) -> Result<u32, TextureArrayError> {
+        let size = texture.size();
+        if frames == 0 || !size.y.is_multiple_of(frames) {
+            return Err(TextureArrayError::UnevenFrames {
+                height: size.y,
+                frames,
+            });
+        }
+        let frame_size = UVec2::new(size.x, size.y / frames);
+        self.check_size(frame_size)?;
+        let format = texture.texture_descriptor.format;
+        let data = texture
+            .convert(SOURCE_FORMAT)
+            .and_then(|converted| converted.data)
+            .ok_or(TextureArrayError::UnsupportedFormat(format))?;
+        let frame_len = frame_size.element_product() as usize * BYTES_PER_PIXEL;
+        let first = self.layers.len() as u32;
+        for frame in data.chunks_exact(frame_len) {
+            self.push(frame_size, frame.to_vec());
+        }
+        Ok(first)
+    }
+
+    /// Adds a texture made of `count` tiles, laid out `columns` to a row from left to right then
+    /// top to bottom, as consecutive layers, returning the index of the first. Any cells of the
+    /// last row after the last tile are left out.
+    pub fn add_tiles(
+        &mut self,
+        texture: &Image,
+        columns: u32,
+        count: u32,
+    ) -> Result<u32, TextureArrayError> {
+        let size = texture.size();
+        let rows = count.div_ceil(columns.max(1));
+        if columns == 0
+            || count == 0
+            || !size.x.is_multiple_of(columns)
+            || !size.y.is_multiple_of(rows)
+        {
+            return Err(TextureArrayError::UnevenTiles {
+                size,
+                columns,
+                rows,
+            });
+        }
+        let tile_size = size / UVec2::new(columns, rows);
+        self.check_size(tile_size)?;
+        let format = texture.texture_descriptor.format;
+        let data = texture
+            .convert(SOURCE_FORMAT)
+            .and_then(|converted| converted.data)
+            .ok_or(TextureArrayError::UnsupportedFormat(format))?;
+        let row_len = tile_size.x as usize * BYTES_PER_PIXEL;
+        let first = self.layers.len() as u32;
+        for tile in 0..count {
+            let corner = UVec2::new(tile % columns, tile / columns) * tile_size;
+            let mut layer = Vec::with_capacity(row_len * tile_size.y as usize);
+            for y in corner.y..corner.y + tile_size.y {
+                let start = (y * size.x + corner.x) as usize * BYTES_PER_PIXEL;
+                layer.extend_from_slice(&data[start..start + row_len]);
+            }
+            self.push(tile_size, layer);
+        }
+        Ok(first)
+    }
+
+    /// Adds a layer of `size` filled with `pixel`, returning its index.
+    pub fn add_solid(&mut self, size: UVec2, pixel: [u8; 4]) -> Result<u32, TextureArrayError> {
+        self.check_size(size)?;
+        Ok(self.push(size, pixel.repeat(size.element_product() as usize)))
+    }
+
+    fn check_size(&self, size: UVec2) -> Result<(), TextureArrayError> {
+        match self.size {
+            Some(expected) if expected != size => Err(TextureArrayError::SizeMismatch {
+                expected,
+                actual: size,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn push(&mut self, size: UVec2, data: Vec<u8>) -> u32 {
+        self.size = Some(size);
+        self.layers.push(data);
+        self.layers.len() as u32 - 1
+    }
+
+    const fn format(&self) -> TextureFormat {
+        if self.srgb {
+            TextureFormat::Rgba8UnormSrgb
+        } else {
+            TextureFormat::Rgba8Unorm
+        }
+    }
+
+    /// The texture array, or `None` if no textures were added.
+    pub fn build(mut self) -> Option<Image> {
+        let size = self.size?;
+        if self.pad_single_layer && self.layers.len() == 1 {
+            self.layers
+                .push(vec![0; size.element_product() as usize * BYTES_PER_PIXEL]);
+        }
+        let mut image = Image::new_uninit(
+            Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: self.layers.len() as u32,
+            },
+            TextureDimension::D2,
+            self.format(),
+            RenderAssetUsages::default(),
+        );
+        let mip_levels = mip_level_count(size);
+        image.texture_descriptor.mip_level_count = mip_levels;
+        // layers are laid out one after the other, each with all of its mip levels
+        let mut data = vec![];
+        for layer in self.layers {
+            let mut level = (layer, size);
+            for _ in 1..mip_levels {
+                let next = downsample(&level.0, level.1, self.srgb);
+                data.append(&mut level.0);
+                level = next;
+            }
+            data.append(&mut level.0);
+        }
+        image.data = Some(data);
+        image.texture_view_descriptor = Some(TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::D2Array),
+            ..default()
+        });
+        // blocks up close keep their pixelated look, but distant blocks are smoothed
+        image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+            mag_filter: ImageFilterMode::Nearest,
+            min_filter: ImageFilterMode::Linear,
+            mipmap_filter: ImageFilterMode::Linear,
+            ..default()
+        });
+        Some(image)
+    }
+
+    /// Lays the textures out in a grid in a texture atlas instead, without mipmaps, or returns
+    /// `None` if no textures were added.
+    ///
+    /// Each texture goes in the cell of the grid with its index in the array, counting from left
+    /// to right then top to bottom. The atlas is viewed as an array with one layer, so that it can
+    /// be bound in place of a texture array.
+    pub fn build_atlas(self) -> Option<Image> {
+        let size = self.size?;
+        let count = self.layers.len() as u32;
+        let columns = (count - 1).isqrt() + 1;
+        let atlas_size = size * UVec2::new(columns, count.div_ceil(columns));
+        let corners: Vec<_> = (0..count)
+            .map(|index| size * UVec2::new(index % columns, index / columns))
+            .collect();
+
+        let row_len = size.x as usize * BYTES_PER_PIXEL;
+        let atlas_row_len = atlas_size.x as usize * BYTES_PER_PIXEL;
+        let layers = if self.pad_single_layer { 2 } else { 1 };
+        let mut data = vec![0; layers * atlas_row_len * atlas_size.y as usize];
+        for (layer, corner) in self.layers.iter().zip(&corners) {
+            for (y, row) in layer.chunks_exact(row_len).enumerate() {
+                let start =
+                    (corner.y as usize + y) * atlas_row_len + corner.x as usize * BYTES_PER_PIXEL;
+                data[start..start + row_len].copy_from_slice(row);
+            }
+        }
+
+        let mut image = Image::new(
+            Extent3d {
+                width: atlas_size.x,
+                height: atlas_size.y,
+                depth_or_array_layers: layers as u32,
+            },
+            TextureDimension::D2,
+            data,
+            self.format(),
+            RenderAssetUsages::default(),
+        );
+        image.texture_view_descriptor = Some(TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::D2Array),
+            ..default()
+        });
+        Some(image)
+    }
+}
+
+/// Number of mip levels down to 1x1.
+const fn mip_level_count(size: UVec2) -> u32 {
+    let largest = if size.x > size.y { size.x } else { size.y };
+    u32::BITS - largest.leading_zeros()
+}
+
+/// Halves an RGBA image, averaging each 2x2 block of pixels in linear space.
+fn downsample(data: &[u8], size: UVec2, srgb: bool) -> (Vec<u8>, UVec2) {
+    let half = (size / 2).max(UVec2::ONE);
+    let mut out = Vec::with_capacity(half.element_product() as usize * BYTES_PER_PIXEL);
+    for y in 0..half.y {
+        for x in 0..half.x {
+            let mut sum = [0.; BYTES_PER_PIXEL];
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let sx = (x * 2 + dx).min(size.x - 1);
+                let sy = (y * 2 + dy).min(size.y - 1);
+                let i = (sy * size.x + sx) as usize * BYTES_PER_PIXEL;
+                for (channel, total) in sum.iter_mut().enumerate() {
+                    let value = data[i + channel] as f32 / 255.;
+                    *total += if channel == 3 || !srgb {
+                        value
+                    } else {
+                        srgb_to_linear(value)
+                    };
+                }
+            }
+            for (channel, total) in sum.into_iter().enumerate() {
+                let value = total / 4.;
+                let value = if channel == 3 || !srgb {
+                    value
+                } else {
+                    linear_to_srgb(value)
+                };
+                out.push((value * 255.).round() as u8);
+            }
+        }
+    }
+    (out, half)
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055f32.mul_add(value.powf(1. / 2.4), -0.055)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texture(width: u32, height: u32, pixel: [u8; 4]) -> Image {
+        Image::new_fill(
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &pixel,
+            SOURCE_FORMAT,
+            RenderAssetUsages::default(),
+        )
+    }
+
+    #[test]
+    fn mip_levels_go_down_to_one_pixel() {
+        assert_eq!(mip_level_count(UVec2::new(64, 64)), 7);
+        assert_eq!(mip_level_count(UVec2::new(16, 4)), 5);
+        assert_eq!(mip_level_count(UVec2::ONE), 1);
+    }
+
+    #[test]
+    fn downsampling_averages_in_linear_space() {
+        let black_and_white = [
+            0, 0, 0, 255, 255, 255, 255, 255, 0, 0, 0, 255, 255, 255, 255, 0,
+        ];
+        let (half, size) = downsample(&black_and_white, UVec2::new(2, 2), true);
+        assert_eq!(size, UVec2::ONE);
+        // a naive average would give 128, which is too dark
+        assert_eq!(half, [188, 188, 188, 191]);
+        // unless the texture isn't a colour
+        let (half, _) = downsample(&black_and_white, UVec2::new(2, 2), false);
+        assert_eq!(half, [128, 128, 128, 191]);
+
+        let (half, size) = downsample(&[10; 4 * 4], UVec2::new(4, 1), true);
+        assert_eq!(size, UVec2::new(2, 1));
+        assert_eq!(half, [10; 2 * 4]);
+    }
+
+    #[test]
+    fn layers_have_all_their_mip_levels() {
+        let mut builder = TextureArrayBuilder::default();
+        assert_eq!(builder.add(&texture(4, 4, [255, 0, 0, 255])), Ok(0));
+        assert_eq!(builder.add(&texture(4, 4, [0, 0, 255, 255])), Ok(1));
+        assert_eq!(
+            builder.add(&texture(8, 8, [0, 255, 0, 255])),
+            Err(TextureArrayError::SizeMismatch {
+                expected: UVec2::new(4, 4),
+                actual: UVec2::new(8, 8),
+            })
+        );
+
+        let image = builder.build().unwrap();
+        assert_eq!(image.texture_descriptor.size.depth_or_array_layers, 2);
+        assert_eq!(image.texture_descriptor.mip_level_count, 3);
+        let data = image.data.unwrap();
+        let layer_len = (16 + 4 + 1) * BYTES_PER_PIXEL;
+        assert_eq!(data.len(), 2 * layer_len);
+        assert_eq!(data[layer_len - 4..layer_len], [255, 0, 0, 255]);
+        assert_eq!(data[2 * layer_len - 4..], [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn strips_are_split_into_frames() {
+        let mut strip = texture(2, 3, [0; 4]);
+        let data = strip.data.as_mut().unwrap();
+        data[8..16].fill(1);
+        data[16..].fill(2);
+
+        let mut builder = TextureArrayBuilder::linear();
+        builder.add_solid(UVec2::new(2, 1), [9; 4]).unwrap();
+        assert_eq!(builder.add_frames(&strip, 3), Ok(1));
+        assert_eq!(
+            builder.add_frames(&strip, 2),
+            Err(TextureArrayError::UnevenFrames {
+                height: 3,
+                frames: 2
+            })
+        );
+        assert_eq!(
+            builder.add_frames(&strip, 1),
+            Err(TextureArrayError::SizeMismatch {
+                expected: UVec2::new(2, 1),
+                actual: UVec2::new(2, 3),
+            })
+        );
+        assert_eq!(builder.layers.len(), 4);
+        for (frame, layer) in builder.layers[1..].iter().enumerate() {
+            assert_eq!(*layer, [frame as u8; 8]);
+        }
+    }
+
+    #[test]
+    fn grids_are_split_into_tiles() {
+        // 2x2 tiles of 1x2 pixels, each filled with its index
+        let mut grid = texture(2, 4, [0; 4]);
+        let data = grid.data.as_mut().unwrap();
+        for (pixel, value) in data.chunks_exact_mut(4).zip([0, 1, 0, 1, 2, 3, 2, 3]) {
+            pixel.fill(value);
+        }
+
+        let mut builder = TextureArrayBuilder::linear();
+        assert_eq!(builder.add_tiles(&grid, 2, 3), Ok(0));
+        assert_eq!(
+            builder.add_tiles(&grid, 3, 3),
+            Err(TextureArrayError::UnevenTiles {
+                size: UVec2::new(2, 4),
+                columns: 3,
+                rows: 1,
+            })
+        );
+        assert_eq!(builder.layers.len(), 3);
+        for (tile, layer) in builder.layers.iter().enumerate() {
+            assert_eq!(*layer, [tile as u8; 8]);
+        }
+    }
+
+    #[test]
+    fn atlases_lay_textures_out_in_a_grid() {
+        let mut builder = TextureArrayBuilder::linear();
+        for value in 0..5 {
+            builder.add_solid(UVec2::new(2, 1), [value; 4]).unwrap();
+        }
+        assert_eq!(
+            builder.add_solid(UVec2::ONE, [0; 4]),
+            Err(TextureArrayError::SizeMismatch {
+                expected: UVec2::new(2, 1),
+                actual: UVec2::ONE,
+            })
+        );
+
+        let image = builder.build_atlas().unwrap();
+        assert_eq!(image.size(), UVec2::new(6, 2));
+        assert_eq!(image.texture_descriptor.size.depth_or_array_layers, 1);
+        assert_eq!(image.texture_descriptor.format, TextureFormat::Rgba8Unorm);
+        let data = image.data.unwrap();
+        let row: Vec<u8> = [0, 0, 1, 1, 2, 2].iter().flat_map(|&v| [v; 4]).collect();
+        assert_eq!(data[..6 * 4], row);
+        let row: Vec<u8> = [3, 3, 4, 4, 0, 0].iter().flat_map(|&v| [v; 4]).collect();
+        assert_eq!(data[6 * 4..], row);
+    }
+
+    #[test]
+    fn single_layers_can_be_padded() {
+        let single = || {
+            let mut builder = TextureArrayBuilder::default();
+            builder.add(&texture(2, 2, [255; 4])).unwrap();
+            builder
+        };
+        let layers = |image: Image| image.texture_descriptor.size.depth_or_array_layers;
+        assert_eq!(layers(single().build().unwrap()), 1);
+        assert_eq!(layers(single().pad_single_layer(true).build().unwrap()), 2);
+        let atlas = single().pad_single_layer(true).build_atlas().unwrap();
+        assert_eq!(atlas.data.as_ref().unwrap()[2 * 2 * 4..], [0; 2 * 2 * 4]);
+        assert_eq!(layers(atlas), 2);
+
+        let mut builder = single().pad_single_layer(true);
+        builder.add(&texture(2, 2, [0; 4])).unwrap();
+        assert_eq!(layers(builder.build().unwrap()), 2);
+    }
+
+    #[test]
+    fn empty_arrays_are_not_built() {
+        assert!(TextureArrayBuilder::default().build().is_none());
+        assert!(TextureArrayBuilder::default().build_atlas().is_none());
+    }
+}
@@ -0,0 +1,744 @@
+//! Checks block definitions and textures for mistakes that would otherwise be silently worked
+//! around when they are loaded, e.g. a face falling back to its colour because its texture is
+//! missing.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+
+use bevy::asset::RenderAssetUsages;
+use bevy::image::{CompressedImageFormats, Image, ImageSampler, ImageType};
+use infinigen_common::blocks::{BlockType, BlockVisibility, Face, MAX_LIGHT_EMISSION, MaterialMap};
+use infinigen_common::mesh::connected::{CONNECTED_COLUMNS, CONNECTED_TILES};
+use strum::IntoEnumIterator;
+
+use crate::assets::packs::ResourcePacks;
+use crate::registry::BlockDefinition;
+
+pub const BLOCK_TYPES_DIR: &str = "blocks/types";
+pub const BLOCK_TEXTURES_DIR: &str = "blocks/textures";
+const BLOCK_TYPE_EXTENSION: &str = ".block.ron";
+const TEXTURE_EXTENSION: &str = ".png";
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AssetIssue {
+    /// A block definition file couldn't be read or parsed.
+    Unreadable { source: String, reason: String },
+    /// More than one definition has the same ID, only the first of them will be registered.
+    DuplicateId {
+        block_id: String,
+        sources: Vec<String>,
+    },
+    /// A block ID or tag that isn't of the form `namespace:name`.
+    MalformedNamespace { block_id: String, name: String },
+    /// A texture named by a block which doesn't exist, so its colour will be used instead.
+    MissingTexture { block_id: String, texture: String },
+    /// A texture which isn't named by any block.
+    UnusedTexture(String),
+    /// A block which emits more light than [`MAX_LIGHT_EMISSION`].
+    InvalidLightEmission {
+        block_id: String,
+        light_emission: u8,
+    },
+    /// A translucent block whose colour has no transparency, and which has faces without a
+    /// texture.
+    OpaqueTranslucent { block_id: String },
+    /// A material map for a face which never has a colour texture, so it won't be used.
+    UntexturedMap { block_id: String, face: Face },
+    /// An animation without any frames, or whose frames take no time.
+    InvalidAnimation { block_id: String, texture: String },
+    /// An animation of a texture which isn't on any face of the block, so it won't be used.
+    UnusedAnimation { block_id: String, texture: String },
+    /// A variation which picks between textures whose weights are all 0.
+    InvalidVariation { block_id: String, texture: String },
+    /// A variation of a texture which isn't on any face of the block, so it won't be used.
+    UnusedVariation { block_id: String, texture: String },
+    /// A variation on a translucent block, whose faces don't vary, so it won't be used.
+    IgnoredVariation { block_id: String, texture: String },
+    /// A connected texture which is also animated or varied, which it can't be.
+    InvalidConnection { block_id: String, texture: String },
+    /// A connected texture which isn't on any face of the block, so it won't be used.
+    UnusedConnection { block_id: String, texture: String },
+    /// A texture, or a frame or tile of one, which isn't the same size as the textures before it
+    /// by name, so its faces will show their colour instead.
+    TextureSizeMismatch {
+        texture: String,
+        size: [u32; 2],
+        expected: [u32; 2],
+    },
+}
+
+impl AssetIssue {
+    /// Whether the issue means assets won't look or behave as defined, rather than just being
+    /// suspicious.
+    pub const fn is_error(&self) -> bool {
+        match self {
+            Self::Unreadable { .. }
+            | Self::DuplicateId { .. }
+            | Self::MalformedNamespace { .. }
+            | Self::MissingTexture { .. }
+            | Self::InvalidLightEmission { .. }
+            | Self::InvalidAnimation { .. }
+            | Self::InvalidVariation { .. }
+            | Self::InvalidConnection { .. }
+            | Self::TextureSizeMismatch { .. } => true,
+            Self::UnusedTexture(_)
+            | Self::OpaqueTranslucent { .. }
+            | Self::UntexturedMap { .. }
+            | Self::UnusedAnimation { .. }
+            | Self::UnusedVariation { .. }
+            | Self::IgnoredVariation { .. }
+            | Self::UnusedConnection { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for AssetIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unreadable { source, reason } => write!(f, "couldn't read {source}: {reason}"),
+            Self::DuplicateId { block_id, sources } => {
+                write!(f, "{block_id} is defined more than once, in {sources:?}")
+            }
+            Self::MalformedNamespace { block_id, name } if block_id == name => {
+                write!(
+                    f,
+                    "block ID '{name}' should be of the form 'namespace:name'"
+                )
+            }
+            Self::MalformedNamespace { block_id, name } => write!(
+                f,
+                "tag '{name}' of {block_id} should be of the form 'namespace:name'"
+            ),
+            Self::MissingTexture { block_id, texture } => {
+                write!(f, "{block_id} uses texture '{texture}' which doesn't exist")
+            }
+            Self::UnusedTexture(texture) => {
+                write!(f, "texture '{texture}' isn't used by any block")
+            }
+            Self::InvalidLightEmission {
+                block_id,
+                light_emission,
+            } => write!(
+                f,
+                "{block_id} emits light at {light_emission}, above the maximum of \
+                 {MAX_LIGHT_EMISSION}"
+            ),
+            Self::OpaqueTranslucent { block_id } => write!(
+                f,
+                "{block_id} is translucent but its colour has an alpha of 255"
+            ),
+            Self::UntexturedMap { block_id, face } => write!(
+                f,
+                "{block_id} has a material map for its {face:?} face, which has no texture"
+            ),
+            Self::InvalidAnimation { block_id, texture } => write!(
+                f,
+                "{block_id} animates texture '{texture}' with no frames or a frame time of 0"
+            ),
+            Self::UnusedAnimation { block_id, texture } => write!(
+                f,
+                "{block_id} animates texture '{texture}', which none of its faces use"
+            ),
+            Self::InvalidVariation { block_id, texture } => write!(
+                f,
+                "{block_id} varies texture '{texture}' between textures which all have a weight of 0"
+            ),
+            Self::UnusedVariation { block_id, texture } => write!(
+                f,
+                "{block_id} varies texture '{texture}', which none of its faces use"
+            ),
+            Self::IgnoredVariation { block_id, texture } => write!(
+                f,
+                "{block_id} varies texture '{texture}', but faces of translucent blocks don't vary"
+            ),
+            Self::InvalidConnection { block_id, texture } => write!(
+                f,
+                "{block_id} connects texture '{texture}', which can't also be animated or varied"
+            ),
+            Self::UnusedConnection { block_id, texture } => write!(
+                f,
+                "{block_id} connects texture '{texture}', which none of its faces use"
+            ),
+            Self::TextureSizeMismatch {
+                texture,
+                size: [width, height],
+                expected: [expected_width, expected_height],
+            } => write!(
+                f,
+                "texture '{texture}' is {width}x{height} per frame or tile, but the textures \
+                 before it are {expected_width}x{expected_height}"
+            ),
+        }
+    }
+}
+
+/// Checks `definitions`, each with a description of where it came from, against each other and
+/// the available `textures` with their sizes in pixels. Texture checks are skipped if `textures`
+/// is `None`.
+pub fn validate<'a>(
+    definitions: impl IntoIterator<Item = (&'a str, &'a BlockType)>,
+    textures: Option<&BTreeMap<String, [u32; 2]>>,
+) -> Vec<AssetIssue> {
+    let mut issues = vec![];
+    let mut sources_by_id: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    let mut used_textures = BTreeSet::new();
+    // size of each frame or tile of the textures shown on faces
+    let mut cell_sizes: BTreeMap<&str, [u32; 2]> = BTreeMap::new();
+
+    for (source, block_type) in definitions {
+        let block_id = block_type.id.as_str();
+        sources_by_id
+            .entry(block_id)
+            .or_default()
+            .push(source.to_owned());
+
+        for name in std::iter::once(block_id).chain(block_type.tags.iter().map(String::as_str)) {
+            if !is_namespaced(name) {
+                issues.push(AssetIssue::MalformedNamespace {
+                    block_id: block_id.to_owned(),
+                    name: name.to_owned(),
+                });
+            }
+        }
+
+        if block_type.light_emission > MAX_LIGHT_EMISSION {
+            issues.push(AssetIssue::InvalidLightEmission {
+                block_id: block_id.to_owned(),
+                light_emission: block_type.light_emission,
+            });
+        }
+
+        // the colour isn't used if every face is textured
+        let fully_textured = block_type
+            .textures
+            .as_ref()
+            .is_some_and(|textures| Face::iter().all(|face| textures.contains_key(&face)));
+        if block_type.visibility == BlockVisibility::Translucent
+            && !fully_textured
+            && std::iter::once(block_type.color)
+                .chain(block_type.variants.iter().filter_map(|v| v.color))
+                .any(|color| color[3] == u8::MAX)
+        {
+            issues.push(AssetIssue::OpaqueTranslucent {
+                block_id: block_id.to_owned(),
+            });
+        }
+
+        let face_textures = || {
+            block_type.textures.iter().chain(
+                block_type
+                    .variants
+                    .iter()
+                    .filter_map(|v| v.textures.as_ref()),
+            )
+        };
+        let maps =
+            || std::iter::once(&block_type.maps).chain(block_type.variants.iter().map(|v| &v.maps));
+
+        let textured_faces: BTreeSet<Face> = face_textures()
+            .flat_map(|textures| textures.keys().copied())
+            .collect();
+        let mapped_faces: BTreeSet<Face> = maps()
+            .flat_map(|maps| MaterialMap::iter().flat_map(|map| maps.get(map).keys().copied()))
+            .collect();
+        for &face in mapped_faces.difference(&textured_faces) {
+            issues.push(AssetIssue::UntexturedMap {
+                block_id: block_id.to_owned(),
+                face,
+            });
+        }
+
+        // alternatives are only shown on faces whose own texture varies
+        let face_texture_names: BTreeSet<&str> = face_textures()
+            .flat_map(|textures| textures.values().map(String::as_str))
+            .collect();
+        let shown_textures: BTreeSet<&str> = face_texture_names
+            .iter()
+            .copied()
+            .chain(
+                block_type
+                    .variation
+                    .iter()
+                    .filter(|(texture, _)| face_texture_names.contains(texture.as_str()))
+                    .flat_map(|(_, variation)| variation.textures.iter())
+                    .map(|(texture, _)| texture.as_str()),
+            )
+            .collect();
+
+        for (texture, variation) in &block_type.variation {
+            if !variation.textures.is_empty()
+                && variation.textures.iter().all(|(_, weight)| *weight == 0)
+            {
+                issues.push(AssetIssue::InvalidVariation {
+                    block_id: block_id.to_owned(),
+                    texture: texture.clone(),
+                });
+            }
+            if !face_texture_names.contains(texture.as_str()) {
+                issues.push(AssetIssue::UnusedVariation {
+                    block_id: block_id.to_owned(),
+                    texture: texture.clone(),
+                });
+            } else if block_type.visibility == BlockVisibility::Translucent {
+                issues.push(AssetIssue::IgnoredVariation {
+                    block_id: block_id.to_owned(),
+                    texture: texture.clone(),
+                });
+            }
+        }
+
+        for texture in &block_type.connected {
+            if block_type.animations.contains_key(texture)
+                || block_type.variation.contains_key(texture)
+            {
+                issues.push(AssetIssue::InvalidConnection {
+                    block_id: block_id.to_owned(),
+                    texture: texture.clone(),
+                });
+            }
+            if !face_texture_names.contains(texture.as_str()) {
+                issues.push(AssetIssue::UnusedConnection {
+                    block_id: block_id.to_owned(),
+                    texture: texture.clone(),
+                });
+            }
+        }
+
+        for (texture, animation) in &block_type.animations {
+            if animation.frames == 0 || animation.frame_time_ms == 0 {
+                issues.push(AssetIssue::InvalidAnimation {
+                    block_id: block_id.to_owned(),
+                    texture: texture.clone(),
+                });
+            }
+            if !shown_textures.contains(texture.as_str()) {
+                issues.push(AssetIssue::UnusedAnimation {
+                    block_id: block_id.to_owned(),
+                    texture: texture.clone(),
+                });
+            }
+        }
+
+        for &texture in &shown_textures {
+            let Some(&[width, height]) = textures.and_then(|textures| textures.get(texture)) else {
+                continue;
+            };
+            let [columns, rows] = if block_type.connected.contains(texture) {
+                [
+                    CONNECTED_COLUMNS,
+                    CONNECTED_TILES.div_ceil(CONNECTED_COLUMNS),
+                ]
+            } else {
+                [
+                    1,
+                    block_type.animations.get(texture).map_or(1, |a| a.frames),
+                ]
+            };
+            // textures which can't be split up are skipped when they're loaded anyway
+            if columns > 0 && rows > 0 && width % columns == 0 && height % rows == 0 {
+                cell_sizes
+                    .entry(texture)
+                    .or_insert([width / columns, height / rows]);
+            }
+        }
+
+        let named_textures: BTreeSet<&str> = shown_textures
+            .into_iter()
+            .chain(maps().flat_map(|maps| maps.textures()))
+            .collect();
+        for texture in named_textures {
+            if let Some(textures) = textures
+                && !textures.contains_key(texture)
+            {
+                issues.push(AssetIssue::MissingTexture {
+                    block_id: block_id.to_owned(),
+                    texture: texture.to_owned(),
+                });
+            }
+            used_textures.insert(texture);
+        }
+    }
+
+    for (block_id, sources) in sources_by_id {
+        if sources.len() > 1 {
+            issues.push(AssetIssue::DuplicateId {
+                block_id: block_id.to_owned(),
+                sources,
+            });
+        }
+    }
+
+    // textures are added in order of name when they're loaded, so the first sets the size
+    if let Some((_, &expected)) = cell_sizes.first_key_value() {
+        for (texture, size) in cell_sizes {
+            if size != expected {
+                issues.push(AssetIssue::TextureSizeMismatch {
+                    texture: texture.to_owned(),
+                    size,
+                    expected,
+                });
+            }
+        }
+    }
+
+    if let Some(textures) = textures {
+        for texture in textures.keys() {
+            if !used_textures.contains(texture.as_str()) {
+                issues.push(AssetIssue::UnusedTexture(texture.clone()));
+            }
+        }
+    }
+
+    issues
+}
+
+/// Validates the block definitions and textures in an `assets` directory on disk, along with
+/// those in `packs`, as they would be loaded.
+pub fn validate_dir(assets: &Path, packs: &ResourcePacks) -> std::io::Result<Vec<AssetIssue>> {
+    let exists = |folder: &Path| assets.join(folder).is_dir();
+    let mut issues = vec![];
+    let mut definitions = vec![];
+    for folder in packs.layered(BLOCK_TYPES_DIR, exists).folders() {
+        for (name, path) in files_with_extension(&assets.join(folder), BLOCK_TYPE_EXTENSION)? {
+            let source = format!("{folder}/{name}");
+            let parsed = std::fs::read_to_string(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|contents| {
+                    ron::de::from_str::<BlockType>(&contents).map_err(|err| err.to_string())
+                });
+            match parsed {
+                Ok(block_type) => definitions.push((source, BlockDefinition(block_type))),
+                Err(reason) => issues.push(AssetIssue::Unreadable { source, reason }),
+            }
+        }
+    }
+    let definitions = packs.without_overridden(definitions);
+
+    // textures from later packs replace earlier ones with the same name
+    let mut textures = BTreeMap::new();
+    for folder in packs.layered(BLOCK_TEXTURES_DIR, exists).folders() {
+        for (name, path) in files_with_extension(&assets.join(folder), TEXTURE_EXTENSION)? {
+            let source = format!("{folder}/{name}");
+            let image = std::fs::read(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|bytes| {
+                    Image::from_buffer(
+                        &bytes,
+                        ImageType::Extension("png"),
+                        CompressedImageFormats::NONE,
+                        true,
+                        ImageSampler::Default,
+                        RenderAssetUsages::default(),
+                    )
+                    .map_err(|err| err.to_string())
+                });
+            match image {
+                Ok(image) => {
+                    let texture = name.trim_end_matches(TEXTURE_EXTENSION).to_owned();
+                    textures.insert(texture, image.size().to_array());
+                }
+                Err(reason) => issues.push(AssetIssue::Unreadable { source, reason }),
+            }
+        }
+    }
+
+    issues.extend(validate(
+        definitions
+            .iter()
+            .map(|(source, definition)| (source.as_str(), &definition.0)),
+        Some(&textures),
+    ));
+    Ok(issues)
+}
+
+/// File names and paths of the files in `dir` ending with `extension`, sorted by name.
+fn files_with_extension(
+    dir: &Path,
+    extension: &str,
+) -> std::io::Result<Vec<(String, std::path::PathBuf)>> {
+    let mut files = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if let Some(name) = path.file_name().and_then(|name| name.to_str())
+            && name.ends_with(extension)
+            && path.is_file()
+        {
+            files.push((name.to_owned(), path));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Whether `name` is of the form `namespace:name`, using only lowercase letters, digits, `_`,
+/// `-` and `.` (and `/` after the namespace).
+pub fn is_namespaced(name: &str) -> bool {
+    let valid = |part: &str, extra: &[char]| {
+        !part.is_empty()
+            && part.chars().all(|c| {
+                c.is_ascii_lowercase()
+                    || c.is_ascii_digit()
+                    || "_-.".contains(c)
+                    || extra.contains(&c)
+            })
+    };
+    name.split_once(':')
+        .is_some_and(|(namespace, path)| valid(namespace, &[]) && valid(path, &['/']))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use infinigen_common::blocks::{Animation, BlockVariant, MaterialMaps, TextureVariation};
+
+    use super::*;
+
+    fn block(id: &str, textures: &[(Face, &str)]) -> BlockType {
+        BlockType {
+            id: id.to_owned(),
+            textures: (!textures.is_empty()).then(|| {
+                textures
+                    .iter()
+                    .map(|(face, texture)| (*face, (*texture).to_owned()))
+                    .collect()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn namespaces() {
+        assert!(is_namespaced("infinigen:stone"));
+        assert!(is_namespaced("my-mod.v2:ores/copper_ore"));
+        assert!(!is_namespaced("stone"));
+        assert!(!is_namespaced(":stone"));
+        assert!(!is_namespaced("infinigen:"));
+        assert!(!is_namespaced("Infinigen:Stone"));
+        assert!(!is_namespaced("infinigen:stone:mossy"));
+        assert!(!is_namespaced("infinigen/blocks:stone"));
+    }
+
+    #[test]
+    fn reports_each_kind_of_issue() {
+        let mut stone = block("infinigen:stone", &[(Face::Top, "stone")]);
+        stone.maps.normal = BTreeMap::from([(Face::Top, "stone_normal".to_owned())]);
+        stone.maps.emissive = BTreeMap::from([(Face::Bottom, "glow".to_owned())]);
+        stone.variation = BTreeMap::from([
+            (
+                "stone".to_owned(),
+                TextureVariation {
+                    textures: vec![("stone".to_owned(), 3), ("stone_cracked".to_owned(), 1)],
+                    ..Default::default()
+                },
+            ),
+            (
+                "cobblestone".to_owned(),
+                TextureVariation {
+                    textures: vec![("cobblestone".to_owned(), 0)],
+                    ..Default::default()
+                },
+            ),
+        ]);
+        let mut grass = block("infinigen:grass", &[(Face::Top, "grass")]);
+        grass.variants.push(BlockVariant {
+            when: BTreeMap::from([("snowy".to_owned(), "true".to_owned())]),
+            color: None,
+            textures: Some(BTreeMap::from([(Face::Top, "snow".to_owned())])),
+            maps: MaterialMaps::default(),
+            tint: None,
+        });
+        grass.light_emission = MAX_LIGHT_EMISSION + 1;
+        grass.animations = BTreeMap::from([
+            (
+                "snow".to_owned(),
+                Animation {
+                    frames: 0,
+                    frame_time_ms: 100,
+                },
+            ),
+            (
+                "lava".to_owned(),
+                Animation {
+                    frames: 8,
+                    frame_time_ms: 100,
+                },
+            ),
+        ]);
+        let mut glass = block(
+            "infinigen:glass",
+            &Face::iter().map(|face| (face, "stone")).collect::<Vec<_>>(),
+        );
+        glass.visibility = BlockVisibility::Translucent;
+        glass.connected = BTreeSet::from(["stone".to_owned(), "glass".to_owned()]);
+        glass.animations = BTreeMap::from([(
+            "stone".to_owned(),
+            Animation {
+                frames: 47,
+                frame_time_ms: 100,
+            },
+        )]);
+        glass.variation = BTreeMap::from([(
+            "stone".to_owned(),
+            TextureVariation {
+                rotate: true,
+                ..Default::default()
+            },
+        )]);
+        let mut water = block("water", &[]);
+        water.visibility = BlockVisibility::Translucent;
+        water.tags.insert("fluid".to_owned());
+        let dirt = block("infinigen:dirt", &[(Face::Top, "wrong_size")]);
+        let textures = BTreeMap::from([
+            ("stone".to_owned(), [64, 64]),
+            ("stone_normal".to_owned(), [64, 64]),
+            ("grass".to_owned(), [64, 64]),
+            ("wrong_size".to_owned(), [16, 16]),
+            ("unknown".to_owned(), [64, 64]),
+        ]);
+
+        let issues = validate(
+            [
+                ("stone.block.ron", &stone),
+                ("grass.block.ron", &grass),
+                ("water.block.ron", &water),
+                ("glass.block.ron", &glass),
+                ("dirt.block.ron", &dirt),
+                ("stone2.block.ron", &stone),
+            ],
+            Some(&textures),
+        );
+
+        assert_eq!(
+            BTreeSet::from_iter(issues),
+            BTreeSet::from([
+                AssetIssue::DuplicateId {
+                    block_id: "infinigen:stone".to_owned(),
+                    sources: vec!["stone.block.ron".to_owned(), "stone2.block.ron".to_owned()],
+                },
+                AssetIssue::MalformedNamespace {
+                    block_id: "water".to_owned(),
+                    name: "water".to_owned(),
+                },
+                AssetIssue::MalformedNamespace {
+                    block_id: "water".to_owned(),
+                    name: "fluid".to_owned(),
+                },
+                AssetIssue::MissingTexture {
+                    block_id: "infinigen:grass".to_owned(),
+                    texture: "snow".to_owned(),
+                },
+                AssetIssue::MissingTexture {
+                    block_id: "infinigen:stone".to_owned(),
+                    texture: "glow".to_owned(),
+                },
+                AssetIssue::MissingTexture {
+                    block_id: "infinigen:stone".to_owned(),
+                    texture: "stone_cracked".to_owned(),
+                },
+                AssetIssue::InvalidVariation {
+                    block_id: "infinigen:stone".to_owned(),
+                    texture: "cobblestone".to_owned(),
+                },
+                AssetIssue::UnusedVariation {
+                    block_id: "infinigen:stone".to_owned(),
+                    texture: "cobblestone".to_owned(),
+                },
+                AssetIssue::IgnoredVariation {
+                    block_id: "infinigen:glass".to_owned(),
+                    texture: "stone".to_owned(),
+                },
+                AssetIssue::UntexturedMap {
+                    block_id: "infinigen:stone".to_owned(),
+                    face: Face::Bottom,
+                },
+                AssetIssue::InvalidLightEmission {
+                    block_id: "infinigen:grass".to_owned(),
+                    light_emission: MAX_LIGHT_EMISSION + 1,
+                },
+                AssetIssue::InvalidAnimation {
+                    block_id: "infinigen:grass".to_owned(),
+                    texture: "snow".to_owned(),
+                },
+                AssetIssue::UnusedAnimation {
+                    block_id: "infinigen:grass".to_owned(),
+                    texture: "lava".to_owned(),
+                },
+                AssetIssue::InvalidConnection {
+                    block_id: "infinigen:glass".to_owned(),
+                    texture: "stone".to_owned(),
+                },
+                AssetIssue::UnusedConnection {
+                    block_id: "infinigen:glass".to_owned(),
+                    texture: "glass".to_owned(),
+                },
+                AssetIssue::TextureSizeMismatch {
+                    texture: "wrong_size".to_owned(),
+                    size: [16, 16],
+                    expected: [64, 64],
+                },
+                AssetIssue::UnusedTexture("unknown".to_owned()),
+                AssetIssue::OpaqueTranslucent {
+                    block_id: "water".to_owned(),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn texture_checks_are_skipped_without_textures() {
+        let stone = block("infinigen:stone", &[(Face::Top, "stone")]);
+        assert_eq!(validate([("stone.block.ron", &stone)], None), []);
+    }
+
+    #[test]
+    fn resource_packs_are_validated_as_layered() {
+        let assets = std::env::temp_dir().join(format!("infinigen-packs-{}", std::process::id()));
+        let write = |path: &str, contents: &str| {
+            let path = assets.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        };
+        write(
+            "blocks/types/stone.block.ron",
+            r#"(id: "infinigen:stone", textures: Some({Top: "missing"}))"#,
+        );
+        std::fs::create_dir_all(assets.join("blocks/textures")).unwrap();
+        write(
+            "packs/a/blocks/types/stone.block.ron",
+            r#"(id: "infinigen:stone")"#,
+        );
+        write(
+            "packs/a/blocks/types/dirt.block.ron",
+            r#"(id: "infinigen:dirt")"#,
+        );
+        write(
+            "packs/a/blocks/types/dirt2.block.ron",
+            r#"(id: "infinigen:dirt")"#,
+        );
+
+        let base = validate_dir(&assets, &ResourcePacks::default());
+        // the pack's stone replaces the one with a missing texture, and a pack without any
+        // directories is skipped
+        let packs = ResourcePacks(vec!["packs/a".to_owned(), "packs/empty".to_owned()]);
+        let layered = validate_dir(&assets, &packs);
+        std::fs::remove_dir_all(&assets).unwrap();
+        assert_eq!(
+            base.unwrap(),
+            [AssetIssue::MissingTexture {
+                block_id: "infinigen:stone".to_owned(),
+                texture: "missing".to_owned(),
+            }]
+        );
+        assert_eq!(
+            layered.unwrap(),
+            [AssetIssue::DuplicateId {
+                block_id: "infinigen:dirt".to_owned(),
+                sources: vec![
+                    "packs/a/blocks/types/dirt.block.ron".to_owned(),
+                    "packs/a/blocks/types/dirt2.block.ron".to_owned(),
+                ],
+            }]
+        );
+    }
+}
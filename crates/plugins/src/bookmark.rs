@@ -0,0 +1,179 @@
+//! Shareable strings identifying an exact view of a world, e.g.
+//! `v1/MountainIslands/42/0/-1283,140,-1752/-1200,130,-1700`.
+//!
+//! Fields are separated by `/`: a version, the world generator, the seed, the zoom level, then the
+//! unzoomed camera position and the point it's looking at.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::settings::{AppSettings, Seed};
+
+const VERSION: &str = "v1";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bookmark {
+    pub world: String,
+    pub seed: u64,
+    pub zoom_level: i8,
+    pub position: [f64; 3],
+    pub target: [f64; 3],
+}
+
+impl Bookmark {
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        Self {
+            world: settings.world.clone(),
+            seed: settings.seed.to_u64(),
+            zoom_level: settings.zoom_level,
+            position: [settings.wx, settings.wy, settings.wz],
+            target: [settings.target_x, settings.target_y, settings.target_z],
+        }
+    }
+
+    /// Overrides everything in `settings` that the bookmark specifies.
+    pub fn apply(&self, settings: &mut AppSettings) {
+        settings.world.clone_from(&self.world);
+        settings.seed = Seed::Number(self.seed);
+        settings.zoom_level = self.zoom_level;
+        [settings.wx, settings.wy, settings.wz] = self.position;
+        [settings.target_x, settings.target_y, settings.target_z] = self.target;
+    }
+}
+
+impl fmt::Display for Bookmark {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [wx, wy, wz] = self.position;
+        let [tx, ty, tz] = self.target;
+        write!(
+            f,
+            "{VERSION}/{}/{}/{}/{wx},{wy},{wz}/{tx},{ty},{tz}",
+            self.world, self.seed, self.zoom_level
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BookmarkError {
+    UnsupportedVersion(String),
+    /// The bookmark doesn't have the expected number of fields.
+    WrongFieldCount(usize),
+    InvalidField {
+        field: &'static str,
+        value: String,
+    },
+}
+
+impl fmt::Display for BookmarkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedVersion(version) => {
+                write!(
+                    f,
+                    "unsupported bookmark version '{version}', expected '{VERSION}'"
+                )
+            }
+            Self::WrongFieldCount(count) => write!(
+                f,
+                "bookmark has {count} fields, expected 6 e.g. '{VERSION}/MountainIslands/0/0/0,0,0/1,0,0'"
+            ),
+            Self::InvalidField { field, value } => {
+                write!(f, "invalid {field} '{value}' in bookmark")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BookmarkError {}
+
+impl FromStr for Bookmark {
+    type Err = BookmarkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.trim().split('/').collect();
+        let &[version, world, seed, zoom_level, position, target] = fields.as_slice() else {
+            return Err(BookmarkError::WrongFieldCount(fields.len()));
+        };
+        if version != VERSION {
+            return Err(BookmarkError::UnsupportedVersion(version.to_string()));
+        }
+        let invalid = |field: &'static str, value: &str| BookmarkError::InvalidField {
+            field,
+            value: value.to_string(),
+        };
+        if world.is_empty() {
+            return Err(invalid("world", world));
+        }
+        let coords = |field: &'static str, value: &str| {
+            let coords: Vec<f64> = value
+                .split(',')
+                .map(f64::from_str)
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid(field, value))?;
+            // `f64::from_str` accepts NaN and infinities, which no camera can be placed at
+            if !coords.iter().all(|coord| coord.is_finite()) {
+                return Err(invalid(field, value));
+            }
+            <[f64; 3]>::try_from(coords).map_err(|_| invalid(field, value))
+        };
+        Ok(Self {
+            world: world.to_string(),
+            seed: seed.parse().map_err(|_| invalid("seed", seed))?,
+            zoom_level: zoom_level
+                .parse()
+                .map_err(|_| invalid("zoom level", zoom_level))?,
+            position: coords("position", position)?,
+            target: coords("target", target)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let bookmark = Bookmark {
+            world: "MountainIslands".to_string(),
+            seed: u64::MAX,
+            zoom_level: -3,
+            position: [-1283., 140.5, -1752.25],
+            target: [0.1, -0.0, 1e-7],
+        };
+        let s = bookmark.to_string();
+        assert_eq!(
+            s,
+            "v1/MountainIslands/18446744073709551615/-3/-1283,140.5,-1752.25/0.1,-0,0.0000001"
+        );
+        assert_eq!(s.parse::<Bookmark>(), Ok(bookmark));
+    }
+
+    #[test]
+    fn rejects_malformed_bookmarks() {
+        assert_eq!(
+            "v2/Flat/0/0/0,0,0/1,0,0".parse::<Bookmark>(),
+            Err(BookmarkError::UnsupportedVersion("v2".to_string()))
+        );
+        assert_eq!(
+            "v1/Flat/0/0,0,0/1,0,0".parse::<Bookmark>(),
+            Err(BookmarkError::WrongFieldCount(5))
+        );
+        for (bookmark, field) in [
+            ("v1//0/0/0,0,0/1,0,0", "world"),
+            ("v1/Flat/-1/0/0,0,0/1,0,0", "seed"),
+            ("v1/Flat/0/200/0,0,0/1,0,0", "zoom level"),
+            ("v1/Flat/0/0/0,0/1,0,0", "position"),
+            ("v1/Flat/0/0/0,0,0/1,x,0", "target"),
+            ("v1/Flat/0/0/NaN,0,0/1,0,0", "position"),
+            ("v1/Flat/0/0/0,inf,0/1,0,0", "position"),
+            ("v1/Flat/0/0/0,0,0/1,0,-inf", "target"),
+            ("v1/Flat/0/0/0,0,0/infinity,0,0", "target"),
+        ] {
+            assert!(
+                matches!(bookmark.parse::<Bookmark>(), Err(BookmarkError::InvalidField { field: f, .. }) if f == field),
+                "{bookmark}"
+            );
+        }
+    }
+}
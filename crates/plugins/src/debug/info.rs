@@ -7,8 +7,14 @@ use bevy_inspector_egui::bevy_egui::egui::{self, Slider};
 use infinigen_common::chunks::CHUNK_SIZE_F32;
 use leafwing_input_manager::prelude::*;
 
+use crate::bookmark::Bookmark;
 use crate::camera::FpsController;
+use crate::registry::BlockRegistry;
 use crate::scene::{self, LoadedChunk};
+use crate::world::WorldSettings;
+
+/// How far in front of the camera a bookmark's target is, in blocks.
+const BOOKMARK_TARGET_DISTANCE: f32 = 16.;
 
 pub fn display_debug_info(
     mut egui: EguiContexts,
@@ -20,6 +26,8 @@ pub fn display_debug_info(
     mut update_msgs: MessageWriter<scene::UpdateSettingsMessage>,
     mut reload_msgs: MessageWriter<scene::ReloadAllChunksMessage>,
     loaded_chunks: Query<&LoadedChunk>,
+    world_settings: Res<WorldSettings>,
+    registry: Res<BlockRegistry>,
 ) -> Result {
     let (camera_transform, mut fps_controller) = camera_query.into_inner();
     egui::Window::new("Position").show(egui.ctx_mut()?, |ui| {
@@ -54,6 +62,33 @@ pub fn display_debug_info(
             ui.label(format!("{}", chunk_pos[2]));
             ui.end_row();
         });
+
+        let zoom = (scene_zoom.zoom_level as f32).exp2();
+        let unzoomed = |translation: Vec3| {
+            // round so bookmarks stay short
+            (translation / zoom)
+                .as_dvec3()
+                .to_array()
+                .map(|w| (w * 100.).round() / 100.)
+        };
+        let bookmark = Bookmark {
+            world: world_settings.world_gen_name.clone(),
+            seed: world_settings.seed,
+            zoom_level: scene_zoom.zoom_level,
+            position: unzoomed(camera_transform.translation),
+            target: unzoomed(
+                camera_transform.translation
+                    + camera_transform.forward() * BOOKMARK_TARGET_DISTANCE,
+            ),
+        };
+        if ui
+            .button("Copy bookmark")
+            .on_hover_text(bookmark.to_string())
+            .clicked()
+        {
+            tracing::info!(%bookmark, "Copied bookmark");
+            ui.ctx().copy_text(bookmark.to_string());
+        }
     });
 
     egui::Window::new("Controls").show(egui.ctx_mut()?, |ui| {
@@ -136,6 +171,32 @@ pub fn display_debug_info(
             ui.end_row();
         });
     });
+
+    egui::Window::new("Blocks")
+        .default_open(false)
+        .show(egui.ctx_mut()?, |ui| {
+            let mut blocks: Vec<_> = registry
+                .definitions
+                .iter()
+                .map(|(mapped_id, definition)| {
+                    (
+                        definition.0.id.as_str(),
+                        registry.definitions.pack(&mapped_id),
+                    )
+                })
+                .collect();
+            blocks.sort_unstable();
+            egui::Grid::new("blocks").num_columns(2).show(ui, |ui| {
+                ui.strong("Block");
+                ui.strong("Resource pack");
+                ui.end_row();
+                for (block_id, pack) in blocks {
+                    ui.label(block_id);
+                    ui.label(pack.unwrap_or("built-in"));
+                    ui.end_row();
+                }
+            });
+        });
     Ok(())
 }
 
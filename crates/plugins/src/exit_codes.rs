@@ -0,0 +1,14 @@
+//! Codes that the app exits with when it fails, following `sysexits.h`
+
+/// The assets have errors in them (`EX_DATAERR`).
+pub const INVALID_ASSETS: u8 = 65;
+
+/// The assets directory couldn't be read (`EX_NOINPUT`).
+pub const UNREADABLE_ASSETS: u8 = 66;
+
+/// The world generator couldn't be initialized, e.g. because blocks it requires are missing
+/// (`EX_UNAVAILABLE`).
+pub const WORLD_INIT_FAILED: u8 = 69;
+
+/// The settings couldn't be loaded or parsed (`EX_CONFIG`).
+pub const INVALID_SETTINGS: u8 = 78;
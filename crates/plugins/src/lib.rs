@@ -3,8 +3,10 @@
 use bevy::prelude::*;
 
 pub mod assets;
+pub mod bookmark;
 pub mod camera;
 pub mod debug;
+pub mod exit_codes;
 pub mod mesh;
 pub mod registry;
 pub mod scene;
@@ -49,6 +51,10 @@ impl Plugin for AppPlugin {
             self.settings,
             self.runtime
         );
+        tracing::info!(
+            bookmark = %bookmark::Bookmark::from_settings(&self.settings),
+            "Starting view"
+        );
         app.insert_resource(self.runtime)
             .init_state::<AppState>()
             .insert_resource(CameraSettings {
@@ -65,9 +71,15 @@ impl Plugin for AppPlugin {
                 vertical_view_distance: self.settings.vertical_view_distance as usize,
                 zoom_level: self.settings.zoom_level,
             })
+            .insert_resource(assets::packs::ResourcePacks(
+                self.settings.resource_packs.clone(),
+            ))
+            .insert_resource(assets::BlockTextureBackend(self.settings.texture_backend))
             .insert_resource(WorldSettings {
                 world_gen_name: self.settings.world.clone(),
-                seed: self.settings.seed as u32,
+                seed: self.settings.seed.to_u64(),
+                flat: self.settings.flat.clone(),
+                heightmap: self.settings.heightmap.clone(),
             })
             .add_plugins((
                 registry::RegistryPlugin,
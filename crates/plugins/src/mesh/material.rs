@@ -0,0 +1,207 @@
+//! The material for chunk meshes, whose vertices are packed by
+//! [`VoxelVertex::pack`](infinigen_common::mesh::vertex::VoxelVertex::pack) rather than having
+//! the usual mesh attributes.
+use bevy::asset::RenderAssetUsages;
+use bevy::mesh::{MeshVertexAttribute, MeshVertexBufferLayoutRef, VertexFormat};
+use bevy::pbr::{
+    ExtendedMaterial, MaterialExtension, MaterialExtensionKey, MaterialExtensionPipeline,
+};
+use bevy::prelude::*;
+use bevy::render::render_resource::{
+    AsBindGroup, Extent3d, RenderPipelineDescriptor, ShaderType, SpecializedMeshPipelineError,
+    TextureDimension, TextureFormat,
+};
+use bevy::shader::ShaderRef;
+use infinigen_common::blocks::MaterialMap;
+use infinigen_common::mesh::textures::AnimatedTexture;
+
+const SHADER_PATH: &str = "embedded://infinigen_plugins/mesh/voxel.wgsl";
+/// Entries per row of the palette and animation images, which must match the shader.
+const TABLE_WIDTH: usize = 256;
+
+/// Perceptual roughness of faces without a metallic/roughness map.
+pub const ROUGHNESS: f32 = 0.75;
+
+/// The first two `u32`s of each packed vertex, with its geometry and appearance.
+pub const ATTRIBUTE_VOXEL: MeshVertexAttribute =
+    MeshVertexAttribute::new("Voxel", 0x1f5e_2c0b_9d4a_7e31, VertexFormat::Uint32x2);
+
+/// The last `u32` of each packed vertex, with its tint, which is left out of meshes without any
+/// tinted faces.
+pub const ATTRIBUTE_VOXEL_TINT: MeshVertexAttribute =
+    MeshVertexAttribute::new("VoxelTint", 0x6b0d_93e2_41c7_a58f, VertexFormat::Uint32);
+
+/// Lit like a [`StandardMaterial`], with the base colour of each face from its vertices.
+pub type VoxelMaterial = ExtendedMaterial<StandardMaterial, VoxelExtension>;
+
+/// Where the faces of chunk meshes get their colours from.
+///
+/// Textures and each kind of material map are texture arrays with the same layers, or texture
+/// atlases with the same layout, so a face finds all of them in the same place. Faces that aren't
+/// textured are coloured from `palette` instead, and animated faces look up which layer to show
+/// in `animations`.
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone, Default)]
+pub struct VoxelExtension {
+    #[texture(100, dimension = "2d_array")]
+    #[sampler(101)]
+    pub textures: Option<Handle<Image>>,
+    #[texture(102, dimension = "2d_array")]
+    pub normal_maps: Option<Handle<Image>>,
+    #[texture(103, dimension = "2d_array")]
+    pub metallic_roughness_maps: Option<Handle<Image>>,
+    #[texture(104, dimension = "2d_array")]
+    pub emissive_maps: Option<Handle<Image>>,
+    /// Colours of faces that aren't textured, as built by [`palette_image`].
+    #[texture(105)]
+    pub palette: Option<Handle<Image>>,
+    #[uniform(106)]
+    pub settings: VoxelSettings,
+    /// Animated textures, as built by [`animations_image`].
+    #[texture(107, sample_type = "u_int")]
+    pub animations: Option<Handle<Image>>,
+}
+
+#[derive(ShaderType, Reflect, Debug, Clone, Copy, Default)]
+pub struct VoxelSettings {
+    /// Size of the texture atlas in pixels, if textures are in one.
+    pub atlas_size: Vec2,
+    /// Size of each texture in pixels.
+    pub texture_size: Vec2,
+}
+
+impl MaterialExtension for VoxelExtension {
+    fn vertex_shader() -> ShaderRef {
+        SHADER_PATH.into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        SHADER_PATH.into()
+    }
+
+    fn prepass_vertex_shader() -> ShaderRef {
+        SHADER_PATH.into()
+    }
+
+    fn specialize(
+        _pipeline: &MaterialExtensionPipeline,
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayoutRef,
+        _key: MaterialExtensionKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        // the usual attributes are missing, so Bevy would have given the pipeline no vertex
+        // buffers at all
+        let mut attributes = vec![ATTRIBUTE_VOXEL.at_shader_location(0)];
+        if layout.0.contains(ATTRIBUTE_VOXEL_TINT) {
+            attributes.push(ATTRIBUTE_VOXEL_TINT.at_shader_location(1));
+            descriptor.vertex.shader_defs.push("VERTEX_TINTS".into());
+        }
+        descriptor.vertex.buffers = vec![layout.0.get_layout(&attributes)?];
+        Ok(())
+    }
+}
+
+/// The pixel of a material map for faces without one, which leaves them looking as if they had
+/// no maps at all.
+pub const fn default_map_pixel(map: MaterialMap) -> [u8; 4] {
+    match map {
+        MaterialMap::Normal => [128, 128, 255, 255],
+        MaterialMap::MetallicRoughness => [0, (ROUGHNESS * 255. + 0.5) as u8, 0, 255],
+        MaterialMap::Emissive => [0, 0, 0, 255],
+    }
+}
+
+/// Whether the map holds colours in sRGB, rather than linear data like normals.
+pub const fn is_srgb(map: MaterialMap) -> bool {
+    matches!(map, MaterialMap::Emissive)
+}
+
+/// An image with a pixel for each colour of the palette, from left to right then top to bottom.
+pub fn palette_image(palette: &[[f32; 4]]) -> Image {
+    let (width, height) = table_size(palette.len());
+    let mut data = vec![0; width * height * 4];
+    for (pixel, color) in data.chunks_exact_mut(4).zip(palette) {
+        for (channel, value) in pixel.iter_mut().zip(color) {
+            *channel = (value * 255.).round() as u8;
+        }
+    }
+    Image::new(
+        Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        // vertex colours were linear, so the palette is too
+        TextureFormat::Rgba8Unorm,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+/// An image with a pixel for each animated texture, laid out like [`palette_image`]. Each pixel
+/// holds the first layer, number of frames and frame time in milliseconds.
+pub fn animations_image(animations: &[AnimatedTexture]) -> Image {
+    let (width, height) = table_size(animations.len());
+    let mut data = vec![0; width * height * 16];
+    for (pixel, animation) in data.chunks_exact_mut(16).zip(animations) {
+        let channels = [
+            animation.first,
+            animation.frames,
+            animation.frame_time_ms,
+            0,
+        ];
+        for (channel, value) in pixel.chunks_exact_mut(4).zip(channels) {
+            channel.copy_from_slice(&value.to_le_bytes());
+        }
+    }
+    Image::new(
+        Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba32Uint,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+/// Width and height of an image with a pixel for each of `len` entries, which is never empty.
+fn table_size(len: usize) -> (usize, usize) {
+    (len.clamp(1, TABLE_WIDTH), len.div_ceil(TABLE_WIDTH).max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn palettes_wrap_into_rows() {
+        let image = palette_image(&[[1., 0., 0., 1.]; TABLE_WIDTH + 1]);
+        assert_eq!(image.size(), UVec2::new(TABLE_WIDTH as u32, 2));
+        let data = image.data.unwrap();
+        assert_eq!(data[..4], [255, 0, 0, 255]);
+        assert_eq!(data[TABLE_WIDTH * 4..][..4], [255, 0, 0, 255]);
+        assert_eq!(data[TABLE_WIDTH * 4 + 4..][..4], [0, 0, 0, 0]);
+
+        let image = palette_image(&[[0., 0., 1., 0.5]]);
+        assert_eq!(image.size(), UVec2::ONE);
+        assert_eq!(image.data.unwrap(), [0, 0, 255, 128]);
+    }
+
+    #[test]
+    fn animations_are_stored_as_integers() {
+        let image = animations_image(&[AnimatedTexture {
+            first: 300,
+            frames: 8,
+            frame_time_ms: 100,
+        }]);
+        assert_eq!(image.size(), UVec2::ONE);
+        assert_eq!(
+            image.data.unwrap(),
+            [44, 1, 0, 0, 8, 0, 0, 0, 100, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(animations_image(&[]).size(), UVec2::ONE);
+    }
+}
@@ -2,6 +2,7 @@ use std::ops::Deref;
 use std::sync::Arc;
 
 use bevy::prelude::*;
+use infinigen_common::mesh::faces::diagonal_offsets;
 use infinigen_common::mesh::shapes::EMPTY_CHUNK_FACES;
 use infinigen_common::world::{ChunkPosition, Direction};
 use infinigen_common::zoom::ZoomLevel;
@@ -113,6 +114,23 @@ pub fn handle_mesh_chunk_requests(
                 }
             }
         }
+        let mut diagonals = vec![];
+        for (offset, diagonal_cpos) in get_diagonal_cposes(chunk_position) {
+            match world.cache.get(&(diagonal_cpos, *zoom_level)) {
+                Some(ChunkStatus::Generating) => all_neighbours_present = false,
+                Some(ChunkStatus::Generated(chunk_info)) => {
+                    diagonals.push((offset, &chunk_info.faces))
+                }
+                Some(ChunkStatus::Empty) => (),
+                None => {
+                    all_neighbours_present = false;
+                    generate_chunk_reqs.write(GenerateChunkRequest {
+                        chunk_position: diagonal_cpos,
+                        zoom_level: *zoom_level,
+                    });
+                }
+            }
+        }
         if !all_neighbours_present {
             // not all neighbours available yet, check again later
             mesh_chunk_rerequests.write(MeshChunkRerequest {
@@ -133,13 +151,24 @@ pub fn handle_mesh_chunk_requests(
             neighbour_faces[dir] = faces[opposite];
         }
 
+        let diagonal_faces = |offset| {
+            diagonals
+                .iter()
+                .find(|(diagonal, _)| *diagonal == offset)
+                .map(|(_, faces)| *faces)
+        };
+
         let mut mesh_info = MeshInfo::default();
+        // translucent blocks are split out of the chunk that was generated, which keeps its tints
+        let tints = chunk_info.opaque.tints();
 
         for translucent in chunk_info.translucents.iter() {
             if let Some(translucent_mesh) = bevy_mesh_greedy_quads(
                 translucent,
                 &neighbour_faces,
+                diagonal_faces,
                 &registry.appearances,
+                tints,
                 &registry.definitions,
             ) {
                 mesh_info.translucents.push(translucent_mesh);
@@ -148,8 +177,11 @@ pub fn handle_mesh_chunk_requests(
 
         mesh_info.opaque = bevy_mesh_visible_block_faces(
             &chunk_info.opaque,
+            *chunk_position,
             &neighbour_faces,
+            diagonal_faces,
             &registry.appearances,
+            tints,
             &registry.definitions,
         );
 
@@ -183,3 +215,20 @@ pub fn get_neighbour_cposes(
         )
     })
 }
+
+/// Positions of the chunks which only meet the chunk at `position` along an edge or at a corner,
+/// along with their offsets from it.
+pub fn get_diagonal_cposes(
+    position: &ChunkPosition,
+) -> impl Iterator<Item = ([i32; 3], ChunkPosition)> + '_ {
+    diagonal_offsets().map(|offset| {
+        (
+            offset,
+            ChunkPosition {
+                x: position.x + offset[0],
+                y: position.y + offset[1],
+                z: position.z + offset[2],
+            },
+        )
+    })
+}
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use ahash::AHashMap;
+use bevy::asset::embedded_asset;
 use bevy::prelude::*;
 use infinigen_common::world::ChunkPosition;
 use infinigen_common::zoom::ZoomLevel;
@@ -8,6 +9,7 @@ use messages::{MeshChunkRequest, MeshChunkRerequest};
 
 use crate::AppState;
 
+pub mod material;
 pub mod messages;
 mod utils;
 
@@ -33,7 +35,9 @@ pub struct MeshPlugin;
 impl Plugin for MeshPlugin {
     fn build(&self, app: &mut App) {
         tracing::info!("Initializing mesh plugin");
-        app.init_resource::<Meshes>()
+        embedded_asset!(app, "voxel.wgsl");
+        app.add_plugins(MaterialPlugin::<material::VoxelMaterial>::default())
+            .init_resource::<Meshes>()
             .add_message::<MeshChunkRequest>()
             .add_message::<MeshChunkRerequest>()
             .add_systems(
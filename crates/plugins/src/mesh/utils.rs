@@ -4,66 +4,66 @@ use bevy::asset::RenderAssetUsages;
 use bevy::mesh::{Indices, VertexAttributeValues};
 use bevy::prelude::*;
 use bevy::render::render_resource::PrimitiveTopology;
-use infinigen_common::chunks::Array3Chunk;
-use infinigen_common::mesh::faces::{BlockVisibilityChecker, prepare_padded_chunk};
+use infinigen_common::chunks::{Array3Chunk, ColumnTints};
+use infinigen_common::mesh::faces::{
+    BlockVisibilityChecker, pad_edges_and_corners, prepare_padded_chunk,
+};
 use infinigen_common::mesh::shapes::ChunkFace;
 use infinigen_common::mesh::textures::BlockAppearances;
+use infinigen_common::mesh::vertex::PACKED_NO_TINT;
 use infinigen_common::mesh::{MeshInfo, mesh_chunk_greedy_quads, mesh_chunk_visible_block_faces};
-use infinigen_common::world::Direction;
+use infinigen_common::world::{ChunkPosition, Direction};
 use linearize::StaticCopyMap;
 
-pub fn to_bevy_mesh(
-    MeshInfo {
-        positions,
-        normals,
-        colors,
-        uvs,
-        indices,
-    }: MeshInfo,
-) -> Mesh {
+use crate::mesh::material::{ATTRIBUTE_VOXEL, ATTRIBUTE_VOXEL_TINT};
+
+pub fn to_bevy_mesh(MeshInfo { vertices, indices }: MeshInfo) -> Mesh {
     let mut render_mesh = Mesh::new(
         PrimitiveTopology::TriangleList,
         RenderAssetUsages::default(),
     );
-    render_mesh.insert_attribute(
-        Mesh::ATTRIBUTE_POSITION,
-        VertexAttributeValues::Float32x3(positions),
-    );
-    render_mesh.insert_attribute(
-        Mesh::ATTRIBUTE_NORMAL,
-        VertexAttributeValues::Float32x3(normals),
-    );
-    render_mesh.insert_attribute(
-        Mesh::ATTRIBUTE_COLOR,
-        VertexAttributeValues::Float32x4(colors),
-    );
-    render_mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, VertexAttributeValues::Float32x2(uvs));
+    let (voxels, tints): (Vec<_>, Vec<_>) = vertices
+        .into_iter()
+        .map(|[geometry, appearance, tint]| ([geometry, appearance], tint))
+        .unzip();
+    render_mesh.insert_attribute(ATTRIBUTE_VOXEL, VertexAttributeValues::Uint32x2(voxels));
+    // most meshes have no tinted faces, so they're spared the space of tints
+    if tints.iter().any(|&tint| tint != PACKED_NO_TINT) {
+        render_mesh.insert_attribute(ATTRIBUTE_VOXEL_TINT, VertexAttributeValues::Uint32(tints));
+    }
     render_mesh.insert_indices(Indices::U32(indices));
     render_mesh
 }
 
-/// Returns a mesh of all visible block faces in the chunk.
+/// Returns a mesh of all visible block faces in the chunk, with tinted faces multiplied by `tints`.
 /// adapted from <https://github.com/bonsairobo/block-mesh-rs/blob/main/examples-crate/render/main.rs>
-pub fn bevy_mesh_visible_block_faces(
+pub fn bevy_mesh_visible_block_faces<'a>(
     chunk: &Array3Chunk,
+    chunk_position: ChunkPosition,
     neighbor_faces: &StaticCopyMap<Direction, ChunkFace>,
+    diagonal_faces: impl Fn([i32; 3]) -> Option<&'a StaticCopyMap<Direction, ChunkFace>>,
     block_textures: &BlockAppearances,
+    tints: Option<&ColumnTints>,
     visibility_checker: impl BlockVisibilityChecker,
 ) -> Option<Mesh> {
-    let samples = prepare_padded_chunk(chunk, neighbor_faces, visibility_checker);
-    let mesh = mesh_chunk_visible_block_faces(&samples, block_textures);
+    let mut samples = prepare_padded_chunk(chunk, neighbor_faces, visibility_checker);
+    pad_edges_and_corners(&mut samples, diagonal_faces);
+    let mesh = mesh_chunk_visible_block_faces(&samples, block_textures, chunk_position, tints);
     mesh.map(to_bevy_mesh)
 }
 
-/// Returns a mesh of quads in a chunk.
+/// Returns a mesh of quads in a chunk, with tinted faces multiplied by `tints`.
 /// adapted from <https://github.com/bonsairobo/block-mesh-rs/blob/main/examples-crate/render/main.rs>
-pub fn bevy_mesh_greedy_quads(
+pub fn bevy_mesh_greedy_quads<'a>(
     chunk: &Array3Chunk,
     neighbor_faces: &StaticCopyMap<Direction, ChunkFace>,
+    diagonal_faces: impl Fn([i32; 3]) -> Option<&'a StaticCopyMap<Direction, ChunkFace>>,
     block_textures: &BlockAppearances,
+    tints: Option<&ColumnTints>,
     visibility_checker: impl BlockVisibilityChecker,
 ) -> Option<Mesh> {
-    let samples = prepare_padded_chunk(chunk, neighbor_faces, visibility_checker);
-    let mesh = mesh_chunk_greedy_quads(&samples, block_textures);
+    let mut samples = prepare_padded_chunk(chunk, neighbor_faces, visibility_checker);
+    pad_edges_and_corners(&mut samples, diagonal_faces);
+    let mesh = mesh_chunk_greedy_quads(&samples, block_textures, tints);
     mesh.map(to_bevy_mesh)
 }
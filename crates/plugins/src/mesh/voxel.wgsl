@@ -0,0 +1,269 @@
+// Shaders for chunk meshes, whose vertices are packed by `VoxelVertex::pack`. Faces are lit like
+// a standard material, with a base colour from the palette, the texture atlas or the texture
+// array, depending on how each vertex is textured. Textured faces also take their normal,
+// roughness, metalness and emission from material maps laid out like the textures, and animated
+// faces show the layer of their current frame.
+#import bevy_pbr::{
+    mesh_functions,
+    mesh_view_bindings::globals,
+    view_transformations::position_world_to_clip,
+}
+
+#ifdef PREPASS_PIPELINE
+#import bevy_pbr::prepass_io::VertexOutput
+#else
+#import bevy_pbr::{
+    forward_io::{VertexOutput, FragmentOutput},
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::{alpha_discard, apply_pbr_lighting, main_pass_post_lighting_processing},
+}
+#endif
+
+const KIND_COLOR: u32 = 0u;
+const KIND_ATLAS: u32 = 1u;
+const KIND_LAYER: u32 = 2u;
+const KIND_ANIMATED: u32 = 3u;
+const TABLE_WIDTH: u32 = 256u;
+
+struct VoxelSettings {
+    atlas_size: vec2<f32>,
+    texture_size: vec2<f32>,
+}
+
+// texture atlases are bound as arrays with a single layer
+@group(#{MATERIAL_BIND_GROUP}) @binding(100) var textures: texture_2d_array<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(101) var textures_sampler: sampler;
+@group(#{MATERIAL_BIND_GROUP}) @binding(102) var normal_maps: texture_2d_array<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(103) var metallic_roughness_maps: texture_2d_array<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(104) var emissive_maps: texture_2d_array<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(105) var palette: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(106) var<uniform> settings: VoxelSettings;
+// first layer, frame count and frame time in milliseconds of each animated texture
+@group(#{MATERIAL_BIND_GROUP}) @binding(107) var animations: texture_2d<u32>;
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) voxel: vec2<u32>,
+#ifdef VERTEX_TINTS
+    @location(1) tint: u32,
+#endif
+}
+
+struct VoxelVertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) world_position: vec4<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(3) ao: f32,
+    @location(4) @interpolate(flat) appearance: u32,
+    @location(5) @interpolate(flat) instance_index: u32,
+    @location(6) @interpolate(flat) tint: vec3<f32>,
+#ifdef VISIBILITY_RANGE_DITHER
+    @location(7) @interpolate(flat) visibility_range_dither: i32,
+#endif
+}
+
+fn unpack_position(geometry: u32) -> vec3<f32> {
+    return vec3<f32>(
+        f32(geometry & 63u),
+        f32((geometry >> 6u) & 63u),
+        f32((geometry >> 12u) & 63u),
+    );
+}
+
+fn unpack_uv(geometry: u32) -> vec2<f32> {
+    return vec2<f32>(f32((geometry >> 18u) & 63u), f32((geometry >> 24u) & 63u));
+}
+
+fn unpack_ao(geometry: u32) -> f32 {
+    return f32(geometry >> 30u);
+}
+
+fn unpack_tint(tint: u32) -> vec3<f32> {
+    // sRGB to linear
+    let srgb = unpack4x8unorm(tint).rgb;
+    let low = srgb / 12.92;
+    let high = pow((srgb + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(high, low, srgb <= vec3<f32>(0.04045));
+}
+
+fn unpack_normal(appearance: u32) -> vec3<f32> {
+    // faces are ordered -X, +X, -Y, +Y, -Z, +Z
+    let face = appearance & 7u;
+    let axis = face / 2u;
+    let sign = select(-1.0, 1.0, face % 2u == 1u);
+    return vec3<f32>(f32(axis == 0u), f32(axis == 1u), f32(axis == 2u)) * sign;
+}
+
+#ifdef PREPASS_PIPELINE
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    let position = unpack_position(vertex.voxel.x);
+    let world_from_local = mesh_functions::get_world_from_local(vertex.instance_index);
+    out.world_position = mesh_functions::mesh_position_local_to_world(world_from_local, vec4<f32>(position, 1.0));
+    out.position = position_world_to_clip(out.world_position.xyz);
+#ifdef UNCLIPPED_DEPTH_ORTHO_EMULATION
+    out.unclipped_depth = out.position.z;
+    out.position.z = min(out.position.z, 1.0); // Clamp depth to avoid clipping
+#endif
+#ifdef NORMAL_PREPASS_OR_DEFERRED_PREPASS
+    out.world_normal = mesh_functions::mesh_normal_local_to_world(
+        unpack_normal(vertex.voxel.y),
+        vertex.instance_index
+    );
+#endif
+#ifdef MOTION_VECTOR_PREPASS
+    let previous_world_from_local = mesh_functions::get_previous_world_from_local(vertex.instance_index);
+    out.previous_world_position = mesh_functions::mesh_position_local_to_world(
+        previous_world_from_local,
+        vec4<f32>(position, 1.0)
+    );
+#endif
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    out.instance_index = vertex.instance_index;
+#endif
+#ifdef VISIBILITY_RANGE_DITHER
+    out.visibility_range_dither = mesh_functions::get_visibility_range_dither_level(
+        vertex.instance_index, world_from_local[3]);
+#endif
+    return out;
+}
+#else
+@vertex
+fn vertex(vertex: Vertex) -> VoxelVertexOutput {
+    var out: VoxelVertexOutput;
+    let world_from_local = mesh_functions::get_world_from_local(vertex.instance_index);
+    out.world_position = mesh_functions::mesh_position_local_to_world(
+        world_from_local,
+        vec4<f32>(unpack_position(vertex.voxel.x), 1.0)
+    );
+    out.position = position_world_to_clip(out.world_position.xyz);
+    out.world_normal = mesh_functions::mesh_normal_local_to_world(
+        unpack_normal(vertex.voxel.y),
+        vertex.instance_index
+    );
+    out.uv = unpack_uv(vertex.voxel.x);
+    out.ao = unpack_ao(vertex.voxel.x);
+    out.appearance = vertex.voxel.y;
+    out.instance_index = vertex.instance_index;
+#ifdef VERTEX_TINTS
+    out.tint = unpack_tint(vertex.tint);
+#else
+    out.tint = vec3<f32>(1.0);
+#endif
+#ifdef VISIBILITY_RANGE_DITHER
+    out.visibility_range_dither = mesh_functions::get_visibility_range_dither_level(
+        vertex.instance_index, world_from_local[3]);
+#endif
+    return out;
+}
+
+fn table_entry(index: u32) -> vec2<u32> {
+    return vec2<u32>(index % TABLE_WIDTH, index / TABLE_WIDTH);
+}
+
+// Where a face's texture and material maps are, as a layer and the coordinates within it, along
+// with how much smaller the texture is within the layer.
+struct TextureCoords {
+    uv: vec2<f32>,
+    layer: i32,
+    scale: vec2<f32>,
+}
+
+fn texture_coords(uv: vec2<f32>, appearance: u32) -> TextureCoords {
+    let kind = (appearance >> 3u) & 3u;
+    let payload = appearance >> 5u;
+    let in_atlas = settings.atlas_size.x > 0.0;
+
+    // the layer, or position in the atlas, of the texture
+    var index = payload;
+    if kind == KIND_ANIMATED {
+        let animation = textureLoad(animations, table_entry(payload), 0);
+        let frame_time = f32(max(animation.z, 1u)) / 1000.0;
+        index = animation.x + u32(globals.time / frame_time) % max(animation.y, 1u);
+    }
+
+    let columns = u32(round(settings.atlas_size.x / max(settings.texture_size.x, 1.0)));
+    let cell = vec2<u32>(index % max(columns, 1u), index / max(columns, 1u));
+    let atlas_start = vec2<f32>(cell) * settings.texture_size;
+
+    // textures repeat across faces merged into one quad
+    let tile_uv = fract(uv);
+    let atlas_scale = settings.texture_size / max(settings.atlas_size, vec2(1.0));
+    if kind == KIND_ATLAS || (kind == KIND_ANIMATED && in_atlas) {
+        return TextureCoords(atlas_start / settings.atlas_size + tile_uv * atlas_scale, 0, atlas_scale);
+    }
+    let layer = select(0u, index, kind == KIND_LAYER || kind == KIND_ANIMATED);
+    return TextureCoords(tile_uv, i32(layer), vec2(1.0));
+}
+
+@fragment
+fn fragment(
+    in: VoxelVertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> FragmentOutput {
+    var mesh: VertexOutput;
+    mesh.position = in.position;
+    mesh.world_position = in.world_position;
+    mesh.world_normal = in.world_normal;
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    mesh.instance_index = in.instance_index;
+#endif
+#ifdef VISIBILITY_RANGE_DITHER
+    mesh.visibility_range_dither = in.visibility_range_dither;
+#endif
+    var pbr_input = pbr_input_from_standard_material(mesh, is_front);
+
+    // mip levels are chosen from the coordinates before they wrap, so that there are no seams
+    // where textures repeat
+    let coords = texture_coords(in.uv, in.appearance);
+    let ddx = dpdx(in.uv) * coords.scale;
+    let ddy = dpdy(in.uv) * coords.scale;
+    let texture = textureSampleGrad(textures, textures_sampler, coords.uv, coords.layer, ddx, ddy);
+    let normal =
+        textureSampleGrad(normal_maps, textures_sampler, coords.uv, coords.layer, ddx, ddy).xyz;
+    let metallic_roughness = textureSampleGrad(
+        metallic_roughness_maps, textures_sampler, coords.uv, coords.layer, ddx, ddy
+    );
+    let emissive =
+        textureSampleGrad(emissive_maps, textures_sampler, coords.uv, coords.layer, ddx, ddy);
+    // textures may be rotated or flipped on a face, so which way they go across it comes from
+    // how the coordinates change between pixels, worked out here where control flow is uniform
+    let dp1 = dpdx(in.world_position.xyz);
+    let dp2 = dpdy(in.world_position.xyz);
+    let duv1 = dpdx(in.uv);
+    let duv2 = dpdy(in.uv);
+    let payload = in.appearance >> 5u;
+    let color = textureLoad(palette, table_entry(payload), 0);
+    let textured = ((in.appearance >> 3u) & 3u) != KIND_COLOR;
+
+    let base_color =
+        pbr_input.material.base_color * select(color, texture, textured) * vec4<f32>(in.tint, 1.0);
+    // fully occluded corners are half as bright
+    let occlusion = 0.5 + in.ao / 6.0;
+    pbr_input.material.base_color = vec4<f32>(base_color.rgb * occlusion, base_color.a);
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+    if textured {
+        let dp2perp = cross(dp2, pbr_input.N);
+        let dp1perp = cross(pbr_input.N, dp1);
+        let right = normalize(dp2perp * duv1.x + dp1perp * duv2.x);
+        // v goes down the image
+        let down = normalize(dp2perp * duv1.y + dp1perp * duv2.y);
+        let tangent_normal = normal * 2.0 - 1.0;
+        pbr_input.N = normalize(
+            right * tangent_normal.x - down * tangent_normal.y + pbr_input.N * tangent_normal.z
+        );
+        pbr_input.material.perceptual_roughness = metallic_roughness.g;
+        pbr_input.material.metallic = metallic_roughness.b;
+        // unaffected by exposure, so a white emissive map is drawn white whatever the lighting
+        pbr_input.material.emissive = vec4<f32>(emissive.rgb, 0.0);
+    }
+
+    var out: FragmentOutput;
+    out.color = apply_pbr_lighting(pbr_input);
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+    return out;
+}
+#endif
@@ -7,6 +7,8 @@ use infinigen_common::world::MappedBlockID;
 use serde::{Deserialize, Serialize};
 use strum::EnumCount;
 
+use crate::mesh::material::VoxelMaterial;
+
 #[derive(
     Default,
     Debug,
@@ -34,6 +36,8 @@ impl From<BlockType> for BlockDefinition {
 #[derive(Debug, Default, Clone)]
 pub struct BlockDefinitions {
     by_mapped_id: AHashMap<MappedBlockID, BlockDefinition>,
+    /// The resource pack each block was loaded from, if any.
+    packs: AHashMap<MappedBlockID, String>,
     next_free_mapped_id: MappedBlockID,
 }
 
@@ -42,6 +46,19 @@ impl BlockDefinitions {
         self.by_mapped_id.get(mapped_id).unwrap()
     }
 
+    pub fn light_emission(&self, mapped_id: &MappedBlockID) -> u8 {
+        self.get(mapped_id).0.light_emission
+    }
+
+    /// Whether the block has `tag`, which may be written with or without a leading `#`.
+    pub fn has_tag(&self, mapped_id: &MappedBlockID, tag: &str) -> bool {
+        self.get(mapped_id).0.has_tag(tag)
+    }
+
+    pub fn property(&self, mapped_id: &MappedBlockID, key: &str) -> Option<&str> {
+        self.get(mapped_id).0.property(key)
+    }
+
     /// Adds a block definition to the mappings and returns the mapped ID, or None if no more IDs are available.
     pub fn add(&mut self, block_definition: BlockDefinition) -> Option<MappedBlockID> {
         let mapped_id = self.next_free_mapped_id;
@@ -52,12 +69,47 @@ impl BlockDefinitions {
         Some(mapped_id)
     }
 
+    /// Adds a block definition, or replaces the one with the same block ID while keeping its mapped
+    /// ID. Returns the mapped ID, or None if no more IDs are available.
+    pub fn set(&mut self, block_definition: BlockDefinition) -> Option<MappedBlockID> {
+        match self.mapped_id(&block_definition.0.id) {
+            Some(mapped_id) => {
+                tracing::debug!(?block_definition, ?mapped_id, "Replacing block");
+                self.by_mapped_id.insert(mapped_id, block_definition);
+                Some(mapped_id)
+            }
+            None => self.add(block_definition),
+        }
+    }
+
+    pub fn pack(&self, mapped_id: &MappedBlockID) -> Option<&str> {
+        self.packs.get(mapped_id).map(String::as_str)
+    }
+
+    pub fn set_pack(&mut self, mapped_id: MappedBlockID, pack: Option<String>) {
+        match pack {
+            Some(pack) => self.packs.insert(mapped_id, pack),
+            None => self.packs.remove(&mapped_id),
+        };
+    }
+
+    pub fn mapped_id(&self, block_id: &str) -> Option<MappedBlockID> {
+        self.by_mapped_id
+            .iter()
+            .find(|(_, block_definition)| block_definition.0.id == block_id)
+            .map(|(mapped_id, _)| *mapped_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (MappedBlockID, &BlockDefinition)> {
+        self.by_mapped_id
+            .iter()
+            .map(|(mapped_id, block_definition)| (*mapped_id, block_definition))
+    }
+
     pub fn palette(&self) -> Palette {
         let mut palette = Palette::default();
         for (mapped_id, block_definition) in self.by_mapped_id.iter() {
-            palette
-                .inner
-                .insert(block_definition.0.id.clone(), *mapped_id);
+            palette.insert(&block_definition.0, *mapped_id);
         }
         palette
     }
@@ -100,13 +152,13 @@ impl BlockVisibilityChecker for BlockDefinitions {
 
 #[derive(Default, Resource)]
 pub struct BlockRegistry {
-    pub materials: [Handle<StandardMaterial>; BlockVisibility::COUNT],
+    pub materials: [Handle<VoxelMaterial>; BlockVisibility::COUNT],
     pub appearances: BlockAppearances,
     pub definitions: BlockDefinitions,
 }
 
 impl BlockRegistry {
-    pub fn get_material(&self, visibility: BlockVisibility) -> Handle<StandardMaterial> {
+    pub fn get_material(&self, visibility: BlockVisibility) -> Handle<VoxelMaterial> {
         self.materials[visibility as usize].clone()
     }
 }
@@ -119,3 +171,74 @@ impl Plugin for RegistryPlugin {
         app.init_resource::<BlockRegistry>();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use super::*;
+
+    #[test]
+    fn properties_are_looked_up_by_mapped_id() {
+        let mut definitions = BlockDefinitions::default();
+        let stone = definitions
+            .add(
+                BlockType {
+                    id: "infinigen:stone".to_string(),
+                    ..Default::default()
+                }
+                .into(),
+            )
+            .unwrap();
+        let lava = definitions
+            .add(
+                BlockType {
+                    id: "infinigen:lava".to_string(),
+                    light_emission: 15,
+                    tags: BTreeSet::from(["infinigen:fluid".to_string()]),
+                    properties: BTreeMap::from([("viscosity".to_string(), "high".to_string())]),
+                    ..Default::default()
+                }
+                .into(),
+            )
+            .unwrap();
+
+        assert_eq!(definitions.light_emission(&stone), 0);
+        assert_eq!(definitions.light_emission(&lava), 15);
+        assert!(definitions.has_tag(&lava, "#infinigen:fluid"));
+        assert!(!definitions.has_tag(&stone, "infinigen:fluid"));
+        assert_eq!(definitions.property(&lava, "viscosity"), Some("high"));
+        assert_eq!(definitions.property(&stone, "viscosity"), None);
+
+        let palette = definitions.palette();
+        assert_eq!(
+            palette.tagged("infinigen:fluid").collect::<Vec<_>>(),
+            [lava]
+        );
+    }
+
+    #[test]
+    fn replaced_definitions_keep_their_mapped_ids() {
+        let block = |id: &str, light_emission| {
+            BlockDefinition::from(BlockType {
+                id: id.to_string(),
+                light_emission,
+                ..Default::default()
+            })
+        };
+        let mut definitions = BlockDefinitions::default();
+        let stone = definitions.set(block("infinigen:stone", 0)).unwrap();
+        let lava = definitions.set(block("infinigen:lava", 0)).unwrap();
+
+        assert_eq!(definitions.set(block("infinigen:lava", 15)), Some(lava));
+        assert_eq!(definitions.light_emission(&lava), 15);
+        let sand = definitions.set(block("infinigen:sand", 0)).unwrap();
+        assert_ne!(sand, stone);
+        assert_ne!(sand, lava);
+        assert_eq!(definitions.mapped_id("infinigen:sand"), Some(sand));
+        assert_eq!(definitions.pack(&sand), None);
+        definitions.set_pack(sand, Some("packs/hd".to_string()));
+        assert_eq!(definitions.pack(&sand), Some("packs/hd"));
+        assert_eq!(definitions.iter().count(), 3);
+    }
+}
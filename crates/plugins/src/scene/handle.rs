@@ -1,3 +1,4 @@
+use bevy::camera::primitives::Aabb;
 use bevy::prelude::*;
 use infinigen_common::blocks::BlockVisibility;
 use infinigen_common::chunks::CHUNK_SIZE_F32;
@@ -151,6 +152,7 @@ pub fn process_spawn_requested(
                 .spawn((
                     Name::new("Opaque mesh"),
                     Mesh3d(mesh_assets.add(opaque_mesh.clone())),
+                    chunk_aabb(),
                     MeshMaterial3d(registry.get_material(BlockVisibility::Opaque)),
                     Transform::default(),
                     Visibility::default(),
@@ -163,6 +165,7 @@ pub fn process_spawn_requested(
                 .spawn((
                     Name::new("Translucent mesh"),
                     Mesh3d(mesh_assets.add(trans_mesh.clone())),
+                    chunk_aabb(),
                     MeshMaterial3d(registry.get_material(BlockVisibility::Translucent)),
                     Transform::default(),
                     Visibility::default(),
@@ -172,3 +175,9 @@ pub fn process_spawn_requested(
         chunk_requests.remove(cpos);
     }
 }
+
+/// Bounds of a chunk mesh, which Bevy can't work out from its packed vertices. Vertices are in
+/// padded chunk coordinates, so they're offset by one.
+fn chunk_aabb() -> Aabb {
+    Aabb::from_min_max(Vec3::ONE, Vec3::splat(CHUNK_SIZE_F32 + 1.))
+}
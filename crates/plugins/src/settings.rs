@@ -1,3 +1,4 @@
+use infinigen_common::noise::derive_seed;
 use serde::{Deserialize, Serialize};
 
 const fn default_horizontal_view_distance() -> u64 {
@@ -36,6 +37,115 @@ const fn default_target_z() -> f64 {
     -1615.
 }
 
+const fn default_heightmap_horizontal_scale() -> f64 {
+    1.
+}
+
+const fn default_heightmap_vertical_scale() -> f64 {
+    256.
+}
+
+const fn default_heightmap_base_height() -> f64 {
+    -32.
+}
+
+fn default_flat_layers() -> String {
+    "dirt".to_string()
+}
+
+const fn default_flat_base_height() -> i64 {
+    -1
+}
+
+/// Settings for generating a flat world.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlatSettings {
+    /// Comma-separated blocks from the bottom up, each optionally repeated with `*`, e.g.
+    /// `"stone*3,dirt*2,grass"`. Block IDs without a namespace are assumed to be `infinigen:`.
+    #[serde(default = "default_flat_layers")]
+    pub layers: String,
+    /// World height of the bottom layer.
+    #[serde(default = "default_flat_base_height")]
+    pub base_height: i64,
+}
+
+impl Default for FlatSettings {
+    fn default() -> Self {
+        Self {
+            layers: default_flat_layers(),
+            base_height: default_flat_base_height(),
+        }
+    }
+}
+
+/// A world seed, given either as a number or as arbitrary text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Seed {
+    Number(u64),
+    Text(String),
+}
+
+impl Default for Seed {
+    fn default() -> Self {
+        Self::Number(0)
+    }
+}
+
+impl Seed {
+    /// Text is hashed, unless it's a number in which case it's the same as [`Seed::Number`].
+    pub fn to_u64(&self) -> u64 {
+        match self {
+            Self::Number(seed) => *seed,
+            Self::Text(text) => text.trim().parse().unwrap_or_else(|_| derive_seed(0, text)),
+        }
+    }
+}
+
+/// What happens beyond the edges of a heightmap image.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HeightmapEdges {
+    /// Repeat the edge pixels forever.
+    #[default]
+    Clamp,
+    /// Repeat the whole image.
+    Tile,
+}
+
+/// Settings for generating a world from a grayscale heightmap image.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeightmapSettings {
+    /// Path to an 8 or 16-bit grayscale PNG, where white is highest.
+    pub path: String,
+    /// Optional path to a colour PNG of the same size, where each pixel picks the surface block
+    /// whose definition has the closest colour.
+    #[serde(default)]
+    pub material_map: Option<String>,
+    /// World blocks per pixel.
+    #[serde(default = "default_heightmap_horizontal_scale")]
+    pub horizontal_scale: f64,
+    /// World blocks between black and white.
+    #[serde(default = "default_heightmap_vertical_scale")]
+    pub vertical_scale: f64,
+    /// World height of black pixels.
+    #[serde(default = "default_heightmap_base_height")]
+    pub base_height: f64,
+    #[serde(default)]
+    pub edges: HeightmapEdges,
+}
+
+/// How block textures are passed to the GPU.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextureBackend {
+    /// All textures stitched into one image, sampled without mipmaps as neighbouring textures
+    /// would bleed into each other.
+    #[default]
+    Atlas,
+    /// A texture array with a layer per texture, each with its own mipmaps, so that distant
+    /// blocks don't shimmer.
+    Array,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppSettings {
     #[serde(default = "default_horizontal_view_distance")]
@@ -62,7 +172,24 @@ pub struct AppSettings {
     pub target_z: f64,
 
     #[serde(default)]
-    pub seed: u64,
+    pub seed: Seed,
+
+    /// Only used by the flat world generator.
+    #[serde(default)]
+    pub flat: FlatSettings,
+
+    /// Only used by world generators that are based on a heightmap image.
+    #[serde(default)]
+    pub heightmap: Option<HeightmapSettings>,
+
+    /// Directories within the assets directory, laid out like it with `blocks/types` and/or
+    /// `blocks/textures`, which are applied in order over the built-in block assets. Later packs
+    /// override block definitions with the same ID and textures with the same name.
+    #[serde(default)]
+    pub resource_packs: Vec<String>,
+
+    #[serde(default)]
+    pub texture_backend: TextureBackend,
 }
 
 #[cfg(test)]
@@ -79,4 +206,50 @@ mod tests {
             .try_deserialize()?;
         Ok(())
     }
+
+    #[test]
+    fn test_seeds_may_be_text() -> Result<()> {
+        let parse = |seed: &str| -> Result<u64> {
+            let settings: AppSettings = config::Config::builder()
+                .add_source(config::File::from_str(
+                    &format!("seed: {seed}"),
+                    config::FileFormat::Yaml,
+                ))
+                .build()?
+                .try_deserialize()?;
+            Ok(settings.seed.to_u64())
+        };
+        assert_eq!(parse("42")?, 42);
+        assert_eq!(parse("\"42\"")?, 42);
+        assert_eq!(parse("hello world")?, parse("\"hello world\"")?);
+        assert_ne!(parse("hello world")?, parse("hello World")?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_resource_packs_keep_their_order() -> Result<()> {
+        let settings: AppSettings = config::Config::builder()
+            .add_source(config::File::from_str(
+                "resource_packs: [\"packs/hd\", \"packs/autumn\"]",
+                config::FileFormat::Yaml,
+            ))
+            .build()?
+            .try_deserialize()?;
+        assert_eq!(settings.resource_packs, ["packs/hd", "packs/autumn"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_texture_backend_defaults_to_atlas() -> Result<()> {
+        let parse = |yaml: &str| -> Result<TextureBackend> {
+            let settings: AppSettings = config::Config::builder()
+                .add_source(config::File::from_str(yaml, config::FileFormat::Yaml))
+                .build()?
+                .try_deserialize()?;
+            Ok(settings.texture_backend)
+        };
+        assert_eq!(parse("seed: 1")?, TextureBackend::Atlas);
+        assert_eq!(parse("texture_backend: \"Array\"")?, TextureBackend::Array);
+        Ok(())
+    }
 }
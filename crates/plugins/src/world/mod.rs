@@ -1,7 +1,6 @@
 use std::sync::Arc;
 
 use ahash::AHashMap;
-use anyhow::Context;
 use bevy::prelude::*;
 use infinigen_common::blocks::Palette;
 use infinigen_common::chunks::Array3Chunk;
@@ -11,8 +10,9 @@ use infinigen_common::zoom::ZoomLevel;
 use linearize::StaticCopyMap;
 use messages::{GenerateChunkRequest, GenerateChunkTask};
 
-use crate::AppState;
 use crate::registry::BlockRegistry;
+use crate::settings::{FlatSettings, HeightmapSettings};
+use crate::{AppState, exit_codes};
 
 pub mod messages;
 
@@ -78,7 +78,9 @@ impl Plugin for WorldPlugin {
 }
 
 pub type WorldInitializerFn = Box<
-    dyn Fn(&str, u32, Palette) -> anyhow::Result<Arc<dyn WorldGen + Send + Sync>> + Send + Sync,
+    dyn Fn(&WorldSettings, Palette) -> anyhow::Result<Arc<dyn WorldGen + Send + Sync>>
+        + Send
+        + Sync,
 >;
 
 #[derive(Resource)]
@@ -87,7 +89,9 @@ pub struct WorldInitializer(pub WorldInitializerFn);
 #[derive(Resource)]
 pub struct WorldSettings {
     pub world_gen_name: String,
-    pub seed: u32,
+    pub seed: u64,
+    pub flat: FlatSettings,
+    pub heightmap: Option<HeightmapSettings>,
 }
 
 fn init_world(
@@ -96,15 +100,18 @@ fn init_world(
     world_initializer: Res<WorldInitializer>,
     settings: Res<WorldSettings>,
     mut world: ResMut<World>,
-) -> Result {
+    mut exit: MessageWriter<AppExit>,
+) {
     let WorldInitializer(world_initializer) = &*world_initializer;
     let world_gen_name = &settings.world_gen_name;
-    world.generator = world_initializer(
-        world_gen_name,
-        settings.seed,
-        registry.definitions.palette(),
-    )
-    .with_context(|| format!("No world generator found for '{world_gen_name}'"))?;
-    next_state.set(AppState::MainGame);
-    Ok(())
+    match world_initializer(&settings, registry.definitions.palette()) {
+        Ok(generator) => {
+            world.generator = generator;
+            next_state.set(AppState::MainGame);
+        }
+        Err(err) => {
+            tracing::error!("Couldn't initialize world generator '{world_gen_name}': {err:#}");
+            exit.write(AppExit::from_code(exit_codes::WORLD_INIT_FAILED));
+        }
+    }
 }